@@ -9,19 +9,23 @@
 //! model.text_spam_probability("hello i have an offer for you");
 //! model.text_spam_probability("Hey it's greg, finished the data analysis");
 //! ```  
-use std::{collections::HashMap, convert, fs, iter};
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert, fs, iter,
+};
 
 use rayon::prelude::*;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 use unicode_segmentation::UnicodeSegmentation;
 
-use crate::{Count, Frequency};
+use crate::{Count, Frequency, Timestamp};
 
 /// A BagOfWords, also referred to as a bow, is a frequency map of words.
 /// Read more about the BagOfWords model here: [BagOfWords Wikipedia](https://en.wikipedia.org/wiki/Bag-of-words_model).
 /// BagOfWords works with Unicode Words. Words are defined by as between
 /// [UAX#29 word boundaries](http://www.unicode.org/reports/tr29/#Word_Boundaries).
-/// BagOfWords is serializable using one of the [serde serialization crates](https://serde.rs/#data-formats)
+/// BagOfWords is serializable using one of the [serde serialization crates](https://serde.rs/#data-formats).
+/// Words are always serialized in sorted order, so equal bags serialize to identical bytes.
 /// ```no_run
 /// use rammer::BagOfWords;
 /// use serde_json;
@@ -29,9 +33,88 @@ use crate::{Count, Frequency};
 /// let big_bow = BagOfWords::from_folder("data/train/ham").expect("Folder not found");
 /// let com_bow = singly_trained_bow.combine(big_bow);
 /// ```
-#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
+///
+/// Words can also carry the [Timestamp](type.Timestamp.html) they were last seen at, so old words
+/// can be [decayed](struct.BagOfWords.html#method.decay) away. A BagOfWords also counts the
+/// documents it was made from and how many of them contained each word, for
+/// [Bernoulli scoring](enum.Scoring.html#variant.Bernoulli). Timestamps and document counts are
+/// not compared by `==`, which only looks at the words and their Counts.
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct BagOfWords {
+    #[serde(serialize_with = "serialize_sorted")]
     bow: HashMap<String, Count>,
+    /// When each stamped word was last seen. Bags saved before this existed have none.
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_sorted"
+    )]
+    last_seen: HashMap<String, Timestamp>,
+    /// When the bag was last decayed. Counts already account for the time before this.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    decayed_at: Option<Timestamp>,
+    /// Number of documents the bag was made from. Bags saved before this existed have 0.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    documents: Count,
+    /// Number of documents each word was found in.
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_sorted"
+    )]
+    document_frequency: HashMap<String, Count>,
+    /// What rounding added to or took off each decayed word's Count and document frequency, so
+    /// decaying often ends up with the same Counts as decaying once. Bags saved before this
+    /// existed have none.
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_sorted"
+    )]
+    remainders: HashMap<String, Remainder>,
+    /// What rounding added to or took off the number of documents.
+    #[serde(default, skip_serializing_if = "is_zero_remainder")]
+    documents_remainder: f64,
+}
+
+/// The fraction of a decayed word's Count and document frequency lost to rounding, between -0.5
+/// and 0.5 until the bag is combined with another.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone, Copy, Default)]
+pub(crate) struct Remainder {
+    /// Fraction of the Count.
+    pub(crate) count: f64,
+    /// Fraction of the number of documents the word was found in.
+    pub(crate) documents: f64,
+}
+
+fn is_zero(count: &Count) -> bool {
+    *count == 0
+}
+
+fn is_zero_remainder(remainder: &f64) -> bool {
+    *remainder == 0.0
+}
+
+/// Multiply a value, plus the remainder rounding left on it, by factor, giving the new value
+/// rounded to the nearest whole number and the new remainder.
+fn scale(value: Count, remainder: f64, factor: f64) -> (Count, f64) {
+    let exact = ((value as f64 + remainder) * factor).max(0.0);
+    let rounded = exact.round();
+    (rounded as Count, exact - rounded)
+}
+
+/// Serialize a map with its keys in sorted order instead of the HashMap's random order, so
+/// serialized models are reproducible and can be diffed.
+pub(crate) fn serialize_sorted<V, S>(
+    map: &HashMap<String, V>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    V: Serialize,
+    S: Serializer,
+{
+    let sorted: BTreeMap<&String, &V> = map.iter().collect();
+    sorted.serialize(serializer)
 }
 
 #[allow(missing_doc_code_examples)]
@@ -44,6 +127,12 @@ impl BagOfWords {
     pub fn new() -> Self {
         BagOfWords {
             bow: HashMap::new(),
+            last_seen: HashMap::new(),
+            decayed_at: None,
+            documents: 0,
+            document_frequency: HashMap::new(),
+            remainders: HashMap::new(),
+            documents_remainder: 0.0,
         }
     }
 
@@ -57,7 +146,7 @@ impl BagOfWords {
     pub fn from_file(file_path: &str) -> Option<Self> {
         fs::read_to_string(file_path)
             .ok()
-            .and_then(|s| Some(BagOfWords::from(&s[..])))
+            .map(|s| BagOfWords::from(&s[..]))
     }
 
     /// Create a BagOfWords from a folder containing either spam training text files, or ham
@@ -72,7 +161,7 @@ impl BagOfWords {
             .filter_map(|entry| {
                 entry
                     .ok()
-                    .and_then(|e| e.path().to_str().and_then(|p| BagOfWords::from_file(p)))
+                    .and_then(|e| e.path().to_str().and_then(BagOfWords::from_file))
             }).collect();
 
         Some(bow)
@@ -81,7 +170,7 @@ impl BagOfWords {
     /// Combines two BagOfWords into a new BagOfWords.
     /// Freqencies of words found in both bags are additive.
     /// This operation is commutative and associative. These properties can be used to dynamically
-    /// grow your training BagOfWords.
+    /// grow your training BagOfWords. Counts stop at Count::MAX rather than wrapping around.
     /// ```
     /// # use rammer::BagOfWords;
     /// let ham_bow_1 = BagOfWords::from("Hello there world"); // Creates: {HELLO: 1, THERE: 1, WORLD: 1}
@@ -90,12 +179,229 @@ impl BagOfWords {
     /// ```
     pub fn combine(mut self, other: Self) -> Self {
         for (k, v) in other.bow {
-            self.bow.entry(k).and_modify(|sv| *sv += v).or_insert(v);
+            self.bow
+                .entry(k)
+                .and_modify(|sv| *sv = sv.saturating_add(v))
+                .or_insert(v);
         }
+        for (k, t) in other.last_seen {
+            self.last_seen
+                .entry(k)
+                .and_modify(|st| *st = (*st).max(t))
+                .or_insert(t);
+        }
+        self.decayed_at = self.decayed_at.max(other.decayed_at);
+        self.documents = self.documents.saturating_add(other.documents);
+        for (k, f) in other.document_frequency {
+            self.document_frequency
+                .entry(k)
+                .and_modify(|sf| *sf = sf.saturating_add(f))
+                .or_insert(f);
+        }
+        for (k, r) in other.remainders {
+            let sr = self.remainders.entry(k).or_default();
+            sr.count += r.count;
+            sr.documents += r.documents;
+        }
+        self.documents_remainder += other.documents_remainder;
         self
     }
 
-    /// Get the sum of all the Counts in a BagOfWords.
+    /// Removes the Counts of another BagOfWords from this one, the inverse of
+    /// [combine](struct.BagOfWords.html#method.combine).
+    /// Counts never go below zero, and words whose Count reaches zero are removed.
+    /// ```
+    /// # use rammer::BagOfWords;
+    /// let bow = BagOfWords::from("hello hello world");
+    /// let sub_bow = bow.subtract(BagOfWords::from("hello world")); // Creates: {HELLO: 1}
+    /// assert_eq!(sub_bow, BagOfWords::from("hello"));
+    /// ```
+    pub fn subtract(mut self, other: Self) -> Self {
+        for (k, v) in other.bow {
+            if let Some(sv) = self.bow.get_mut(&k) {
+                *sv = sv.saturating_sub(v);
+                if *sv == 0 {
+                    self.bow.remove(&k);
+                    self.last_seen.remove(&k);
+                    self.document_frequency.remove(&k);
+                    self.remainders.remove(&k);
+                }
+            }
+        }
+        for (k, r) in other.remainders {
+            if let Some(sr) = self.remainders.get_mut(&k) {
+                sr.count -= r.count;
+                sr.documents -= r.documents;
+            }
+        }
+        self.documents = self.documents.saturating_sub(other.documents);
+        self.documents_remainder -= other.documents_remainder;
+        for (k, f) in other.document_frequency {
+            if let Some(sf) = self.document_frequency.get_mut(&k) {
+                *sf = sf.saturating_sub(f);
+                if *sf == 0 {
+                    self.document_frequency.remove(&k);
+                }
+            }
+        }
+        self
+    }
+
+    /// Removes words seen fewer than min_count times. Words seen only once or twice are mostly
+    /// typos, ids and other noise, and make up most of a large BagOfWords.
+    /// ```
+    /// # use rammer::BagOfWords;
+    /// let bow = BagOfWords::from("hello hello world").prune_min_count(2);
+    /// assert_eq!(bow, BagOfWords::from("hello hello"));
+    /// ```
+    pub fn prune_min_count(self, min_count: Count) -> Self {
+        self.retain(|_, count| count >= min_count)
+    }
+
+    /// Record that every word in the BagOfWords was seen at time, keeping any later time already
+    /// recorded. Stamp bags before combining them into a model so its words can be decayed.
+    /// ```
+    /// # use rammer::BagOfWords;
+    /// let bow = BagOfWords::from("hello world").seen_at(1_600_000_000);
+    /// assert_eq!(bow.last_seen("hello"), Some(1_600_000_000));
+    /// ```
+    pub fn seen_at(mut self, time: Timestamp) -> Self {
+        for word in self.bow.keys() {
+            let seen = self.last_seen.entry(word.clone()).or_insert(time);
+            *seen = (*seen).max(time);
+        }
+        self
+    }
+
+    /// When a word was last seen, or None if it is not in the BagOfWords or was never stamped.
+    /// ```
+    /// # use rammer::BagOfWords;
+    /// let bow = BagOfWords::from("hello").seen_at(10).combine(BagOfWords::from("world"));
+    /// assert_eq!(bow.last_seen("HELLO"), Some(10));
+    /// assert_eq!(bow.last_seen("world"), None);
+    /// ```
+    pub fn last_seen(&self, word: &str) -> Option<Timestamp> {
+        self.last_seen.get(&word.to_uppercase()).copied()
+    }
+
+    /// Number of documents the BagOfWords was made from. Each text it is built
+    /// [from](struct.BagOfWords.html#impl-From%3C%26str%3E) is one document.
+    /// ```
+    /// # use rammer::BagOfWords;
+    /// let bow = BagOfWords::from("hello hello").combine(BagOfWords::from("hello world"));
+    /// assert_eq!(bow.documents(), 2);
+    /// ```
+    pub fn documents(&self) -> Count {
+        self.documents
+    }
+
+    /// Number of documents a word was found in, however many times it appeared in each, or None
+    /// if it is not in the BagOfWords or its documents weren't counted.
+    /// ```
+    /// # use rammer::BagOfWords;
+    /// let bow = BagOfWords::from("free free free").combine(BagOfWords::from("free offer"));
+    /// assert_eq!(bow.document_frequency("free"), Some(2));
+    /// assert_eq!(bow.document_frequency("missing"), None);
+    /// ```
+    pub fn document_frequency(&self, word: &str) -> Option<Count> {
+        self.document_frequency.get(&word.to_uppercase()).copied()
+    }
+
+    /// Halve the Count of each word every half_life seconds it has not been seen, as of now.
+    /// Counts are rounded to the nearest whole number, and words whose Count reaches zero are
+    /// removed, so words which stop appearing eventually expire. What rounding adds or takes off
+    /// is kept and carried into the next decay, so a Count of 5 decayed a little every day still
+    /// shrinks.
+    ///
+    /// A word ages from when it was last seen, or from the previous decay if that was later, so
+    /// decaying daily and decaying once at the end of a month give the same Counts, up to
+    /// floating point rounding. Words which were never stamped only start to age from the first
+    /// decay. The number of documents each word was found in decays with its Count, and the
+    /// number of documents the bag was made from shrinks by the same fraction as the document
+    /// frequencies do in total, so [Bernoulli scoring](enum.Scoring.html#variant.Bernoulli) still
+    /// sees the same share of documents containing each word that didn't age any faster than the
+    /// rest. A half_life of 0 leaves the BagOfWords unchanged.
+    /// ```
+    /// # use rammer::BagOfWords;
+    /// let day = 24 * 60 * 60;
+    /// let bow = BagOfWords::from("offer offer offer offer hello").seen_at(0);
+    /// let decayed = bow.decay(14 * day, 7 * day); // two half lives
+    /// assert_eq!(decayed, BagOfWords::from("offer"));
+    /// ```
+    pub fn decay(mut self, now: Timestamp, half_life: Timestamp) -> Self {
+        if half_life == 0 {
+            return self;
+        }
+        let (last_seen, decayed_at) = (&self.last_seen, self.decayed_at);
+        let factor = |word: &str| match (last_seen.get(word).copied(), decayed_at) {
+            (None, None) => 1.0,
+            (seen, decayed) => {
+                let age = now.saturating_sub(seen.max(decayed).unwrap_or(0)) as f64;
+                0.5f64.powf(age / half_life as f64)
+            }
+        };
+        let old_remainders = std::mem::take(&mut self.remainders);
+        let mut remainders = HashMap::new();
+        // Exact total of the document frequencies before and after decaying.
+        let (mut frequencies_before, mut frequencies_after) = (0.0, 0.0);
+        for (word, count) in self.bow.iter_mut() {
+            let factor = factor(word);
+            let old = old_remainders.get(word).copied().unwrap_or_default();
+            let mut remainder = Remainder::default();
+            let (decayed, left) = scale(*count, old.count, factor);
+            *count = decayed;
+            remainder.count = left;
+            if let Some(frequency) = self.document_frequency.get_mut(word) {
+                let exact = *frequency as f64 + old.documents;
+                frequencies_before += exact;
+                frequencies_after += exact * factor;
+                let (decayed, left) = scale(*frequency, old.documents, factor);
+                *frequency = decayed;
+                if decayed > 0 {
+                    remainder.documents = left;
+                }
+            }
+            if *count > 0 && remainder != Remainder::default() {
+                remainders.insert(word.clone(), remainder);
+            }
+        }
+        self.remainders = remainders;
+        if frequencies_before > 0.0 {
+            let factor = frequencies_after / frequencies_before;
+            let (documents, left) = scale(self.documents, self.documents_remainder, factor);
+            self.documents = documents;
+            self.documents_remainder = left;
+        }
+        self.bow.retain(|_, count| *count > 0);
+        let bow = &self.bow;
+        self.last_seen.retain(|word, _| bow.contains_key(word));
+        self.document_frequency
+            .retain(|word, frequency| *frequency > 0 && bow.contains_key(word));
+        self.decayed_at = self.decayed_at.max(Some(now));
+        self
+    }
+
+    /// Remove every stamped word which has not been seen in the max_age seconds before now.
+    /// Words which were never stamped are kept.
+    /// ```
+    /// # use rammer::BagOfWords;
+    /// let bow = BagOfWords::from("old").seen_at(100).combine(BagOfWords::from("new").seen_at(900));
+    /// assert_eq!(bow.expire(1000, 500), BagOfWords::from("new"));
+    /// ```
+    pub fn expire(mut self, now: Timestamp, max_age: Timestamp) -> Self {
+        let cutoff = now.saturating_sub(max_age);
+        let last_seen = &self.last_seen;
+        self.bow
+            .retain(|word, _| last_seen.get(word).is_none_or(|&seen| seen >= cutoff));
+        let bow = &self.bow;
+        self.last_seen.retain(|word, _| bow.contains_key(word));
+        self.document_frequency
+            .retain(|word, _| bow.contains_key(word));
+        self.remainders.retain(|word, _| bow.contains_key(word));
+        self
+    }
+
+    /// Get the sum of all the Counts in a BagOfWords, stopping at Count::MAX.
     /// Used internally for frequency calculations.
     /// ```
     /// # use rammer::BagOfWords;
@@ -103,7 +409,131 @@ impl BagOfWords {
     /// ham_bow.total_word_count(); // returns a sum of Counts.
     /// ```
     pub fn total_word_count(&self) -> Count {
-        self.bow.values().sum()
+        self.bow
+            .values()
+            .fold(0, |total: Count, &count| total.saturating_add(count))
+    }
+
+    /// Number of distinct words in the BagOfWords.
+    /// ```
+    /// # use rammer::BagOfWords;
+    /// assert_eq!(BagOfWords::from("hello world WOrLD").len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.bow.len()
+    }
+
+    /// Returns true if the BagOfWords has no words.
+    /// ```
+    /// # use rammer::BagOfWords;
+    /// assert!(BagOfWords::new().is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.bow.is_empty()
+    }
+
+    /// Count of a single already uppercased token, as produced by
+    /// [From<&str>](struct.BagOfWords.html#impl-From%3C%26str%3E). Used internally for scoring,
+    /// where the text has already been split into words.
+    pub(crate) fn token_count(&self, token: &str) -> Option<Count> {
+        self.bow.get(token).copied()
+    }
+
+    /// Build a BagOfWords from already uppercased tokens and their counts, adding the counts of
+    /// repeated tokens. Used when reading models from disk.
+    pub(crate) fn from_counts<I: IntoIterator<Item = (String, Count)>>(counts: I) -> Self {
+        let mut bow = BagOfWords::new();
+        for (token, count) in counts {
+            let sum = bow.bow.entry(token).or_insert(0);
+            *sum = sum.saturating_add(count);
+        }
+        bow
+    }
+
+    /// Keep only the tokens for which keep returns true.
+    pub(crate) fn retain<F: FnMut(&str, Count) -> bool>(mut self, mut keep: F) -> Self {
+        self.bow.retain(|token, count| keep(token, *count));
+        let bow = &self.bow;
+        self.last_seen.retain(|token, _| bow.contains_key(token));
+        self.document_frequency
+            .retain(|token, _| bow.contains_key(token));
+        self.remainders.retain(|token, _| bow.contains_key(token));
+        self
+    }
+
+    /// Set when already uppercased tokens were last seen, and when the bag was last decayed. Used
+    /// when reading models from disk.
+    pub(crate) fn with_aging<I: IntoIterator<Item = (String, Timestamp)>>(
+        mut self,
+        last_seen: I,
+        decayed_at: Option<Timestamp>,
+    ) -> Self {
+        self.last_seen.extend(last_seen);
+        self.decayed_at = decayed_at;
+        self
+    }
+
+    /// Set the number of documents the bag was made from and how many of them each already
+    /// uppercased token was found in. Used when reading models from disk.
+    pub(crate) fn with_documents<I: IntoIterator<Item = (String, Count)>>(
+        mut self,
+        documents: Count,
+        document_frequency: I,
+    ) -> Self {
+        self.documents = documents;
+        self.document_frequency.extend(document_frequency);
+        self
+    }
+
+    /// Set what rounding left on decayed already uppercased tokens and on the number of
+    /// documents. Used when reading models from disk.
+    pub(crate) fn with_remainders<I: IntoIterator<Item = (String, Remainder)>>(
+        mut self,
+        remainders: I,
+        documents_remainder: f64,
+    ) -> Self {
+        self.remainders.extend(remainders);
+        self.documents_remainder = documents_remainder;
+        self
+    }
+
+    /// What rounding left on every decayed token, in no particular order.
+    pub(crate) fn remainders(&self) -> impl Iterator<Item = (&str, Remainder)> + '_ {
+        self.remainders
+            .iter()
+            .map(|(token, &remainder)| (&token[..], remainder))
+    }
+
+    /// What rounding left on the number of documents.
+    pub(crate) fn documents_remainder(&self) -> f64 {
+        self.documents_remainder
+    }
+
+    /// Number of documents an already uppercased token was found in.
+    pub(crate) fn token_document_frequency(&self, token: &str) -> Option<Count> {
+        self.document_frequency.get(token).copied()
+    }
+
+    /// Every token with the number of documents it was found in, in no particular order.
+    pub(crate) fn document_frequencies(&self) -> impl Iterator<Item = (&str, Count)> + '_ {
+        self.document_frequency
+            .iter()
+            .map(|(token, &frequency)| (&token[..], frequency))
+    }
+
+    /// When an already uppercased token was last seen.
+    pub(crate) fn token_last_seen(&self, token: &str) -> Option<Timestamp> {
+        self.last_seen.get(token).copied()
+    }
+
+    /// When the bag was last decayed.
+    pub(crate) fn decayed_at(&self) -> Option<Timestamp> {
+        self.decayed_at
+    }
+
+    /// Every token in the BagOfWords with its count, in no particular order.
+    pub(crate) fn tokens(&self) -> impl Iterator<Item = (&str, Count)> + '_ {
+        self.bow.iter().map(|(token, &count)| (&token[..], count))
     }
 
     /// Calculates the Frequency of a word in the BagOfWords by taking count_of_a_word / total_word_count.
@@ -119,13 +549,30 @@ impl BagOfWords {
             .split_word_bounds()
             .filter(|&s| !s.trim().is_empty())
             .collect();
-        if word_vec.len() == 0 || word_vec.len() > 1 {
+        if word_vec.len() != 1 {
             return None;
         }
 
         self.bow
             .get(&word_vec[0].to_uppercase()[..])
-            .and_then(|&v| Some(v as Frequency / self.total_word_count() as Frequency))
+            .map(|&v| v as Frequency / self.total_word_count() as Frequency)
+    }
+}
+
+/// Two BagOfWords are equal if they have the same words with the same Counts, whenever those
+/// words were seen and however many documents they came from.
+impl PartialEq for BagOfWords {
+    #[allow(missing_doc_code_examples)]
+    fn eq(&self, other: &Self) -> bool {
+        self.bow == other.bow
+    }
+}
+
+impl Eq for BagOfWords {}
+
+impl Default for BagOfWords {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -141,8 +588,11 @@ impl convert::From<&str> for BagOfWords {
     fn from(s: &str) -> BagOfWords {
         let mut bow = BagOfWords::new();
         for w in s.split_word_bounds().filter(|&s| !s.trim().is_empty()) {
-            *bow.bow.entry(w.to_uppercase()).or_insert(0) += 1;
+            let count = bow.bow.entry(w.to_uppercase()).or_insert(0);
+            *count = count.saturating_add(1);
         }
+        bow.documents = 1;
+        bow.document_frequency = bow.bow.keys().map(|word| (word.clone(), 1)).collect();
         bow
     }
 }
@@ -190,7 +640,7 @@ impl FromParallelIterator<BagOfWords> for BagOfWords {
         //let par_iter = par_iter.into_par_iter();
         par_iter
             .into_par_iter()
-            .reduce(|| BagOfWords::new(), |a, b| a.combine(b))
+            .reduce(BagOfWords::new, |a, b| a.combine(b))
     }
 }
 
@@ -208,6 +658,7 @@ mod tests {
         let fbow: BagOfWords = BagOfWords::new();
         let bow = BagOfWords {
             bow: HashMap::new(),
+            ..BagOfWords::new()
         };
         assert_eq!(fbow, bow);
     }
@@ -225,9 +676,10 @@ mod tests {
         let bow = BagOfWords {
             bow: {
                 let mut hm = HashMap::new();
-                hm.insert("HELLO".to_string(), 1u32);
+                hm.insert("HELLO".to_string(), 1);
                 hm
             },
+            ..BagOfWords::new()
         };
         assert_eq!(fbow, bow);
     }
@@ -237,9 +689,10 @@ mod tests {
         let bow = BagOfWords {
             bow: {
                 let mut hm = HashMap::new();
-                hm.insert("HELLO".to_string(), 2u32);
+                hm.insert("HELLO".to_string(), 2);
                 hm
             },
+            ..BagOfWords::new()
         };
         assert_eq!(fbow, bow);
     }
@@ -250,9 +703,10 @@ mod tests {
         let bow = BagOfWords {
             bow: {
                 let mut hm = HashMap::new();
-                hm.insert("😊".to_string(), 1u32);
+                hm.insert("😊".to_string(), 1);
                 hm
             },
+            ..BagOfWords::new()
         };
         assert_eq!(fbow, bow);
     }
@@ -263,9 +717,10 @@ mod tests {
         let bow = BagOfWords {
             bow: {
                 let mut hm = HashMap::new();
-                hm.insert("😊".to_string(), 2u32);
+                hm.insert("😊".to_string(), 2);
                 hm
             },
+            ..BagOfWords::new()
         };
         assert_eq!(fbow, bow);
     }
@@ -276,10 +731,11 @@ mod tests {
         let bow = BagOfWords {
             bow: {
                 let mut hm = HashMap::new();
-                hm.insert("😊".to_string(), 2u32);
-                hm.insert("HELLO".to_string(), 1u32);
+                hm.insert("😊".to_string(), 2);
+                hm.insert("HELLO".to_string(), 1);
                 hm
             },
+            ..BagOfWords::new()
         };
         assert_eq!(fbow, bow);
     }
@@ -290,9 +746,10 @@ mod tests {
         let bow = BagOfWords {
             bow: {
                 let mut hm = HashMap::new();
-                hm.insert("😊".to_string(), 2u32);
+                hm.insert("😊".to_string(), 2);
                 hm
             },
+            ..BagOfWords::new()
         };
         assert_eq!(fbow, bow);
     }
@@ -304,11 +761,12 @@ mod tests {
         let bow = BagOfWords {
             bow: {
                 let mut hm = HashMap::new();
-                hm.insert("HI".to_string(), 1u32);
-                hm.insert("HI".to_string(), 1u32);
-                hm.insert("HI".to_string(), 1u32);
+                hm.insert("HI".to_string(), 1);
+                hm.insert("HI".to_string(), 1);
+                hm.insert("HI".to_string(), 1);
                 hm
             },
+            ..BagOfWords::new()
         };
         assert_eq!(fbow, bow);
     }
@@ -366,6 +824,36 @@ mod tests {
         assert_eq!(fbow, bow)
     }
 
+    #[test]
+    fn combine_saturates() {
+        let big = BagOfWords::from_counts(vec![("THE".to_string(), Count::MAX - 1)]);
+        let bow = big.clone().combine(BagOfWords::from("the the the"));
+        assert_eq!(bow.token_count("THE"), Some(Count::MAX));
+        let bow = bow.combine(big).combine(BagOfWords::from("a"));
+        assert_eq!(bow.total_word_count(), Count::MAX);
+    }
+
+    /*****************************************/
+    /* SUBTRACT TESTS                        */
+    /*****************************************/
+
+    #[test]
+    fn subtract_inverse_of_combine() {
+        let bow = BagOfWords::from("hello there world");
+        let fbow = bow
+            .clone()
+            .combine(BagOfWords::from("hello 😊"))
+            .subtract(BagOfWords::from("hello 😊"));
+        assert_eq!(fbow, bow);
+    }
+
+    #[test]
+    fn subtract_saturates_at_zero() {
+        let fbow = BagOfWords::from("hello there").subtract(BagOfWords::from("hello hello world"));
+        let bow = BagOfWords::from("there");
+        assert_eq!(fbow, bow);
+    }
+
     /*****************************************/
     /* FROM ITER TESTS                         */
     /*****************************************/
@@ -453,4 +941,88 @@ mod tests {
         let bow = BagOfWords::from("hello there you cutie pie");
         assert_eq!(bow.word_frequency("hello").unwrap(), 0.2f64);
     }
+
+    /*****************************************/
+    /* AGING TESTS                           */
+    /*****************************************/
+    #[test]
+    fn combine_keeps_latest_stamp() {
+        let bow = BagOfWords::from("hello world")
+            .seen_at(20)
+            .combine(BagOfWords::from("hello").seen_at(10))
+            .combine(BagOfWords::from("new"));
+        assert_eq!(bow.last_seen("hello"), Some(20));
+        assert_eq!(bow.last_seen("new"), None);
+        assert_eq!(bow, BagOfWords::from("hello hello world new"));
+    }
+
+    #[test]
+    fn decay_in_steps_matches_decay_at_once() {
+        let day = 24 * 60 * 60;
+        let bow = BagOfWords::from(&"spam ".repeat(1000)[..])
+            .seen_at(0)
+            .combine(BagOfWords::from(&"ham ".repeat(1000)[..]).seen_at(14 * day));
+        let once = bow.clone().decay(28 * day, 7 * day);
+        let weekly = (1..=4).fold(bow, |bow, week| bow.decay(week * 7 * day, 7 * day));
+        assert_eq!(once.token_count("SPAM"), Some(63));
+        assert_eq!(once.token_count("HAM"), Some(250));
+        assert_eq!(weekly, once);
+        assert_eq!(weekly.decayed_at(), Some(28 * day));
+    }
+
+    #[test]
+    fn decay_daily_matches_decay_monthly() {
+        let day = 24 * 60 * 60;
+        let text = format!("{}{}{}", "offer ".repeat(100), "hello ".repeat(37), "lunch ".repeat(5));
+        let bow = BagOfWords::from(&text[..])
+            .combine(BagOfWords::from("offer hello"))
+            .seen_at(0);
+        let once = bow.clone().decay(30 * day, 7 * day);
+        let daily = (1..=30).fold(bow, |bow, d| bow.decay(d * day, 7 * day));
+        // 100 * 0.5^(30 / 7) is 5.16, 38 is 1.96 and 5 is 0.26, which expires.
+        assert_eq!(once.token_count("OFFER"), Some(5));
+        assert_eq!(once.token_count("HELLO"), Some(2));
+        assert_eq!(once.token_count("LUNCH"), None);
+        assert_eq!(daily, once);
+        assert_eq!(daily.documents(), once.documents());
+        assert_eq!(daily.document_frequency("offer"), once.document_frequency("offer"));
+        // Rounding each day on its own would have kept a Count of 5 forever.
+        let small = BagOfWords::from("lunch lunch lunch lunch lunch").seen_at(0);
+        let small = (1..=30).fold(small, |bow, d| bow.decay(d * day, 7 * day));
+        assert!(small.is_empty());
+    }
+
+    #[test]
+    fn decay_shrinks_documents_with_frequencies() {
+        let bow: BagOfWords = (0..40)
+            .map(|i| BagOfWords::from(if i % 2 == 0 { "hello lunch" } else { "hello" }))
+            .collect::<BagOfWords>()
+            .seen_at(0)
+            .decay(10, 10);
+        assert_eq!(bow.documents(), 20);
+        assert_eq!(bow.document_frequency("hello"), Some(20));
+        assert_eq!(bow.document_frequency("lunch"), Some(10));
+        assert_eq!(BagOfWords::from("hi").decay(10, 10).documents(), 1);
+    }
+
+    #[test]
+    fn unstamped_words_age_from_first_decay() {
+        let bow = BagOfWords::from("old old old old").decay(100, 10);
+        assert_eq!(bow.token_count("OLD"), Some(4));
+        let bow = bow.decay(120, 10);
+        assert_eq!(bow.token_count("OLD"), Some(1));
+        assert!(bow.clone().decay(200, 10).is_empty());
+        assert_eq!(bow.clone().decay(130, 0), bow);
+    }
+
+    #[test]
+    fn stamps_are_optional_in_json() {
+        let legacy: BagOfWords = serde_json::from_str(r#"{"bow":{"HI":2}}"#).unwrap();
+        assert_eq!(serde_json::to_string(&legacy).unwrap(), r#"{"bow":{"HI":2}}"#);
+        let stamped = legacy.seen_at(5).decay(5, 10);
+        let json = serde_json::to_string(&stamped).unwrap();
+        assert_eq!(json, r#"{"bow":{"HI":2},"last_seen":{"HI":5},"decayed_at":5}"#);
+        let read: BagOfWords = serde_json::from_str(&json).unwrap();
+        assert_eq!((read.last_seen("hi"), read.decayed_at()), (Some(5), Some(5)));
+    }
 }
@@ -0,0 +1,237 @@
+//! Bernoulli naive Bayes scoring, where a text is the set of tokens it contains.
+//! Multinomial scoring counts a token every time it appears, so a message repeating "FREE" 40
+//! times gets 40 times the push towards spam. The Bernoulli event model instead asks, for every
+//! token in the vocabulary, whether the text contains it at all, using the fraction of training
+//! documents of each class that contained it.
+//!
+//! A token is present in a ham document with probability (1 + documents containing it) /
+//! (2 + ham documents), and likewise for spam. Every vocabulary token missing from the text also
+//! counts, as evidence for the class it is rarely missing from, so a message without the words
+//! that nearly every ham message has leans towards spam. Tokens never seen in training are
+//! ignored. Document frequencies are kept by [BagOfWords](struct.BagOfWords.html) as it is
+//! built, so models saved before they existed need retraining before they can use this scoring.
+//! The log odds of a text lacking every token take a pass over both vocabularies, so score many
+//! texts with a [scorer](struct.HSModel.html#method.scorer), which works them out once.
+//! ```
+//! use rammer::{BagOfWords, HSModel, Scoring};
+//! let ham: BagOfWords = vec!["hello lunch", "hello free lunch", "hello meeting"]
+//!     .into_iter()
+//!     .map(BagOfWords::from)
+//!     .collect();
+//! let spam: BagOfWords = vec!["free offer", "free click", "offer now"]
+//!     .into_iter()
+//!     .map(BagOfWords::from)
+//!     .collect();
+//! let model = HSModel::from_bows(ham, spam).with_scoring(Scoring::Bernoulli);
+//! let once = model.text_spam_probability("free");
+//! assert_eq!(model.text_spam_probability(&"free ".repeat(40)), once);
+//! ```
+use std::collections::HashSet;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{BagOfWords, Count, Frequency, HSModel, Probability};
+
+/// The document counts of a model and the log odds of a text containing none of its tokens,
+/// which depends on every token in the model, so is worked out once and reused for every text.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub(crate) struct BernoulliWeights {
+    /// Number of ham and spam training documents.
+    documents: (Count, Count),
+    /// Log odds of a text which contains no token from the vocabulary.
+    absent: f64,
+}
+
+impl BernoulliWeights {
+    /// Work out the weights of model from the document frequencies of both its BagOfWords.
+    pub(crate) fn for_model(model: &HSModel) -> Self {
+        let mut weights = BernoulliWeights {
+            documents: (model.ham_bow.documents(), model.spam_bow.documents()),
+            absent: 0.0,
+        };
+        let ham_tokens = model.ham_bow.document_frequencies().map(|(token, ham)| {
+            let spam = model.spam_bow.token_document_frequency(token).unwrap_or(0);
+            (ham, spam)
+        });
+        let spam_only = model
+            .spam_bow
+            .document_frequencies()
+            .filter(|(token, _)| model.ham_bow.token_document_frequency(token).is_none())
+            .map(|(_, spam)| (0, spam));
+        weights.absent = ham_tokens
+            .chain(spam_only)
+            .map(|(ham, spam)| {
+                let (p_ham, p_spam) = weights.presence(ham, spam);
+                Frequency::ln(1.0 - p_spam) - Frequency::ln(1.0 - p_ham)
+            })
+            .sum();
+        weights
+    }
+
+    /// Smoothed probability that a ham and a spam document contain a token found in ham and spam
+    /// documents.
+    fn presence(&self, ham: Count, spam: Count) -> (Probability, Probability) {
+        let smoothed = |found: Count, documents: Count| {
+            (found as Frequency + 1.0) / (documents as Frequency + 2.0)
+        };
+        (
+            smoothed(ham, self.documents.0),
+            smoothed(spam, self.documents.1),
+        )
+    }
+
+    /// Log odds of a text containing no vocabulary token at all.
+    pub(crate) fn absent(&self) -> f64 {
+        self.absent
+    }
+
+    /// How much the log odds of a text change, positive towards spam, when it contains a token
+    /// found in ham and spam documents rather than lacking it, or None if the token was never
+    /// seen.
+    pub(crate) fn token_log_odds(&self, ham: Count, spam: Count) -> Option<f64> {
+        if ham == 0 && spam == 0 {
+            return None;
+        }
+        let (p_ham, p_spam) = self.presence(ham, spam);
+        let present = Frequency::ln(p_spam) - Frequency::ln(p_ham);
+        let absent = Frequency::ln(1.0 - p_spam) - Frequency::ln(1.0 - p_ham);
+        Some(present - absent)
+    }
+
+    /// Log odds that a slice of text is spam, with document frequencies looked up in model.
+    pub(crate) fn text_log_odds(&self, model: &HSModel, text: &str) -> f64 {
+        let upper = text.to_uppercase();
+        let tokens: HashSet<&str> = upper
+            .split_word_bounds()
+            .filter(|&s| !s.trim().is_empty())
+            .collect();
+        self.log_odds(model, tokens.into_iter())
+    }
+
+    /// Log odds that text already tokenized into a BagOfWords is spam.
+    pub(crate) fn bow_log_odds(&self, model: &HSModel, bow: &BagOfWords) -> f64 {
+        self.log_odds(model, bow.tokens().map(|(token, _)| token))
+    }
+
+    /// Log odds that a text containing each of the distinct tokens is spam.
+    fn log_odds<'t, I: Iterator<Item = &'t str>>(&self, model: &HSModel, tokens: I) -> f64 {
+        self.absent
+            + tokens
+                .filter_map(|token| {
+                    let ham = model.ham_bow.token_document_frequency(token).unwrap_or(0);
+                    let spam = model.spam_bow.token_document_frequency(token).unwrap_or(0);
+                    self.token_log_odds(ham, spam)
+                })
+                .sum::<f64>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Scoring;
+
+    fn documents(texts: &[&str]) -> BagOfWords {
+        texts.iter().map(|&text| BagOfWords::from(text)).collect()
+    }
+
+    #[test]
+    fn decay_scores_like_fewer_documents() {
+        let repeat = |texts: &[&str], n: usize| {
+            let texts: Vec<&str> = texts.iter().cycle().take(n).cloned().collect();
+            documents(&texts).seen_at(0)
+        };
+        let (ham, spam) = (
+            ["hello lunch", "hello meeting"],
+            ["free offer", "hello offer"],
+        );
+        // One half life halves every document frequency and the number of documents alike, so
+        // the model scores like one trained on half as many documents.
+        let decayed = HSModel::from_bows(
+            repeat(&ham, 200).decay(10, 10),
+            repeat(&spam, 200).decay(10, 10),
+        )
+        .with_scoring(Scoring::Bernoulli);
+        let half = HSModel::from_bows(repeat(&ham, 100), repeat(&spam, 100))
+            .with_scoring(Scoring::Bernoulli);
+        assert_eq!(decayed.ham_bow.documents(), 100);
+        for text in &[
+            "hello lunch",
+            "free offer",
+            "hello",
+            "meeting offer",
+            "nothing",
+        ] {
+            let (decayed, half) = (decayed.text_log_odds(text), half.text_log_odds(text));
+            assert!((decayed - half).abs() < 1e-9, "{}", text);
+        }
+    }
+
+    #[test]
+    fn matches_the_definition() {
+        let model = HSModel::from_bows(
+            documents(&["hello lunch", "hello hello", "meeting"]),
+            documents(&["free offer", "free"]),
+        )
+        .with_scoring(Scoring::Bernoulli);
+        // Presence probabilities of each vocabulary token, smoothed over 3 ham and 2 spam
+        // documents.
+        let vocabulary = [
+            ("HELLO", 3.0 / 5.0, 1.0 / 4.0),
+            ("LUNCH", 2.0 / 5.0, 1.0 / 4.0),
+            ("MEETING", 2.0 / 5.0, 1.0 / 4.0),
+            ("FREE", 1.0 / 5.0, 3.0 / 4.0),
+            ("OFFER", 1.0 / 5.0, 2.0 / 4.0),
+        ];
+        let text = "free hello unknown free";
+        let n: f64 = vocabulary
+            .iter()
+            .map(|&(token, p_ham, p_spam): &(&str, f64, f64)| {
+                if text.to_uppercase().contains(token) {
+                    p_spam.ln() - p_ham.ln()
+                } else {
+                    (1.0 - p_spam).ln() - (1.0 - p_ham).ln()
+                }
+            })
+            .sum();
+        let expected = 1.0 / (1.0 + (-n).exp());
+        assert!((model.text_spam_probability(text) - expected).abs() < 1e-12);
+        let from_bow = model.bow_spam_probability(&BagOfWords::from(text));
+        assert!((from_bow - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn scorer_matches_the_model() {
+        let model = HSModel::from_bows(
+            documents(&["hello lunch", "hello meeting", "hello report"]),
+            documents(&["free offer", "offer now", "click here"]),
+        )
+        .with_scoring(Scoring::Bernoulli);
+        let scorer = model.scorer();
+        for text in &["hello offer", "free free free", "unknown", ""] {
+            assert_eq!(scorer.text_log_odds(text), model.text_log_odds(text));
+            assert_eq!(scorer.explain(text), model.explain(text));
+            let bow = BagOfWords::from(*text);
+            assert_eq!(scorer.bow_log_odds(&bow), model.bow_log_odds(&bow));
+        }
+    }
+
+    #[test]
+    fn absent_tokens_count() {
+        let model = HSModel::from_bows(
+            documents(&["hello lunch", "hello meeting", "hello report"]),
+            documents(&["free offer", "offer now", "click here"]),
+        )
+        .with_scoring(Scoring::Bernoulli);
+        // Text with no known tokens lacks HELLO, which nearly every ham document has.
+        assert!(model.text_spam_probability("unknown") > 0.5);
+        assert!(model.text_spam_probability("hello") < 0.5);
+        // Without document counts, as in models saved before they were kept, nothing is known.
+        let legacy = HSModel::from_bows(
+            BagOfWords::from_counts(vec![("HELLO".to_string(), 3)]),
+            BagOfWords::from_counts(vec![("OFFER".to_string(), 2)]),
+        )
+        .with_scoring(Scoring::Bernoulli);
+        assert_eq!(legacy.text_spam_probability("hello offer"), 0.5);
+    }
+}
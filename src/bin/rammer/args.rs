@@ -0,0 +1,292 @@
+use std::{fs, path::Path, str::FromStr};
+
+use rammer::{Corpus, HSModel, Label, Probability, Scoring, SignificantTokens, Thresholds};
+
+use crate::error::{CliError, CliResult};
+
+/// Options taking a value which every subcommand accepts.
+const GLOBAL_VALUES: [&str; 7] = [
+    "model",
+    "threads",
+    "ham-threshold",
+    "spam-threshold",
+    "scoring",
+    "significant-tokens",
+    "min-deviation",
+];
+
+/// A parsed subcommand line: `--name value`, `--name=value`, `--flag` and positional arguments.
+pub struct Args {
+    positional: Vec<String>,
+    values: Vec<(String, String)>,
+    flags: Vec<String>,
+}
+
+impl Args {
+    /// Parse raw arguments, accepting only the options listed in values and flags plus the
+    /// global options. Sets up the thread pool when --threads is given.
+    pub fn parse(raw: &[String], values: &[&str], flags: &[&str]) -> CliResult<Args> {
+        let mut args = Args {
+            positional: Vec::new(),
+            values: Vec::new(),
+            flags: Vec::new(),
+        };
+        let takes_value = |name: &str| values.contains(&name) || GLOBAL_VALUES.contains(&name);
+
+        let mut raw = raw.iter();
+        while let Some(arg) = raw.next() {
+            if arg == "--" {
+                args.positional.extend(raw.by_ref().cloned());
+                break;
+            }
+            let option = match arg.strip_prefix("--") {
+                Some(option) => option,
+                None => {
+                    args.positional.push(arg.clone());
+                    continue;
+                }
+            };
+            let (name, inline_value) = match option.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (option, None),
+            };
+
+            if takes_value(name) {
+                let value = match inline_value {
+                    Some(value) => value,
+                    None => raw
+                        .next()
+                        .cloned()
+                        .ok_or_else(|| CliError::Usage(format!("--{} needs a value", name)))?,
+                };
+                args.values.push((name.to_string(), value));
+            } else if flags.contains(&name) && inline_value.is_none() {
+                args.flags.push(name.to_string());
+            } else {
+                return Err(CliError::Usage(format!("unknown option --{}", name)));
+            }
+        }
+
+        if let Some(threads) = args.parsed::<usize>("threads")? {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build_global()
+                .map_err(|e| CliError::Software(format!("could not start threads: {}", e)))?;
+        }
+        Ok(args)
+    }
+
+    pub fn positional(&self) -> &[String] {
+        &self.positional
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|f| f == name)
+    }
+
+    /// The last value given for an option.
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| &v[..])
+    }
+
+    /// Every value given for an option which may be repeated.
+    pub fn values(&self, name: &str) -> Vec<&str> {
+        self.values
+            .iter()
+            .filter(|(n, _)| n == name)
+            .map(|(_, v)| &v[..])
+            .collect()
+    }
+
+    pub fn value_or<'a>(&'a self, name: &str, default: &'a str) -> &'a str {
+        self.value(name).unwrap_or(default)
+    }
+
+    pub fn parsed<T: FromStr>(&self, name: &str) -> CliResult<Option<T>> {
+        self.value(name)
+            .map(|v| {
+                v.parse()
+                    .map_err(|_| CliError::Usage(format!("invalid value for --{}: {}", name, v)))
+            })
+            .transpose()
+    }
+
+    pub fn model_path(&self) -> &str {
+        self.value_or("model", crate::DEFAULT_MODEL)
+    }
+
+    pub fn format(&self) -> CliResult<Format> {
+        self.parsed("format").map(Option::unwrap_or_default)
+    }
+
+    /// Thresholds given on the command line, falling back to defaults, usually the model's own
+    /// thresholds.
+    pub fn thresholds(&self, defaults: &Thresholds) -> CliResult<Thresholds> {
+        let thresholds = Thresholds {
+            ham: self.parsed("ham-threshold")?.unwrap_or(defaults.ham),
+            spam: self.parsed("spam-threshold")?.unwrap_or(defaults.spam),
+        };
+        if !(0.0..=1.0).contains(&thresholds.ham)
+            || !(0.0..=1.0).contains(&thresholds.spam)
+            || thresholds.ham > thresholds.spam
+        {
+            return Err(CliError::Usage(
+                "thresholds must satisfy 0 <= ham-threshold <= spam-threshold <= 1".to_string(),
+            ));
+        }
+        Ok(thresholds)
+    }
+
+    /// Scoring given with --scoring, falling back to default, usually the model's own scoring.
+    pub fn scoring(&self, default: Scoring) -> CliResult<Scoring> {
+        match self.value("scoring") {
+            Some(value) => value.parse().map_err(|_| {
+                CliError::Usage(format!(
+                    "unknown scoring {}, expected multinomial, complement or bernoulli",
+                    value
+                ))
+            }),
+            None => Ok(default),
+        }
+    }
+
+    /// Significant token selection given with --significant-tokens and --min-deviation, falling
+    /// back to default, usually the model's own. A count of 0 scores every token.
+    pub fn significant(
+        &self,
+        default: Option<SignificantTokens>,
+    ) -> CliResult<Option<SignificantTokens>> {
+        let count: Option<usize> = self.parsed("significant-tokens")?;
+        let min_deviation: Option<Probability> = self.parsed("min-deviation")?;
+        if count.is_none() && min_deviation.is_none() {
+            return Ok(default);
+        }
+        let base = default.unwrap_or_default();
+        let significant = SignificantTokens {
+            count: count.unwrap_or(base.count),
+            min_deviation: min_deviation.unwrap_or(base.min_deviation),
+        };
+        if !(0.0..=0.5).contains(&significant.min_deviation) {
+            return Err(CliError::Usage(
+                "--min-deviation must be between 0 and 0.5".to_string(),
+            ));
+        }
+        Ok(Some(significant).filter(|s| s.count > 0))
+    }
+
+    /// Read the model named by --model, applying any threshold and scoring overrides.
+    pub fn read_model(&self) -> CliResult<HSModel> {
+        let model = read_model(self.model_path())?;
+        let thresholds = self.thresholds(&model.config.thresholds)?;
+        let scoring = self.scoring(model.config.scoring)?;
+        if scoring == Scoring::Bernoulli
+            && model.ham_bow.documents() == 0
+            && model.spam_bow.documents() == 0
+        {
+            return Err(CliError::Data(
+                "bernoulli scoring needs document counts, which this model predates; retrain it"
+                    .to_string(),
+            ));
+        }
+        let significant = self.significant(model.config.significant)?;
+        let mut model = model.with_thresholds(thresholds).with_scoring(scoring);
+        model.config.significant = significant;
+        Ok(model)
+    }
+
+    /// Build a corpus from the --ham and --spam folders, or the given defaults if neither is set.
+    pub fn corpus(&self, default_ham: &str, default_spam: &str) -> CliResult<Corpus> {
+        self.corpus_from("ham", "spam", default_ham, default_spam)
+    }
+
+    /// Build a corpus from the folders given to the ham_option and spam_option options, or the
+    /// given defaults if neither is set.
+    pub fn corpus_from(
+        &self,
+        ham_option: &str,
+        spam_option: &str,
+        default_ham: &str,
+        default_spam: &str,
+    ) -> CliResult<Corpus> {
+        let (ham, spam) = match (self.values(ham_option), self.values(spam_option)) {
+            (ham, spam) if ham.is_empty() && spam.is_empty() => {
+                (vec![default_ham], vec![default_spam])
+            }
+            folders => folders,
+        };
+        let mut corpus = Corpus::new();
+        for (dirs, label) in [(ham, Label::Ham), (spam, Label::Spam)].iter() {
+            for dir in dirs {
+                corpus = corpus
+                    .add_folder(dir, *label)
+                    .ok_or_else(|| CliError::NoInput(format!("could not read folder {}", dir)))?;
+            }
+        }
+        Ok(corpus)
+    }
+}
+
+/// Output format for reports.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    #[default]
+    Text,
+    Json,
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => Err(()),
+        }
+    }
+}
+
+pub fn read_model(path: &str) -> CliResult<HSModel> {
+    if !Path::new(path).is_file() {
+        return Err(CliError::NoInput(format!("model {} does not exist", path)));
+    }
+    HSModel::read_from_file(path)
+        .ok_or_else(|| CliError::Data(format!("{} is not a valid model", path)))
+}
+
+/// Write a model as json if path ends in .json, otherwise in the binary model format.
+pub fn write_model(model: &HSModel, path: &str) -> CliResult<()> {
+    if let Some(parent) = Path::new(path).parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent).map_err(|e| {
+                CliError::CantCreate(format!("could not create {}: {}", parent.display(), e))
+            })?;
+        }
+    }
+    let written = if path.ends_with(".json") {
+        model.write_to_json(path)
+    } else {
+        model.write_to_file(path)
+    };
+    written.ok_or_else(|| CliError::CantCreate(format!("could not write model to {}", path)))
+}
+
+/// Serialize a value as pretty json for output.
+pub fn to_json<T: serde::Serialize>(value: &T) -> CliResult<String> {
+    serde_json::to_string_pretty(value).map_err(|e| CliError::Software(e.to_string()))
+}
+
+/// Quote a csv field if it contains a delimiter.
+pub fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -0,0 +1,66 @@
+use std::time::Instant;
+
+use rammer::{score_corpus, Calibration, CalibrationMethod, EvaluationReport, ReliabilityBin};
+
+use crate::{
+    args::{write_model, Args},
+    error::{CliError, CliResult},
+};
+
+pub fn run(raw: &[String]) -> CliResult<i32> {
+    let args = Args::parse(
+        raw,
+        &["ham", "spam", "method", "held-out", "seed", "output"],
+        &[],
+    )?;
+    let method: CalibrationMethod = match args.value("method") {
+        Some(value) => value.parse().map_err(|_| {
+            CliError::Usage(format!(
+                "unknown calibration method {}, expected platt or isotonic",
+                value
+            ))
+        })?,
+        None => CalibrationMethod::default(),
+    };
+    let held_out: f64 = args.parsed("held-out")?.unwrap_or(0.5);
+    if !(held_out > 0.0 && held_out < 1.0) {
+        return Err(CliError::Usage(
+            "--held-out must be between 0 and 1".to_string(),
+        ));
+    }
+    let seed: u64 = args.parsed("seed")?.unwrap_or(0);
+    let model = args.read_model()?;
+    let corpus = args.corpus("data/validate/ham", "data/validate/spam")?;
+
+    let start = Instant::now();
+    let scored = score_corpus(&model, &corpus);
+    let seconds = start.elapsed().as_secs_f64();
+    let (calibration, mut held_out) = Calibration::fit_held_out(method, &scored, held_out, seed)
+        .ok_or_else(|| {
+            CliError::Data(
+                "calibration needs at least 2 validation documents of both ham and spam"
+                    .to_string(),
+            )
+        })?;
+    // Both errors are measured on the held out documents, which the calibration wasn't fitted to.
+    let before = EvaluationReport::from_scores(&held_out, &model.config.thresholds, seconds);
+    let model = model.with_calibration(calibration);
+    for doc in &mut held_out {
+        doc.probability = model.config.probability(doc.log_odds);
+    }
+    let after = EvaluationReport::from_scores(&held_out, &model.config.thresholds, seconds);
+    let output = args.value_or("output", args.model_path());
+    write_model(&model, output)?;
+
+    println!(
+        "Wrote {} calibration fitted to {} documents to {}; expected calibration error on {} held out documents {:.4} before, {:.4} after\n",
+        method,
+        scored.len() - held_out.len(),
+        output,
+        held_out.len(),
+        ReliabilityBin::calibration_error(&before.reliability),
+        ReliabilityBin::calibration_error(&after.reliability)
+    );
+    println!("{}", after.to_text());
+    Ok(0)
+}
@@ -0,0 +1,114 @@
+use std::{
+    fs,
+    io::{self, Read},
+};
+
+use rayon::prelude::*;
+use serde::Serialize;
+
+use rammer::{Classification, ClassifierConfig, MappedModel, Probability};
+
+use crate::{
+    args::{csv_field, to_json, Args, Format},
+    error::{CliError, CliResult},
+};
+
+#[derive(Serialize)]
+struct Classified {
+    path: String,
+    classification: Classification,
+    probability: Probability,
+    log_odds: f64,
+}
+
+pub fn run(raw: &[String]) -> CliResult<i32> {
+    let args = Args::parse(raw, &["format"], &[])?;
+    let format = args.format()?;
+
+    let texts: Vec<(String, String)> = if args.positional().is_empty() {
+        let mut bytes = Vec::new();
+        io::stdin()
+            .read_to_end(&mut bytes)
+            .map_err(|e| CliError::Io(format!("could not read stdin: {}", e)))?;
+        vec![(
+            "-".to_string(),
+            String::from_utf8_lossy(&bytes).into_owned(),
+        )]
+    } else {
+        args.positional()
+            .iter()
+            .map(|path| {
+                fs::read(path)
+                    .map(|bytes| (path.clone(), String::from_utf8_lossy(&bytes).into_owned()))
+                    .map_err(|e| CliError::NoInput(format!("could not read {}: {}", path, e)))
+            })
+            .collect::<CliResult<_>>()?
+    };
+
+    // A mapped model is scored straight from the file instead of being loaded.
+    let results = match MappedModel::open(args.model_path()) {
+        Some(model) => {
+            let scoring = args.scoring(model.config.scoring)?;
+            let significant = args.significant(model.config.significant)?;
+            let mut model = model.with_scoring(scoring).ok_or_else(|| {
+                CliError::Usage(format!(
+                    "{} scoring needs document frequencies, which mapped models don't store",
+                    scoring
+                ))
+            })?;
+            model.config.significant = significant;
+            model.config.thresholds = args.thresholds(&model.config.thresholds)?;
+            classify_texts(&texts, &model.config, |text| model.text_log_odds(text))
+        }
+        None => {
+            let model = args.read_model()?;
+            let scorer = model.scorer();
+            classify_texts(&texts, &model.config, |text| scorer.text_log_odds(text))
+        }
+    };
+
+    match format {
+        Format::Text => {
+            for r in &results {
+                println!("{}: {} ({:.4})", r.path, r.classification, r.probability);
+            }
+        }
+        Format::Json => println!("{}", to_json(&results)?),
+        Format::Csv => {
+            println!("path,classification,probability,log_odds");
+            for r in &results {
+                println!(
+                    "{},{},{},{}",
+                    csv_field(&r.path),
+                    r.classification,
+                    r.probability,
+                    r.log_odds
+                );
+            }
+        }
+    }
+    Ok(0)
+}
+
+fn classify_texts<F>(
+    texts: &[(String, String)],
+    config: &ClassifierConfig,
+    log_odds: F,
+) -> Vec<Classified>
+where
+    F: Fn(&str) -> f64 + Sync,
+{
+    texts
+        .par_iter()
+        .map(|(path, text)| {
+            let log_odds = log_odds(text);
+            let probability = config.probability(log_odds);
+            Classified {
+                path: path.clone(),
+                classification: config.thresholds.classify(probability),
+                probability,
+                log_odds,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,29 @@
+use rammer::Scoring;
+
+use crate::{
+    args::{read_model, write_model, Args},
+    error::{CliError, CliResult},
+};
+
+pub fn run(raw: &[String]) -> CliResult<i32> {
+    let args = Args::parse(raw, &["output"], &["mapped"])?;
+    let output = args
+        .value("output")
+        .ok_or_else(|| CliError::Usage("convert needs --output".to_string()))?;
+    let model = read_model(args.model_path())?;
+    if args.flag("mapped") {
+        if model.config.scoring == Scoring::Bernoulli {
+            return Err(CliError::Usage(
+                "mapped models can't use bernoulli scoring, which needs document counts"
+                    .to_string(),
+            ));
+        }
+        model.write_to_mapped(output).ok_or_else(|| {
+            CliError::CantCreate(format!("could not write mapped model to {}", output))
+        })?;
+    } else {
+        write_model(&model, output)?;
+    }
+    println!("Wrote {}", output);
+    Ok(0)
+}
@@ -0,0 +1,90 @@
+use std::path::Path;
+
+use rammer::{cross_corpus, ClassifierConfig, Corpus, Metrics, Scoring, Thresholds};
+
+use crate::{
+    args::{Args, Format},
+    error::{CliError, CliResult},
+};
+
+pub fn run(raw: &[String]) -> CliResult<i32> {
+    let args = Args::parse(
+        raw,
+        &[
+            "corpus",
+            "train",
+            "test-fraction",
+            "seed",
+            "metric",
+            "format",
+        ],
+        &[],
+    )?;
+    let format = args.format()?;
+    let seed: u64 = args.parsed("seed")?.unwrap_or(0);
+    let test_fraction: f64 = args.parsed("test-fraction")?.unwrap_or(0.3);
+    if !(test_fraction > 0.0 && test_fraction < 1.0) {
+        return Err(CliError::Usage(
+            "--test-fraction must be between 0 and 1".to_string(),
+        ));
+    }
+    let metric = args.value_or("metric", "accuracy");
+    if !Metrics::NAMES.contains(&metric) {
+        return Err(CliError::Usage(format!(
+            "unknown metric {}, expected one of {}",
+            metric,
+            Metrics::NAMES.join(", ")
+        )));
+    }
+    let config = ClassifierConfig {
+        thresholds: args.thresholds(&Thresholds::default())?,
+        scoring: args.scoring(Scoring::default())?,
+        significant: args.significant(None)?,
+        calibration: None,
+    };
+
+    let mut corpora: Vec<(&str, Corpus)> = Vec::new();
+    for value in args.values("corpus") {
+        let (name, dir) = value
+            .split_once('=')
+            .filter(|(name, _)| !name.is_empty() && !name.contains('+'))
+            .ok_or_else(|| CliError::Usage(format!("--corpus must be NAME=DIR, not {}", value)))?;
+        let (ham, spam) = (Path::new(dir).join("ham"), Path::new(dir).join("spam"));
+        let corpus = Corpus::from_folders(&ham.to_string_lossy(), &spam.to_string_lossy())
+            .ok_or_else(|| CliError::NoInput(format!("{} needs ham and spam folders", dir)))?;
+        corpora.push((name, corpus));
+    }
+    if corpora.is_empty() {
+        return Err(CliError::Usage(
+            "cross-corpus needs at least one --corpus".to_string(),
+        ));
+    }
+    let mut train_sets = args.values("train");
+    if train_sets.is_empty() {
+        train_sets = corpora.iter().map(|(name, _)| *name).collect();
+    }
+
+    let named: Vec<(&str, &Corpus)> = corpora.iter().map(|(n, c)| (*n, c)).collect();
+    let matrix = cross_corpus(&named, &train_sets, test_fraction, seed, &config).ok_or_else(|| {
+        CliError::Data(
+            "every --train name must be a --corpus, and every corpus needs at least 2 ham and 2 spam"
+                .to_string(),
+        )
+    })?;
+    match format {
+        Format::Text => print!(
+            "{}",
+            matrix
+                .to_text(metric)
+                .ok_or_else(|| CliError::Software("unknown metric".to_string()))?
+        ),
+        Format::Json => println!(
+            "{}",
+            matrix
+                .to_json()
+                .ok_or_else(|| CliError::Software("could not serialize matrix".to_string()))?
+        ),
+        Format::Csv => print!("{}", matrix.to_csv()),
+    }
+    Ok(0)
+}
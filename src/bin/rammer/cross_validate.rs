@@ -0,0 +1,41 @@
+use rammer::{cross_validate, ClassifierConfig, Scoring, Thresholds};
+
+use crate::{
+    args::{Args, Format},
+    error::{CliError, CliResult},
+};
+
+pub fn run(raw: &[String]) -> CliResult<i32> {
+    let args = Args::parse(raw, &["ham", "spam", "folds", "seed", "format"], &[])?;
+    let format = args.format()?;
+    let k: usize = args.parsed("folds")?.unwrap_or(10);
+    let seed: u64 = args.parsed("seed")?.unwrap_or(0);
+    if k < 2 {
+        return Err(CliError::Usage("--folds must be at least 2".to_string()));
+    }
+    let config = ClassifierConfig {
+        thresholds: args.thresholds(&Thresholds::default())?,
+        scoring: args.scoring(Scoring::default())?,
+        significant: args.significant(None)?,
+        calibration: None,
+    };
+    let corpus = args.corpus("data/train/ham", "data/train/spam")?;
+
+    let report = cross_validate(&corpus, k, seed, &config).ok_or_else(|| {
+        CliError::Data(format!(
+            "{}-fold cross-validation needs at least {} readable ham and spam documents",
+            k, k
+        ))
+    })?;
+    match format {
+        Format::Text => println!("{}", report.to_text()),
+        Format::Json => println!(
+            "{}",
+            report
+                .to_json()
+                .ok_or_else(|| CliError::Software("could not serialize report".to_string()))?
+        ),
+        Format::Csv => print!("{}", report.to_csv()),
+    }
+    Ok(0)
+}
@@ -0,0 +1,53 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    args::{write_model, Args},
+    error::{CliError, CliResult},
+};
+
+const DAY: u64 = 24 * 60 * 60;
+
+pub fn run(raw: &[String]) -> CliResult<i32> {
+    let args = Args::parse(raw, &["half-life", "max-age", "now", "output"], &[])?;
+    let half_life: Option<f64> = args.parsed("half-life")?;
+    let max_age: Option<f64> = args.parsed("max-age")?;
+    if half_life.is_none() && max_age.is_none() {
+        return Err(CliError::Usage(
+            "decay needs --half-life or --max-age".to_string(),
+        ));
+    }
+    if half_life.is_some_and(|d| d.is_nan() || d <= 0.0)
+        || max_age.is_some_and(|d| d.is_nan() || d < 0.0)
+    {
+        return Err(CliError::Usage(
+            "--half-life must be positive and --max-age must not be negative".to_string(),
+        ));
+    }
+    let now = match args.parsed("now")? {
+        Some(now) => now,
+        None => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| CliError::Software(e.to_string()))?
+            .as_secs(),
+    };
+    let mut model = args.read_model()?;
+    let before = (model.ham_bow.len(), model.spam_bow.len());
+    if let Some(days) = max_age {
+        model = model.expire(now, (days * DAY as f64) as u64);
+    }
+    if let Some(days) = half_life {
+        model = model.decay(now, ((days * DAY as f64) as u64).max(1));
+    }
+    let output = args.value_or("output", args.model_path());
+    write_model(&model, output)?;
+
+    println!(
+        "Kept {} of {} ham tokens and {} of {} spam tokens, wrote {}",
+        model.ham_bow.len(),
+        before.0,
+        model.spam_bow.len(),
+        before.1,
+        output
+    );
+    Ok(0)
+}
@@ -0,0 +1,40 @@
+use rammer::ModelDiff;
+
+use crate::{
+    args::{read_model, Args, Format},
+    error::{CliError, CliResult},
+};
+
+pub fn run(raw: &[String]) -> CliResult<i32> {
+    let args = Args::parse(raw, &["against", "ham", "spam", "top", "format"], &[])?;
+    let format = args.format()?;
+    let against = args
+        .value("against")
+        .ok_or_else(|| CliError::Usage("diff needs --against".to_string()))?;
+    let old = args.read_model()?;
+    let new = read_model(against)?;
+    let new_thresholds = args.thresholds(&new.config.thresholds)?;
+    let new = new.with_thresholds(new_thresholds);
+    let top = args.parsed("top")?.unwrap_or(20);
+    let validation = if args.values("ham").is_empty() && args.values("spam").is_empty() {
+        None
+    } else {
+        Some(args.corpus("", "")?)
+    };
+
+    let diff = ModelDiff::new(&old, &new, top, validation.as_ref());
+    match format {
+        Format::Text => print!("{}", diff.to_text()),
+        Format::Json => println!(
+            "{}",
+            diff.to_json()
+                .ok_or_else(|| CliError::Software("could not serialize diff".to_string()))?
+        ),
+        Format::Csv => {
+            return Err(CliError::Usage(
+                "diff supports the text and json formats".to_string(),
+            ))
+        }
+    }
+    Ok(0)
+}
@@ -0,0 +1,47 @@
+use std::fmt;
+
+/// An error which ends the program, and the exit status it ends with.
+/// Exit statuses follow the BSD sysexits.h conventions.
+#[derive(Debug)]
+pub enum CliError {
+    /// The command line was wrong.
+    Usage(String),
+    /// An input file or folder does not exist or can not be read.
+    NoInput(String),
+    /// An input was read but is not valid, e.g. a corrupt model.
+    Data(String),
+    /// An output file could not be created or written.
+    CantCreate(String),
+    /// Reading or writing a stream failed.
+    Io(String),
+    /// Something went wrong inside rammer itself.
+    Software(String),
+}
+
+impl CliError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CliError::Usage(_) => 64,
+            CliError::Data(_) => 65,
+            CliError::NoInput(_) => 66,
+            CliError::Software(_) => 70,
+            CliError::CantCreate(_) => 73,
+            CliError::Io(_) => 74,
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CliError::Usage(m)
+            | CliError::NoInput(m)
+            | CliError::Data(m)
+            | CliError::CantCreate(m)
+            | CliError::Io(m)
+            | CliError::Software(m) => write!(f, "{}", m),
+        }
+    }
+}
+
+pub type CliResult<T> = Result<T, CliError>;
@@ -0,0 +1,47 @@
+use std::io::{self, Read, Write};
+
+use rammer::{annotate, Classification};
+
+use crate::{
+    args::Args,
+    error::{CliError, CliResult},
+};
+
+/// Reads a message on stdin and writes it to stdout with X-Spam headers added, for use from
+/// procmail, maildrop or a Postfix pipe. The exit status is the classification: 0 for spam, 1
+/// for ham and 2 for unsure, like bogofilter.
+pub fn run(raw: &[String]) -> CliResult<i32> {
+    let args = Args::parse(raw, &[], &[])?;
+    if !args.positional().is_empty() {
+        return Err(CliError::Usage(
+            "filter reads the message from stdin".to_string(),
+        ));
+    }
+
+    let mut message = Vec::new();
+    io::stdin()
+        .read_to_end(&mut message)
+        .map_err(|e| CliError::Io(format!("could not read message: {}", e)))?;
+
+    let mut stdout = io::stdout();
+    let model = match args.read_model() {
+        Ok(model) => model,
+        Err(e) => {
+            // Pass the message through untouched so mail is never lost.
+            let _ = stdout.write_all(&message).and_then(|_| stdout.flush());
+            return Err(e);
+        }
+    };
+
+    let (annotated, class) = annotate(&message, &model);
+    stdout
+        .write_all(&annotated)
+        .and_then(|_| stdout.flush())
+        .map_err(|e| CliError::Io(format!("could not write message: {}", e)))?;
+
+    Ok(match class {
+        Classification::Spam => 0,
+        Classification::Ham => 1,
+        Classification::Unsure => 2,
+    })
+}
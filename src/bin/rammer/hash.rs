@@ -0,0 +1,50 @@
+use rammer::{HashedBagOfWords, HashingReport, Scoring};
+
+use crate::{
+    args::{Args, Format},
+    error::{CliError, CliResult},
+};
+
+/// Buckets used when --buckets is not given, 16 MiB of counts.
+const DEFAULT_BUCKETS: usize = 1 << 20;
+
+pub fn run(raw: &[String]) -> CliResult<i32> {
+    let args = Args::parse(raw, &["ham", "spam", "buckets", "seed", "format"], &[])?;
+    let format = args.format()?;
+    let buckets = args.parsed("buckets")?.unwrap_or(DEFAULT_BUCKETS);
+    if buckets == 0 {
+        return Err(CliError::Usage("--buckets must be at least 1".to_string()));
+    }
+    let seed = args.parsed("seed")?.unwrap_or(0);
+    if HashedBagOfWords::new(buckets, seed).is_none() {
+        return Err(CliError::Usage(format!(
+            "--buckets {} is too many to allocate",
+            buckets
+        )));
+    }
+    let model = args.read_model()?;
+    if model.config.scoring == Scoring::Bernoulli {
+        return Err(CliError::Usage(
+            "hashed models don't support bernoulli scoring".to_string(),
+        ));
+    }
+    let corpus = args.corpus("data/validate/ham", "data/validate/spam")?;
+
+    let report = HashingReport::new(&model, buckets, seed, &corpus)
+        .ok_or_else(|| CliError::Software("could not encode the model".to_string()))?;
+    match format {
+        Format::Text => print!("{}", report.to_text()),
+        Format::Json => println!(
+            "{}",
+            report
+                .to_json()
+                .ok_or_else(|| CliError::Software("could not serialize report".to_string()))?
+        ),
+        Format::Csv => {
+            return Err(CliError::Usage(
+                "hash supports the text and json formats".to_string(),
+            ))
+        }
+    }
+    Ok(0)
+}
@@ -0,0 +1,54 @@
+use rammer::ModelSummary;
+
+use crate::{
+    args::{read_model, to_json, Args, Format},
+    error::{CliError, CliResult},
+};
+
+pub fn run(raw: &[String]) -> CliResult<i32> {
+    let args = Args::parse(raw, &["top", "min-count", "token", "format"], &["dump"])?;
+    let format = args.format()?;
+    let model = read_model(args.model_path())?;
+    if args.flag("dump") {
+        println!("{}", to_json(&model)?);
+        return Ok(0);
+    }
+
+    let tokens = args.values("token");
+    if !tokens.is_empty() {
+        let stats: Vec<_> = tokens.iter().map(|t| model.token_stats(t)).collect();
+        match format {
+            Format::Text => {
+                println!(
+                    "{:<24} {:>10} {:>10} {:>12}",
+                    "token", "ham", "spam", "probability"
+                );
+                for s in &stats {
+                    print!("{}", s.to_row());
+                }
+            }
+            Format::Json => println!("{}", to_json(&stats)?),
+            Format::Csv => return Err(csv_unsupported()),
+        }
+        return Ok(0);
+    }
+
+    let top = args.parsed("top")?.unwrap_or(20);
+    let min_count = args.parsed("min-count")?.unwrap_or(5);
+    let summary = ModelSummary::new(&model, top, min_count);
+    match format {
+        Format::Text => print!("{}", summary.to_text()),
+        Format::Json => println!(
+            "{}",
+            summary
+                .to_json()
+                .ok_or_else(|| CliError::Software("could not serialize summary".to_string()))?
+        ),
+        Format::Csv => return Err(csv_unsupported()),
+    }
+    Ok(0)
+}
+
+fn csv_unsupported() -> CliError {
+    CliError::Usage("inspect supports the text and json formats".to_string())
+}
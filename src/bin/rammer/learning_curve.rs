@@ -0,0 +1,71 @@
+use rammer::{learning_curve, ClassifierConfig, Scoring, Thresholds};
+
+use crate::{
+    args::{Args, Format},
+    error::{CliError, CliResult},
+};
+
+/// Fractions of the training data used when --fractions is not given.
+const DEFAULT_FRACTIONS: &str = "0.1,0.2,0.3,0.4,0.5,0.6,0.7,0.8,0.9,1.0";
+
+pub fn run(raw: &[String]) -> CliResult<i32> {
+    let args = Args::parse(
+        raw,
+        &[
+            "ham",
+            "spam",
+            "validate-ham",
+            "validate-spam",
+            "fractions",
+            "seed",
+            "format",
+        ],
+        &[],
+    )?;
+    let format = args.format()?;
+    let seed: u64 = args.parsed("seed")?.unwrap_or(0);
+    let fractions = args
+        .value_or("fractions", DEFAULT_FRACTIONS)
+        .split(',')
+        .map(|f| {
+            f.trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|f| *f > 0.0 && *f <= 1.0)
+                .ok_or_else(|| {
+                    CliError::Usage(format!("--fractions must be between 0 and 1, not {}", f))
+                })
+        })
+        .collect::<CliResult<Vec<f64>>>()?;
+    let config = ClassifierConfig {
+        thresholds: args.thresholds(&Thresholds::default())?,
+        scoring: args.scoring(Scoring::default())?,
+        significant: args.significant(None)?,
+        calibration: None,
+    };
+    let train = args.corpus("data/train/ham", "data/train/spam")?;
+    let validation = args.corpus_from(
+        "validate-ham",
+        "validate-spam",
+        "data/validate/ham",
+        "data/validate/spam",
+    )?;
+
+    let curve =
+        learning_curve(&train, &validation, &fractions, seed, &config).ok_or_else(|| {
+            CliError::Data(
+                "learning curves need training ham and spam and validation documents".to_string(),
+            )
+        })?;
+    match format {
+        Format::Text => print!("{}", curve.to_text()),
+        Format::Json => println!(
+            "{}",
+            curve
+                .to_json()
+                .ok_or_else(|| CliError::Software("could not serialize curve".to_string()))?
+        ),
+        Format::Csv => print!("{}", curve.to_csv()),
+    }
+    Ok(0)
+}
@@ -0,0 +1,225 @@
+//! rammer trains, runs and inspects spam/ham models from the command line.
+//! Run `rammer help` for usage.
+mod args;
+mod calibrate;
+mod classify;
+mod convert;
+mod cross_corpus;
+mod cross_validate;
+mod decay;
+mod diff;
+mod error;
+mod filter;
+mod hash;
+mod inspect;
+mod learning_curve;
+mod prune;
+mod retrain;
+mod roc;
+mod train;
+mod tune;
+mod validate;
+
+use std::{env, process};
+
+use error::{CliError, CliResult};
+
+/// Model used when --model is not given.
+pub const DEFAULT_MODEL: &str = "out/models/enron1_model.json";
+
+const USAGE: &str = "usage: rammer <command> [options]
+
+commands:
+  train      build a model from folders of known ham and spam
+  retrain    update a model from a Maildir's inbox and Junk folder
+  classify   classify files, or stdin when no files are given
+  filter     read a message on stdin and write it to stdout with X-Spam headers
+  validate   measure a model against folders of known ham and spam
+  roc        print the ROC and precision-recall curves of a model
+  tune       pick thresholds from validation data and save them in the model
+  calibrate  fit probabilities to validation data and save the calibration in the model
+  cross-validate
+             train and measure models on k folds of labelled data
+  learning-curve
+             measure models trained on growing fractions of the training data
+  cross-corpus
+             train on each corpus and measure on every other
+  decay      age out tokens which have not been seen recently
+  prune      shrink a model by dropping its least useful tokens
+  hash       measure how a model does with its tokens hashed into a fixed number of buckets
+  diff       compare a model with a retrained one
+  inspect    summarize a model, or look up single tokens
+  convert    rewrite a model as json or in the binary model format
+  help       print this message
+
+options for every command:
+  --model PATH            model file (default out/models/enron1_model.json), read as json or
+                          binary; models are written as json when PATH ends in .json and in the
+                          smaller, faster binary format otherwise
+  --threads N             number of worker threads (default one per cpu)
+  --ham-threshold P       classify probabilities below P as ham
+  --spam-threshold P      classify probabilities above P as spam
+  --scoring NAME          multinomial, complement naive Bayes for training data with far more
+                          ham than spam, or bernoulli, which counts each token once per message;
+                          train saves it in the model (default the model's)
+  --significant-tokens N  score only the N distinct tokens of a text furthest from 0.5, or
+                          every token when N is 0; train saves it (default the model's, or 15
+                          when only --min-deviation is given)
+  --min-deviation P       never score tokens whose spam probability is within P of 0.5
+
+train options:
+  --ham DIR, --spam DIR   training folders, may be repeated (default data/train/ham, data/train/spam)
+  --maildir DIR           also train on a Maildir's inbox (ham) and .Junk folder (spam)
+
+retrain options:
+  --maildir DIR           Maildir to learn from (required)
+  --learned PATH          where learned message ids are kept (default <model>.learned.json)
+  --ham-folder NAME       also learn NAME as ham, may be repeated
+  --spam-folder NAME      also learn NAME as spam, may be repeated
+
+classify options:
+  --format FORMAT         text, json or csv (default text)
+
+filter exits with 0 for spam, 1 for ham and 2 for unsure.
+
+validate options:
+  --ham DIR, --spam DIR   validation folders, may be repeated (default data/validate/ham, data/validate/spam)
+  --format FORMAT         text, json or csv (default text)
+  --verbose               print the probability of every document
+  --errors PATH           write the misclassified documents and their top tokens to PATH as json
+  --top-tokens N          tokens listed for each misclassified document (default 10)
+  --compare-scoring       also show the metrics of every scoring method side by side
+
+roc options:
+  --ham DIR, --spam DIR   validation folders, may be repeated (default data/validate/ham, data/validate/spam)
+  --format FORMAT         text, json or csv (default text)
+
+tune options:
+  --ham DIR, --spam DIR   validation folders, may be repeated (default data/validate/ham, data/validate/spam)
+  --max-fpr RATE          highest fraction of ham which may be called spam (required)
+  --max-fnr RATE          highest fraction of spam which may be called ham (default 0.05)
+  --output PATH           where to write the tuned model (default the --model path)
+
+calibrate options:
+  --ham DIR, --spam DIR   validation folders, may be repeated (default data/validate/ham, data/validate/spam)
+  --method NAME           platt, a sigmoid of the log odds, or isotonic, an increasing step
+                          function which needs more data (default platt); thresholds apply to the
+                          calibrated probabilities, so tune afterwards
+  --held-out F            fraction of each label kept out of the fit to measure the calibration
+                          on (default 0.5)
+  --seed N                seed for choosing the held out documents (default 0)
+  --output PATH           where to write the calibrated model (default the --model path)
+
+cross-validate options:
+  --ham DIR, --spam DIR   labelled folders, may be repeated (default data/train/ham, data/train/spam)
+  --folds K               number of folds (default 10)
+  --seed N                seed for shuffling documents into folds (default 0)
+  --format FORMAT         text, json or csv (default text)
+
+learning-curve options:
+  --ham DIR, --spam DIR   training folders, may be repeated (default data/train/ham, data/train/spam)
+  --validate-ham DIR, --validate-spam DIR
+                          validation folders, may be repeated (default data/validate/ham, data/validate/spam)
+  --fractions LIST        comma separated fractions of the training data (default 0.1,0.2,...,1.0)
+  --seed N                seed for drawing the training subsets (default 0)
+  --format FORMAT         text, json or csv (default text)
+
+cross-corpus options:
+  --corpus NAME=DIR       a corpus with ham and spam subfolders, may be repeated (required)
+  --train NAMES           train on the named corpora joined with +, may be repeated
+                          (default each corpus on its own)
+  --test-fraction F       fraction of every corpus held out for testing (default 0.3)
+  --seed N                seed for splitting the corpora (default 0)
+  --metric NAME           metric shown by the text format (default accuracy)
+  --format FORMAT         text, json or csv (default text)
+
+decay options:
+  --half-life DAYS        halve the count of every token each DAYS it goes unseen; tokens
+                          learned by retrain are stamped with their delivery time, other tokens
+                          only start to age from the first decay
+  --max-age DAYS          drop stamped tokens not seen for DAYS
+  --now SECONDS           the current time as a unix timestamp (default the system clock)
+  --output PATH           where to write the decayed model (default the --model path)
+
+prune options:
+  --ham DIR, --spam DIR   validation folders, may be repeated (default data/validate/ham, data/validate/spam)
+  --min-count N           drop tokens seen fewer than N times in ham and spam
+  --neutral E             drop tokens whose spam probability is within E of 0.5
+  --top-k K               keep only the K most informative tokens
+  --output PATH           where to write the pruned model (default the --model path)
+  --format FORMAT         text or json (default text)
+
+hash options:
+  --ham DIR, --spam DIR   validation folders, may be repeated (default data/validate/ham, data/validate/spam)
+  --buckets N             buckets in each hashed bag, 8 bytes each (default 1048576)
+  --seed S                seed the tokens are hashed with (default 0)
+  --format FORMAT         text or json (default text)
+
+diff options:
+  --against PATH          the newer model, compared with --model (required)
+  --ham DIR, --spam DIR   validation folders, may be repeated; when given, documents the two
+                          models classify differently are listed
+  --top N                 tokens whose spam probability changed most to list (default 20)
+  --format FORMAT         text or json (default text)
+
+inspect options:
+  --top N                 spammiest and hammiest tokens to list (default 20)
+  --min-count N           only list tokens seen at least N times in ham and spam (default 5)
+  --token WORD            print the counts and spam probability of WORD, may be repeated
+  --format FORMAT         text or json (default text)
+  --dump                  print the whole model as json
+
+convert options:
+  --output PATH           where to write the model, binary unless PATH ends in .json (required)
+  --mapped                write a read-only model which classify memory-maps instead of loading
+
+Errors exit with the sysexits.h codes: 64 usage, 65 bad data, 66 missing input,
+70 internal error, 73 can't create output, 74 i/o error.";
+
+fn main() {
+    let raw: Vec<String> = env::args().skip(1).collect();
+    match run(&raw) {
+        Ok(code) => process::exit(code),
+        Err(e) => {
+            eprintln!("rammer: {}", e);
+            if let CliError::Usage(_) = e {
+                eprintln!("run `rammer help` for usage");
+            }
+            process::exit(e.exit_code());
+        }
+    }
+}
+
+fn run(raw: &[String]) -> CliResult<i32> {
+    let (command, rest) = match raw.split_first() {
+        Some(split) => split,
+        None => {
+            eprintln!("{}", USAGE);
+            return Err(CliError::Usage("no command given".to_string()));
+        }
+    };
+    match &command[..] {
+        "train" => train::run(rest),
+        "retrain" => retrain::run(rest),
+        "classify" => classify::run(rest),
+        "filter" => filter::run(rest),
+        "validate" => validate::run(rest),
+        "roc" => roc::run(rest),
+        "tune" => tune::run(rest),
+        "calibrate" => calibrate::run(rest),
+        "cross-validate" => cross_validate::run(rest),
+        "learning-curve" => learning_curve::run(rest),
+        "cross-corpus" => cross_corpus::run(rest),
+        "decay" => decay::run(rest),
+        "prune" => prune::run(rest),
+        "hash" => hash::run(rest),
+        "diff" => diff::run(rest),
+        "inspect" => inspect::run(rest),
+        "convert" => convert::run(rest),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(0)
+        }
+        _ => Err(CliError::Usage(format!("unknown command {}", command))),
+    }
+}
@@ -0,0 +1,72 @@
+use rammer::PruneReport;
+
+use crate::{
+    args::{write_model, Args, Format},
+    error::{CliError, CliResult},
+};
+
+pub fn run(raw: &[String]) -> CliResult<i32> {
+    let args = Args::parse(
+        raw,
+        &[
+            "ham",
+            "spam",
+            "min-count",
+            "top-k",
+            "neutral",
+            "output",
+            "format",
+        ],
+        &[],
+    )?;
+    let format = args.format()?;
+    let min_count = args.parsed("min-count")?;
+    let top_k = args.parsed("top-k")?;
+    let neutral: Option<f64> = args.parsed("neutral")?;
+    if min_count.is_none() && top_k.is_none() && neutral.is_none() {
+        return Err(CliError::Usage(
+            "prune needs --min-count, --top-k or --neutral".to_string(),
+        ));
+    }
+    if neutral.is_some_and(|e| !(0.0..=0.5).contains(&e)) {
+        return Err(CliError::Usage(
+            "--neutral must be between 0 and 0.5".to_string(),
+        ));
+    }
+    let model = args.read_model()?;
+    let corpus = args.corpus("data/validate/ham", "data/validate/spam")?;
+
+    let mut pruned = model.clone();
+    if let Some(min_count) = min_count {
+        pruned = pruned.prune_min_count(min_count);
+    }
+    if let Some(epsilon) = neutral {
+        pruned = pruned.prune_neutral(epsilon);
+    }
+    if let Some(k) = top_k {
+        pruned = pruned.prune_top_k(k);
+    }
+    let report = PruneReport::new(&model, &pruned, &corpus)
+        .ok_or_else(|| CliError::Software("could not encode the model".to_string()))?;
+    let output = args.value_or("output", args.model_path());
+    write_model(&pruned, output)?;
+
+    match format {
+        Format::Text => {
+            println!("Wrote {}\n", output);
+            print!("{}", report.to_text());
+        }
+        Format::Json => println!(
+            "{}",
+            report
+                .to_json()
+                .ok_or_else(|| CliError::Software("could not serialize report".to_string()))?
+        ),
+        Format::Csv => {
+            return Err(CliError::Usage(
+                "prune supports the text and json formats".to_string(),
+            ))
+        }
+    }
+    Ok(0)
+}
@@ -0,0 +1,61 @@
+use rammer::{Label, LearnedMessages, Maildir};
+
+use crate::{
+    args::{write_model, Args},
+    error::{CliError, CliResult},
+};
+
+pub fn run(raw: &[String]) -> CliResult<i32> {
+    let args = Args::parse(
+        raw,
+        &["maildir", "learned", "ham-folder", "spam-folder"],
+        &[],
+    )?;
+    let dir = args
+        .value("maildir")
+        .ok_or_else(|| CliError::Usage("retrain needs --maildir".to_string()))?;
+    let model_path = args.model_path();
+    let learned_path = args.value("learned").map_or_else(
+        || format!("{}.learned.json", model_path.trim_end_matches(".json")),
+        str::to_string,
+    );
+
+    let model = args.read_model()?;
+    let mut learned = if std::path::Path::new(&learned_path).exists() {
+        LearnedMessages::read_from_json(&learned_path).ok_or_else(|| {
+            CliError::Data(format!("{} is not a learned messages file", learned_path))
+        })?
+    } else {
+        LearnedMessages::new()
+    };
+
+    let mut maildir = Maildir::new(dir);
+    for folder in args.values("ham-folder") {
+        maildir = maildir.label_folder(folder, Label::Ham);
+    }
+    for folder in args.values("spam-folder") {
+        maildir = maildir.label_folder(folder, Label::Spam);
+    }
+    let corpus = maildir
+        .corpus()
+        .ok_or_else(|| CliError::NoInput(format!("{} is not a maildir", dir)))?;
+
+    let (model, summary) = model.retrain(&corpus, &mut learned);
+    write_model(&model, model_path)?;
+    learned.write_to_json(&learned_path).ok_or_else(|| {
+        CliError::CantCreate(format!(
+            "could not write learned messages to {}",
+            learned_path
+        ))
+    })?;
+
+    println!(
+        "Learned {} ham, {} spam, relearned {}, skipped {}, unreadable {}",
+        summary.learned_ham,
+        summary.learned_spam,
+        summary.relearned,
+        summary.skipped,
+        summary.unreadable
+    );
+    Ok(0)
+}
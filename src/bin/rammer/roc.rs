@@ -0,0 +1,46 @@
+use serde::Serialize;
+
+use rammer::{score_corpus, CurvePoint, RocCurve};
+
+use crate::{
+    args::{to_json, Args, Format},
+    error::{CliError, CliResult},
+};
+
+#[derive(Serialize)]
+struct Curves<'a> {
+    auc: f64,
+    average_precision: f64,
+    points: &'a [CurvePoint],
+}
+
+pub fn run(raw: &[String]) -> CliResult<i32> {
+    let args = Args::parse(raw, &["ham", "spam", "format"], &[])?;
+    let format = args.format()?;
+    let model = args.read_model()?;
+    let corpus = args.corpus("data/validate/ham", "data/validate/spam")?;
+
+    let scored = score_corpus(&model, &corpus);
+    if scored.is_empty() {
+        return Err(CliError::Data("no validation documents found".to_string()));
+    }
+    let curve = RocCurve::from_scores(&scored);
+
+    match format {
+        Format::Text => {
+            println!("ROC AUC:           {:.4}", curve.auc());
+            println!("Average precision: {:.4}", curve.average_precision());
+            println!("Distinct scores:   {}", curve.points.len());
+        }
+        Format::Json => println!(
+            "{}",
+            to_json(&Curves {
+                auc: curve.auc(),
+                average_precision: curve.average_precision(),
+                points: &curve.points,
+            })?
+        ),
+        Format::Csv => print!("{}", curve.to_csv()),
+    }
+    Ok(0)
+}
@@ -0,0 +1,47 @@
+use rammer::{HSModel, Label, Maildir};
+
+use crate::{
+    args::{write_model, Args},
+    error::{CliError, CliResult},
+};
+
+pub fn run(raw: &[String]) -> CliResult<i32> {
+    let args = Args::parse(raw, &["ham", "spam", "maildir"], &[])?;
+    if !args.positional().is_empty() {
+        return Err(CliError::Usage(
+            "train takes no positional arguments".to_string(),
+        ));
+    }
+
+    let mut corpus = match args.value("maildir") {
+        Some(_) if args.values("ham").is_empty() && args.values("spam").is_empty() => {
+            rammer::Corpus::new()
+        }
+        _ => args.corpus("data/train/ham", "data/train/spam")?,
+    };
+    if let Some(dir) = args.value("maildir") {
+        let mail = Maildir::new(dir)
+            .corpus()
+            .ok_or_else(|| CliError::NoInput(format!("{} is not a maildir", dir)))?;
+        corpus = corpus.combine(mail);
+    }
+    if corpus.is_empty() {
+        return Err(CliError::Data("no training documents found".to_string()));
+    }
+
+    let (ham_bow, spam_bow) = corpus.bows();
+    let model = HSModel::from_bows(ham_bow, spam_bow);
+    let thresholds = args.thresholds(&model.config.thresholds)?;
+    let scoring = args.scoring(model.config.scoring)?;
+    let mut model = model.with_thresholds(thresholds).with_scoring(scoring);
+    model.config.significant = args.significant(None)?;
+    write_model(&model, args.model_path())?;
+
+    println!(
+        "Trained on {} ham and {} spam documents, wrote {}",
+        corpus.count(Label::Ham),
+        corpus.count(Label::Spam),
+        args.model_path()
+    );
+    Ok(0)
+}
@@ -0,0 +1,43 @@
+use std::time::Instant;
+
+use rammer::{optimize_thresholds, score_corpus, EvaluationReport};
+
+use crate::{
+    args::{write_model, Args},
+    error::{CliError, CliResult},
+};
+
+pub fn run(raw: &[String]) -> CliResult<i32> {
+    let args = Args::parse(raw, &["ham", "spam", "max-fpr", "max-fnr", "output"], &[])?;
+    let max_fpr: f64 = args
+        .parsed("max-fpr")?
+        .ok_or_else(|| CliError::Usage("tune needs --max-fpr".to_string()))?;
+    let max_fnr: f64 = args.parsed("max-fnr")?.unwrap_or(0.05);
+    if !(0.0..=1.0).contains(&max_fpr) || !(0.0..=1.0).contains(&max_fnr) {
+        return Err(CliError::Usage(
+            "--max-fpr and --max-fnr must be between 0 and 1".to_string(),
+        ));
+    }
+    let model = args.read_model()?;
+    let corpus = args.corpus("data/validate/ham", "data/validate/spam")?;
+
+    let start = Instant::now();
+    let scored = score_corpus(&model, &corpus);
+    let seconds = start.elapsed().as_secs_f64();
+    let thresholds = optimize_thresholds(&scored, max_fpr, max_fnr).ok_or_else(|| {
+        CliError::Data("tuning needs validation documents of both ham and spam".to_string())
+    })?;
+    let model = model.with_thresholds(thresholds);
+    let output = args.value_or("output", args.model_path());
+    write_model(&model, output)?;
+
+    println!(
+        "Wrote thresholds ham < {:.6}, spam > {:.6} to {}\n",
+        thresholds.ham, thresholds.spam, output
+    );
+    println!(
+        "{}",
+        EvaluationReport::from_scores(&scored, &thresholds, seconds).to_text()
+    );
+    Ok(0)
+}
@@ -0,0 +1,79 @@
+use std::{fs, time::Instant};
+
+use rammer::{score_corpus, ErrorAnalysis, EvaluationReport, ScoringComparison};
+
+use crate::{
+    args::{Args, Format},
+    error::{CliError, CliResult},
+};
+
+pub fn run(raw: &[String]) -> CliResult<i32> {
+    let args = Args::parse(
+        raw,
+        &["ham", "spam", "format", "errors", "top-tokens"],
+        &["verbose", "compare-scoring"],
+    )?;
+    let format = args.format()?;
+    let model = args.read_model()?;
+    let corpus = args.corpus("data/validate/ham", "data/validate/spam")?;
+
+    let start = Instant::now();
+    let scored = score_corpus(&model, &corpus);
+    let seconds = start.elapsed().as_secs_f64();
+    if scored.is_empty() {
+        return Err(CliError::Data("no validation documents found".to_string()));
+    }
+    if args.flag("verbose") {
+        for doc in &scored {
+            eprintln!("Probability: {:.8}\t\t({})", doc.probability, doc.label);
+        }
+    }
+
+    if let Some(path) = args.value("errors") {
+        let top_tokens = args.parsed("top-tokens")?.unwrap_or(10);
+        let errors = ErrorAnalysis::from_scores(&model, &scored, top_tokens);
+        let json = errors
+            .to_json()
+            .ok_or_else(|| CliError::Software("could not serialize errors".to_string()))?;
+        fs::write(path, json)
+            .map_err(|e| CliError::CantCreate(format!("could not write {}: {}", path, e)))?;
+        eprintln!(
+            "Wrote {} false positives and {} false negatives to {}",
+            errors.false_positives.len(),
+            errors.false_negatives.len(),
+            path
+        );
+    }
+
+    let report = EvaluationReport::from_scores(&scored, &model.config.thresholds, seconds);
+    if args.flag("compare-scoring") {
+        let comparison = ScoringComparison::evaluate(&model, &corpus);
+        match format {
+            Format::Text => println!(
+                "{}\n\nScoring {}:\n{}",
+                report.to_text(),
+                model.config.scoring,
+                comparison.to_text()
+            ),
+            Format::Json => println!(
+                "{}",
+                comparison.to_json().ok_or_else(|| {
+                    CliError::Software("could not serialize comparison".to_string())
+                })?
+            ),
+            Format::Csv => print!("{}", comparison.to_csv()),
+        }
+        return Ok(0);
+    }
+    match format {
+        Format::Text => println!("{}", report.to_text()),
+        Format::Json => println!(
+            "{}",
+            report
+                .to_json()
+                .ok_or_else(|| CliError::Software("could not serialize report".to_string()))?
+        ),
+        Format::Csv => print!("{}", report.to_csv()),
+    }
+    Ok(0)
+}
@@ -0,0 +1,566 @@
+//! Turning a model's log odds into probabilities which mean what they say.
+//! Naive Bayes treats every token as independent evidence, so its probabilities are far too
+//! confident: plenty of ham scores 0.99. A [Calibration](enum.Calibration.html) is fitted on
+//! held out documents the model has already scored and maps their log odds to the fraction of
+//! documents like them which really were spam. Platt scaling fits a sigmoid, which needs little
+//! data and keeps the ranking strictly; isotonic regression fits any increasing step function,
+//! which follows the data more closely but needs more of it.
+//!
+//! A [ReliabilityBin](struct.ReliabilityBin.html) per slice of the probability range shows how
+//! well calibrated a model is: in a well calibrated model, the documents scored about 0.7 are
+//! about 70% spam.
+//! ```
+//! use rammer::{logit, Calibration, CalibrationMethod, Label, ScoredDocument};
+//! let doc = |label, log_odds| ScoredDocument { path: "x".into(), label, probability: 0.0, log_odds };
+//! // The model is sure of everything, but is wrong a third of the time.
+//! let scored = vec![
+//!     doc(Label::Spam, 20.0), doc(Label::Spam, 20.0), doc(Label::Ham, 20.0),
+//!     doc(Label::Ham, -20.0), doc(Label::Ham, -20.0), doc(Label::Spam, -20.0),
+//! ];
+//! let calibration = Calibration::fit(CalibrationMethod::Isotonic, &scored).unwrap();
+//! assert!((calibration.probability(20.0) - 2.0 / 3.0).abs() < 1e-12);
+//! ```
+use std::{convert::TryFrom, fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cross_corpus::stratified_split, logistic, rng::SplitMix64, Label, Probability, ScoredDocument,
+};
+
+/// Ways of fitting a [Calibration](enum.Calibration.html).
+/// ```
+/// # use rammer::CalibrationMethod;
+/// assert_eq!("isotonic".parse::<CalibrationMethod>(), Ok(CalibrationMethod::Isotonic));
+/// assert_eq!(CalibrationMethod::Platt.to_string(), "platt");
+/// ```
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Copy, Hash, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CalibrationMethod {
+    /// A sigmoid of the log odds, as described by
+    /// [Platt, 1999](https://www.researchgate.net/publication/2594015).
+    #[default]
+    Platt,
+    /// The increasing step function which fits the labels best, found with the pool adjacent
+    /// violators algorithm.
+    Isotonic,
+}
+
+impl fmt::Display for CalibrationMethod {
+    #[allow(missing_doc_code_examples)]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CalibrationMethod::Platt => write!(f, "platt"),
+            CalibrationMethod::Isotonic => write!(f, "isotonic"),
+        }
+    }
+}
+
+impl FromStr for CalibrationMethod {
+    type Err = ();
+
+    #[allow(missing_doc_code_examples)]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "platt" => Ok(CalibrationMethod::Platt),
+            "isotonic" => Ok(CalibrationMethod::Isotonic),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A fitted mapping from log odds to calibrated spam probabilities, saved with a model in its
+/// [ClassifierConfig](struct.ClassifierConfig.html). Saved calibrations with non-finite
+/// parameters, or isotonic knots which are empty, out of order or outside 0 to 1, are rejected
+/// when they are read.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "method", rename_all = "lowercase", try_from = "CalibrationFile")]
+pub enum Calibration {
+    /// The probability is logistic(slope * log odds + intercept).
+    Platt {
+        /// How much each unit of log odds counts. Below 1 for an over-confident model.
+        slope: f64,
+        /// Log odds of a document the model scored 0.5.
+        intercept: f64,
+    },
+    /// The probability is interpolated linearly between knots, and constant beyond the first and
+    /// last.
+    Isotonic {
+        /// Log odds of each knot, in increasing order.
+        log_odds: Vec<f64>,
+        /// Calibrated probability at each knot, never decreasing.
+        probabilities: Vec<Probability>,
+    },
+}
+
+/// The saved fields of a Calibration, before they are checked.
+#[derive(Deserialize)]
+#[serde(tag = "method", rename_all = "lowercase")]
+enum CalibrationFile {
+    Platt {
+        slope: f64,
+        intercept: f64,
+    },
+    Isotonic {
+        log_odds: Vec<f64>,
+        probabilities: Vec<Probability>,
+    },
+}
+
+impl TryFrom<CalibrationFile> for Calibration {
+    type Error = String;
+
+    #[allow(missing_doc_code_examples)]
+    fn try_from(file: CalibrationFile) -> Result<Self, Self::Error> {
+        match file {
+            CalibrationFile::Platt { slope, intercept } => {
+                if !(slope.is_finite() && intercept.is_finite()) {
+                    return Err("platt calibration parameters must be finite".to_string());
+                }
+                Ok(Calibration::Platt { slope, intercept })
+            }
+            CalibrationFile::Isotonic {
+                log_odds,
+                probabilities,
+            } => {
+                if log_odds.is_empty() || log_odds.len() != probabilities.len() {
+                    return Err(
+                        "isotonic calibration needs as many probabilities as knots, and at least one"
+                            .to_string(),
+                    );
+                }
+                let increasing = log_odds.iter().all(|x| x.is_finite())
+                    && log_odds.windows(2).all(|w| w[0] < w[1]);
+                if !increasing {
+                    return Err("isotonic knots must be finite and strictly increasing".to_string());
+                }
+                let probable = probabilities.iter().all(|p| (0.0..=1.0).contains(p))
+                    && probabilities.windows(2).all(|w| w[0] <= w[1]);
+                if !probable {
+                    return Err(
+                        "isotonic probabilities must be between 0 and 1 and never decrease"
+                            .to_string(),
+                    );
+                }
+                Ok(Calibration::Isotonic {
+                    log_odds,
+                    probabilities,
+                })
+            }
+        }
+    }
+}
+
+#[allow(missing_doc_code_examples)]
+impl Calibration {
+    /// Fit a calibration with method to documents which have already been scored, usually held
+    /// out from training. Returns None unless scored contains both ham and spam.
+    pub fn fit(method: CalibrationMethod, scored: &[ScoredDocument]) -> Option<Self> {
+        let spam = scored.iter().filter(|d| d.label == Label::Spam).count();
+        if spam == 0 || spam == scored.len() {
+            return None;
+        }
+        Some(match method {
+            CalibrationMethod::Platt => fit_platt(scored),
+            CalibrationMethod::Isotonic => fit_isotonic(scored),
+        })
+    }
+
+    /// Fit a calibration with method to some of the scored documents, holding out held_out of
+    /// each label, chosen at random with seed, to measure it on. A calibration measured on the
+    /// documents it was fitted to looks better than it is, isotonic ones most of all. Returns the
+    /// calibration and the held out documents, or None unless held_out is between 0 and 1 and
+    /// there are at least 2 documents of each label.
+    /// ```
+    /// # use rammer::{Calibration, CalibrationMethod, Label, ScoredDocument};
+    /// let doc = |label, log_odds| ScoredDocument { path: "x".into(), label, probability: 0.0, log_odds };
+    /// let scored: Vec<ScoredDocument> = (0..10)
+    ///     .map(|i| doc(if i % 2 == 0 { Label::Ham } else { Label::Spam }, i as f64 - 5.0))
+    ///     .collect();
+    /// let (calibration, held_out) = Calibration::fit_held_out(CalibrationMethod::Platt, &scored, 0.4, 0).unwrap();
+    /// assert_eq!(held_out.len(), 4);
+    /// assert!(Calibration::fit_held_out(CalibrationMethod::Platt, &scored[..3], 0.4, 0).is_none());
+    /// ```
+    pub fn fit_held_out(
+        method: CalibrationMethod,
+        scored: &[ScoredDocument],
+        held_out: f64,
+        seed: u64,
+    ) -> Option<(Self, Vec<ScoredDocument>)> {
+        if !(held_out > 0.0 && held_out < 1.0) {
+            return None;
+        }
+        let mut rng = SplitMix64::new(seed);
+        let (fit, held_out) = stratified_split(scored.to_vec(), |d| d.label, held_out, &mut rng)?;
+        Some((Calibration::fit(method, &fit)?, held_out))
+    }
+
+    /// The method the calibration was fitted with.
+    pub fn method(&self) -> CalibrationMethod {
+        match self {
+            Calibration::Platt { .. } => CalibrationMethod::Platt,
+            Calibration::Isotonic { .. } => CalibrationMethod::Isotonic,
+        }
+    }
+
+    /// The calibrated spam probability of a document with the given log odds.
+    /// ```
+    /// # use rammer::Calibration;
+    /// let calibration = Calibration::Platt { slope: 0.5, intercept: 0.0 };
+    /// assert_eq!(calibration.probability(0.0), 0.5);
+    /// let isotonic = Calibration::Isotonic { log_odds: vec![-1.0, 1.0], probabilities: vec![0.2, 0.6] };
+    /// assert_eq!(isotonic.probability(-5.0), 0.2);
+    /// assert!((isotonic.probability(0.0) - 0.4).abs() < 1e-12);
+    /// ```
+    pub fn probability(&self, log_odds: f64) -> Probability {
+        match self {
+            Calibration::Platt { slope, intercept } => logistic(slope * log_odds + intercept),
+            Calibration::Isotonic {
+                log_odds: knots,
+                probabilities,
+            } => {
+                // Calibrations read from a file are checked, but ones built in code might not have
+                // a probability for every knot, so nothing is indexed directly.
+                let after = knots.partition_point(|&x| x <= log_odds);
+                let knot = |i: usize| Some((*knots.get(i)?, *probabilities.get(i)?));
+                match (after.checked_sub(1).and_then(knot), knot(after)) {
+                    (Some((x0, y0)), Some((x1, y1))) => {
+                        y0 + (y1 - y0) * (log_odds - x0) / (x1 - x0)
+                    }
+                    (Some((_, y)), None) | (None, Some((_, y))) => y,
+                    (None, None) => probabilities.last().copied().unwrap_or(0.5),
+                }
+            }
+        }
+    }
+}
+
+/// ln(1 + e^z) without overflow.
+fn softplus(z: f64) -> f64 {
+    z.max(0.0) + (-z.abs()).exp().ln_1p()
+}
+
+/// Fit a sigmoid by Newton's method with a backtracking line search, following
+/// [Lin, Lin and Weng, 2007](https://www.csie.ntu.edu.tw/~cjlin/papers/plattprob.pdf). Labels are
+/// softened to (spam + 1) / (spam + 2) and 1 / (ham + 2), as Platt did, so the fit stays finite
+/// when the model separates the documents perfectly.
+fn fit_platt(scored: &[ScoredDocument]) -> Calibration {
+    let spam = scored.iter().filter(|d| d.label == Label::Spam).count() as f64;
+    let ham = scored.len() as f64 - spam;
+    let (high, low) = ((spam + 1.0) / (spam + 2.0), 1.0 / (ham + 2.0));
+    let points: Vec<(f64, f64)> = scored
+        .iter()
+        .map(|d| (d.log_odds, if d.label == Label::Spam { high } else { low }))
+        .collect();
+    let loss = |slope: f64, intercept: f64| -> f64 {
+        points
+            .iter()
+            .map(|&(x, t)| {
+                let z = slope * x + intercept;
+                softplus(z) - t * z
+            })
+            .sum()
+    };
+
+    let (mut slope, mut intercept) = (0.0, ((spam + 1.0) / (ham + 1.0)).ln());
+    let mut current = loss(slope, intercept);
+    for _ in 0..100 {
+        let (mut g_slope, mut g_intercept) = (0.0, 0.0);
+        let (mut h_slope, mut h_cross, mut h_intercept) = (1e-12, 0.0, 1e-12);
+        for &(x, t) in &points {
+            let p = logistic(slope * x + intercept);
+            let w = p * (1.0 - p);
+            g_slope += (p - t) * x;
+            g_intercept += p - t;
+            h_slope += w * x * x;
+            h_cross += w * x;
+            h_intercept += w;
+        }
+        if g_slope.abs() < 1e-5 && g_intercept.abs() < 1e-5 {
+            break;
+        }
+        let determinant = h_slope * h_intercept - h_cross * h_cross;
+        let d_slope = -(h_intercept * g_slope - h_cross * g_intercept) / determinant;
+        let d_intercept = -(h_slope * g_intercept - h_cross * g_slope) / determinant;
+        let decrease = g_slope * d_slope + g_intercept * d_intercept;
+
+        let mut step = 1.0;
+        while step >= 1e-10 {
+            let next = loss(slope + step * d_slope, intercept + step * d_intercept);
+            if next < current + 1e-4 * step * decrease {
+                slope += step * d_slope;
+                intercept += step * d_intercept;
+                current = next;
+                break;
+            }
+            step /= 2.0;
+        }
+        if step < 1e-10 {
+            break;
+        }
+    }
+    Calibration::Platt { slope, intercept }
+}
+
+/// Fit an increasing step function with the pool adjacent violators algorithm. Documents with
+/// equal log odds are pooled first, then neighbouring blocks are merged while a block's spam
+/// fraction is below the one before it. Each block becomes a knot at its lowest and highest log
+/// odds.
+fn fit_isotonic(scored: &[ScoredDocument]) -> Calibration {
+    let mut points: Vec<(f64, f64)> = scored
+        .iter()
+        .map(|d| (d.log_odds, if d.label == Label::Spam { 1.0 } else { 0.0 }))
+        .collect();
+    points.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    // Each block is its lowest and highest log odds, sum of labels and number of documents.
+    let mut blocks: Vec<(f64, f64, f64, f64)> = Vec::new();
+    for (x, y) in points {
+        match blocks.last_mut() {
+            Some(last) if last.1 == x => {
+                last.2 += y;
+                last.3 += 1.0;
+            }
+            _ => blocks.push((x, x, y, 1.0)),
+        }
+        while blocks.len() > 1 {
+            let (second, last) = (blocks[blocks.len() - 2], blocks[blocks.len() - 1]);
+            if second.2 / second.3 < last.2 / last.3 {
+                break;
+            }
+            blocks.pop();
+            let merged = blocks.last_mut().unwrap();
+            merged.1 = last.1;
+            merged.2 += last.2;
+            merged.3 += last.3;
+        }
+    }
+
+    let (mut log_odds, mut probabilities) = (Vec::new(), Vec::new());
+    for (low, high, sum, count) in blocks {
+        let p = sum / count;
+        log_odds.push(low);
+        probabilities.push(p);
+        if high > low {
+            log_odds.push(high);
+            probabilities.push(p);
+        }
+    }
+    Calibration::Isotonic {
+        log_odds,
+        probabilities,
+    }
+}
+
+/// Number of equal width bins [EvaluationReport](struct.EvaluationReport.html) divides the
+/// probability range into for its reliability diagram.
+pub const RELIABILITY_BINS: usize = 10;
+
+/// One bar of a reliability diagram: the documents given probabilities within a range, how
+/// likely the model said they were to be spam and how many were.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct ReliabilityBin {
+    /// Lowest probability in the bin.
+    pub lower: Probability,
+    /// Highest probability in the bin. Only the last bin includes it.
+    pub upper: Probability,
+    /// Number of documents in the bin.
+    pub documents: usize,
+    /// Mean probability the model gave the documents, 0 for an empty bin.
+    pub mean_probability: Probability,
+    /// Fraction of the documents which are spam, 0 for an empty bin.
+    pub spam_fraction: f64,
+}
+
+#[allow(missing_doc_code_examples)]
+impl ReliabilityBin {
+    /// Divide scored documents into bins equal width bins by probability, keeping empty bins so
+    /// every diagram has the same bars.
+    /// ```
+    /// # use rammer::{Label, ReliabilityBin, ScoredDocument};
+    /// let doc = |label, probability| ScoredDocument { path: "x".into(), label, probability, log_odds: 0.0 };
+    /// let bins = ReliabilityBin::from_scores(&[doc(Label::Spam, 0.9), doc(Label::Ham, 1.0)], 2);
+    /// assert_eq!((bins[0].documents, bins[1].documents), (0, 2));
+    /// assert_eq!(bins[1].spam_fraction, 0.5);
+    /// ```
+    pub fn from_scores(scored: &[ScoredDocument], bins: usize) -> Vec<Self> {
+        let mut sums = vec![(0, 0.0, 0); bins];
+        for d in scored {
+            let i = ((d.probability * bins as f64) as usize).min(bins.saturating_sub(1));
+            if let Some(sum) = sums.get_mut(i) {
+                sum.0 += 1;
+                sum.1 += d.probability;
+                sum.2 += (d.label == Label::Spam) as usize;
+            }
+        }
+        sums.into_iter()
+            .enumerate()
+            .map(|(i, (documents, probabilities, spam))| {
+                let mean = |total: f64| {
+                    if documents > 0 {
+                        total / documents as f64
+                    } else {
+                        0.0
+                    }
+                };
+                ReliabilityBin {
+                    lower: i as f64 / bins as f64,
+                    upper: (i + 1) as f64 / bins as f64,
+                    documents,
+                    mean_probability: mean(probabilities),
+                    spam_fraction: mean(spam as f64),
+                }
+            })
+            .collect()
+    }
+
+    /// Expected calibration error of a reliability diagram: the gap between the mean probability
+    /// and the spam fraction of each bin, weighted by its share of the documents.
+    /// ```
+    /// # use rammer::{Label, ReliabilityBin, ScoredDocument};
+    /// let doc = |label, probability| ScoredDocument { path: "x".into(), label, probability, log_odds: 0.0 };
+    /// let bins = ReliabilityBin::from_scores(&[doc(Label::Spam, 0.75), doc(Label::Ham, 0.75)], 4);
+    /// assert_eq!(ReliabilityBin::calibration_error(&bins), 0.25);
+    /// ```
+    pub fn calibration_error(bins: &[ReliabilityBin]) -> f64 {
+        let documents: usize = bins.iter().map(|b| b.documents).sum();
+        if documents == 0 {
+            return 0.0;
+        }
+        bins.iter()
+            .map(|b| b.documents as f64 * (b.mean_probability - b.spam_fraction).abs())
+            .sum::<f64>()
+            / documents as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc(label: Label, log_odds: f64) -> ScoredDocument {
+        ScoredDocument {
+            path: "x".into(),
+            label,
+            probability: logistic(log_odds),
+            log_odds,
+        }
+    }
+
+    #[test]
+    fn platt_recovers_a_sigmoid() {
+        // Documents at each log odds are spam in proportion to logistic(0.5 * x - 1).
+        let mut scored = Vec::new();
+        for x in -10..=10 {
+            let x = x as f64;
+            let spam = (logistic(0.5 * x - 1.0) * 1000.0).round() as usize;
+            scored.extend((0..spam).map(|_| doc(Label::Spam, x)));
+            scored.extend((spam..1000).map(|_| doc(Label::Ham, x)));
+        }
+        match Calibration::fit(CalibrationMethod::Platt, &scored).unwrap() {
+            Calibration::Platt { slope, intercept } => {
+                assert!((slope - 0.5).abs() < 0.01, "{}", slope);
+                assert!((intercept + 1.0).abs() < 0.01, "{}", intercept);
+            }
+            other => panic!("{:?}", other),
+        }
+        // Perfectly separated documents still give a finite, increasing fit.
+        let separated = vec![doc(Label::Ham, -3.0), doc(Label::Spam, 3.0)];
+        let calibration = Calibration::fit(CalibrationMethod::Platt, &separated).unwrap();
+        assert!(calibration.probability(3.0) > calibration.probability(-3.0));
+        assert!(calibration.probability(1e6) <= 1.0);
+    }
+
+    #[test]
+    fn isotonic_pools_violators() {
+        let scored = vec![
+            doc(Label::Ham, -2.0),
+            doc(Label::Spam, -1.0),
+            doc(Label::Ham, 0.0),
+            doc(Label::Spam, 1.0),
+            doc(Label::Spam, 2.0),
+        ];
+        let calibration = Calibration::fit(CalibrationMethod::Isotonic, &scored).unwrap();
+        assert_eq!(
+            calibration,
+            Calibration::Isotonic {
+                log_odds: vec![-2.0, -1.0, 0.0, 1.0, 2.0],
+                probabilities: vec![0.0, 0.5, 0.5, 1.0, 1.0],
+            }
+        );
+        assert_eq!(calibration.probability(-0.5), 0.5);
+        assert_eq!(calibration.probability(0.5), 0.75);
+        assert_eq!(calibration.method(), CalibrationMethod::Isotonic);
+        assert!(Calibration::fit(CalibrationMethod::Isotonic, &scored[..1]).is_none());
+    }
+
+    #[test]
+    fn invalid_calibrations_are_rejected() {
+        let rejected = |json: &str| serde_json::from_str::<Calibration>(json).is_err();
+        let isotonic = |log_odds: &str, probabilities: &str| {
+            format!(
+                r#"{{"method":"isotonic","log_odds":{},"probabilities":{}}}"#,
+                log_odds, probabilities
+            )
+        };
+        assert!(!rejected(&isotonic("[-1.0,1.0]", "[0.2,0.6]")));
+        assert!(rejected(&isotonic("[-1.0,1.0]", "[0.2]")));
+        assert!(rejected(&isotonic("[]", "[]")));
+        assert!(rejected(&isotonic("[1.0,1.0]", "[0.2,0.6]")));
+        assert!(rejected(&isotonic("[1.0,-1.0]", "[0.2,0.6]")));
+        assert!(rejected(&isotonic("[-1.0,1.0]", "[0.2,1.5]")));
+        assert!(rejected(&isotonic("[-1.0,1.0]", "[0.6,0.2]")));
+        assert!(!rejected(
+            r#"{"method":"platt","slope":0.5,"intercept":0.0}"#
+        ));
+
+        // Calibrations built in code aren't checked, but still can't panic.
+        let mismatched = Calibration::Isotonic {
+            log_odds: vec![-1.0, 1.0, 2.0],
+            probabilities: vec![0.2],
+        };
+        for &log_odds in &[-5.0, 0.0, 1.5, 5.0] {
+            assert_eq!(mismatched.probability(log_odds), 0.2);
+        }
+    }
+
+    #[test]
+    fn held_out_documents_are_not_fitted() {
+        let scored: Vec<ScoredDocument> = (0..40)
+            .map(|i| {
+                let label = if i < 10 { Label::Spam } else { Label::Ham };
+                let mut d = doc(label, i as f64);
+                d.path = i.to_string().into();
+                d
+            })
+            .collect();
+        let (_, held_out) =
+            Calibration::fit_held_out(CalibrationMethod::Isotonic, &scored, 0.25, 7).unwrap();
+        // A quarter of each label, however unbalanced the labels are.
+        let count = |label| held_out.iter().filter(|d| d.label == label).count();
+        assert_eq!((count(Label::Spam), count(Label::Ham)), (3, 8));
+        let (_, again) =
+            Calibration::fit_held_out(CalibrationMethod::Isotonic, &scored, 0.25, 7).unwrap();
+        assert_eq!(held_out, again);
+        assert!(Calibration::fit_held_out(CalibrationMethod::Platt, &scored, 1.0, 7).is_none());
+    }
+
+    #[test]
+    fn reliability_bins_cover_every_document() {
+        let scored: Vec<ScoredDocument> = (0..=20)
+            .map(|i| ScoredDocument {
+                path: "x".into(),
+                label: if i % 2 == 0 { Label::Spam } else { Label::Ham },
+                probability: i as f64 / 20.0,
+                log_odds: 0.0,
+            })
+            .collect();
+        let bins = ReliabilityBin::from_scores(&scored, RELIABILITY_BINS);
+        assert_eq!(bins.len(), RELIABILITY_BINS);
+        assert_eq!(bins.iter().map(|b| b.documents).sum::<usize>(), 21);
+        // 0.95 and 1.0 both land in the last bin.
+        assert_eq!(bins[9].documents, 3);
+        assert!((bins[9].mean_probability - 2.85 / 3.0).abs() < 1e-12);
+        assert_eq!((bins[0].lower, bins[9].upper), (0.0, 1.0));
+    }
+}
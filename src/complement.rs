@@ -0,0 +1,214 @@
+//! Complement naive Bayes scoring, for training data with far more of one class than the other.
+//! Multinomial scoring estimates the spam weights of a token from the spam corpus alone, so with
+//! four times as much ham the ham estimates are much better and scores lean towards ham.
+//! Complement naive Bayes estimates each class's weights from every other class instead, which for
+//! two classes means spam is scored with weights learned from ham, and ham with weights from spam.
+//!
+//! Following [Rennie et al., 2003](https://people.csail.mit.edu/jrennie/papers/icml03-nb.pdf),
+//! each class's Laplace smoothed log probabilities are divided by their mean absolute value over
+//! the vocabulary, so neither class's weights are larger just because its corpus is. The paper
+//! divides by the sum instead, which differs only by the vocabulary size shared by both classes,
+//! so the classifications are the same but a text's log odds grow with its length as multinomial
+//! scores do. Tokens seen in only one class count, and tokens never seen are ignored. The means
+//! take a pass over the whole vocabulary, so score many texts with a
+//! [scorer](struct.HSModel.html#method.scorer), which works them out once.
+//! ```
+//! use rammer::{BagOfWords, HSModel, Scoring};
+//! let model = HSModel::from_bows(
+//!     BagOfWords::from("hello lunch meeting report hello lunch meeting report"),
+//!     BagOfWords::from("free offer"),
+//! )
+//! .with_scoring(Scoring::Complement);
+//! assert!(model.text_spam_probability("free offer") > 0.5);
+//! ```
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{Count, Frequency, HSModel, TokenCounts};
+
+/// Normalizing constants of a model's complement weights. They depend on every token in the
+/// model, so they are worked out once and reused for every text scored.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub(crate) struct ComplementWeights {
+    /// ln of the smoothed ham and spam totals, the vocabulary size plus the total count.
+    log_totals: (f64, f64),
+    /// Mean absolute log probability of a token in ham and in spam.
+    means: (f64, f64),
+}
+
+impl ComplementWeights {
+    /// Work out the weights of model from the counts of both its BagOfWords.
+    pub(crate) fn for_model(model: &HSModel) -> Self {
+        let ham_tokens = model
+            .ham_bow
+            .tokens()
+            .map(|(token, ham)| (ham, model.spam_bow.token_count(token).unwrap_or(0)));
+        let spam_only = model
+            .spam_bow
+            .tokens()
+            .filter(|(token, _)| model.ham_bow.token_count(token).is_none())
+            .map(|(_, spam)| (0, spam));
+        Self::new(ham_tokens.chain(spam_only), model.totals())
+    }
+
+    /// Work out the weights from the ham and spam count of every token in a model, and the
+    /// model's totals.
+    pub(crate) fn new<I: IntoIterator<Item = (Count, Count)>>(
+        counts: I,
+        (ham_total, spam_total): (Count, Count),
+    ) -> Self {
+        let (mut vocabulary, mut ham_logs, mut spam_logs) = (0.0, 0.0, 0.0);
+        for (ham, spam) in counts {
+            if ham == 0 && spam == 0 {
+                continue;
+            }
+            vocabulary += 1.0;
+            ham_logs += Frequency::ln_1p(ham as Frequency);
+            spam_logs += Frequency::ln_1p(spam as Frequency);
+        }
+        let log_totals = (
+            Frequency::ln(vocabulary + ham_total as Frequency),
+            Frequency::ln(vocabulary + spam_total as Frequency),
+        );
+        // The smoothed probability of a token is (1 + count) / (vocabulary + total), so the sum of
+        // the absolute log probabilities is vocabulary * ln(vocabulary + total) minus the sum of
+        // ln(1 + count), which saves a second pass over the tokens.
+        let mean = |log_total: f64, logs: f64| {
+            if vocabulary > 0.0 {
+                log_total - logs / vocabulary
+            } else {
+                0.0
+            }
+        };
+        ComplementWeights {
+            log_totals,
+            means: (mean(log_totals.0, ham_logs), mean(log_totals.1, spam_logs)),
+        }
+    }
+
+    /// Log odds that a token seen ham and spam times in training adds to a text, positive towards
+    /// spam, or None if it was never seen.
+    pub(crate) fn token_log_odds(&self, ham: Count, spam: Count) -> Option<f64> {
+        if ham == 0 && spam == 0 {
+            return None;
+        }
+        let normalized = |log_total: f64, count: Count, mean: f64| {
+            if mean > 0.0 {
+                (log_total - Frequency::ln_1p(count as Frequency)) / mean
+            } else {
+                0.0
+            }
+        };
+        // Spam is scored with weights from ham, its complement, so a token which is rare in ham
+        // pushes towards spam, and the other way around.
+        let spam_score = normalized(self.log_totals.0, ham, self.means.0);
+        let ham_score = normalized(self.log_totals.1, spam, self.means.1);
+        Some(spam_score - ham_score)
+    }
+
+    /// Log odds that a slice of text is spam, with counts looked up in model.
+    pub(crate) fn text_log_odds<C: TokenCounts + ?Sized>(&self, model: &C, text: &str) -> f64 {
+        self.log_odds(
+            model,
+            text.to_uppercase()
+                .split_word_bounds()
+                .filter(|&s| !s.trim().is_empty())
+                .map(|word| (word, 1)),
+        )
+    }
+
+    /// Log odds that text made of tokens, each with its number of occurrences, is spam, with
+    /// counts looked up in model.
+    pub(crate) fn log_odds<'t, C, I>(&self, model: &C, tokens: I) -> f64
+    where
+        C: TokenCounts + ?Sized,
+        I: Iterator<Item = (&'t str, Count)>,
+    {
+        tokens
+            .filter_map(|(token, occurrences)| {
+                let (ham, spam) = model.counts(token);
+                self.token_log_odds(ham, spam)
+                    .map(|w| occurrences as f64 * w)
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BagOfWords, Scoring};
+
+    #[test]
+    fn weights_match_the_definition() {
+        // Vocabulary A, B and C, with ham counts 3, 1, 0 and spam counts 0, 1, 2.
+        let model = HSModel::from_bows(BagOfWords::from("a a a b"), BagOfWords::from("b c c"))
+            .with_scoring(Scoring::Complement);
+        let weights = ComplementWeights::for_model(&model);
+        let theta_ham = [4.0 / 7.0, 2.0 / 7.0, 1.0 / 7.0];
+        let theta_spam = [1.0 / 6.0, 2.0 / 6.0, 3.0 / 6.0];
+        let mean = |theta: &[f64; 3]| theta.iter().map(|t: &f64| -t.ln()).sum::<f64>() / 3.0;
+        let (ham_mean, spam_mean) = (mean(&theta_ham), mean(&theta_spam));
+        let counts = [(3, 0), (1, 1), (0, 2)];
+        for (i, &(ham, spam)) in counts.iter().enumerate() {
+            let expected = -theta_ham[i].ln() / ham_mean + theta_spam[i].ln() / spam_mean;
+            let w = weights.token_log_odds(ham, spam).unwrap();
+            assert!((w - expected).abs() < 1e-12);
+        }
+        assert_eq!(weights.token_log_odds(0, 0), None);
+    }
+
+    #[test]
+    fn text_and_bow_scores_agree() {
+        let model = HSModel::from_bows(
+            BagOfWords::from("hello there, lunch at noon? hello"),
+            BagOfWords::from("free offer, click now! free"),
+        )
+        .with_scoring(Scoring::Complement);
+        for text in &[
+            "hello free offer",
+            "free free click",
+            "",
+            "unknown words only",
+        ] {
+            let from_text = model.text_spam_probability(text);
+            let from_bow = model.bow_spam_probability(&BagOfWords::from(*text));
+            assert!((from_text - from_bow).abs() < 1e-9, "{}", text);
+        }
+        assert_eq!(model.text_spam_probability("unknown words only"), 0.5);
+    }
+
+    #[test]
+    fn scorer_matches_the_model() {
+        let model = HSModel::from_bows(
+            BagOfWords::from("hello there, lunch at noon? hello"),
+            BagOfWords::from("free offer, click now! free"),
+        )
+        .with_scoring(Scoring::Complement);
+        let scorer = model.scorer();
+        for text in &["hello free offer", "free free click", "lunch", ""] {
+            assert_eq!(scorer.text_log_odds(text), model.text_log_odds(text));
+            assert_eq!(scorer.classify(text), model.classify(text));
+            assert_eq!(scorer.explain(text), model.explain(text));
+            let bow = BagOfWords::from(*text);
+            assert_eq!(
+                scorer.bow_spam_probability(&bow),
+                model.bow_spam_probability(&bow)
+            );
+        }
+    }
+
+    #[test]
+    fn complement_is_less_biased_towards_the_larger_class() {
+        // Ham is four times the size of spam, and MEETING is as common in spam as in ham,
+        // relative to each corpus's size.
+        let ham = BagOfWords::from(&"hello lunch report meeting ".repeat(4)[..]);
+        let spam = BagOfWords::from("free offer click meeting");
+        let multinomial = HSModel::from_bows(ham.clone(), spam.clone());
+        let complement = HSModel::from_bows(ham, spam).with_scoring(Scoring::Complement);
+        // A spam message which shares one word with ham and introduces a new spammy one.
+        let text = "meeting offer winner";
+        assert_eq!(multinomial.text_spam_probability(text), 0.5);
+        assert!(complement.text_spam_probability(text) > 0.5);
+        assert!(complement.text_spam_probability("hello lunch") < 0.5);
+    }
+}
@@ -0,0 +1,249 @@
+//! A Corpus is a collection of documents on disk whose class, spam or ham, is already known.
+//! Corpora are used to train new models and to measure how well an existing model performs.
+//! ```no_run
+//! use rammer::{Corpus, HSModel};
+//! let corpus = Corpus::from_folders("data/train/ham", "data/train/spam").expect("Folder not found");
+//! let (ham_bow, spam_bow) = corpus.bows();
+//! let model = HSModel::from_bows(ham_bow, spam_bow);
+//! ```
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::BagOfWords;
+
+/// The known class of a document.
+/// ```
+/// # use rammer::Label;
+/// assert_eq!(Label::Spam.to_string(), "spam");
+/// ```
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Label {
+    /// Text which is not spam.
+    Ham,
+    /// Unsolicited or unwanted text.
+    Spam,
+}
+
+impl fmt::Display for Label {
+    #[allow(missing_doc_code_examples)]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Label::Ham => write!(f, "ham"),
+            Label::Spam => write!(f, "spam"),
+        }
+    }
+}
+
+/// A single document on disk and its known Label.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Document {
+    /// Location of the document's text.
+    pub path: PathBuf,
+    /// The known class of the document.
+    pub label: Label,
+}
+
+/// A readable document from a Corpus, tokenized once so it can be trained on and scored many
+/// times.
+pub(crate) struct TokenizedDocument {
+    pub(crate) path: PathBuf,
+    pub(crate) label: Label,
+    pub(crate) bow: BagOfWords,
+}
+
+impl Document {
+    /// Read the document's text from disk.
+    /// This will return None if the file can not be read as UTF-8 text.
+    /// ```no_run
+    /// # use rammer::{Document, Label};
+    /// let doc = Document { path: "data/train/spam/1.txt".into(), label: Label::Spam };
+    /// let text = doc.text().expect("File not found");
+    /// ```
+    pub fn text(&self) -> Option<String> {
+        fs::read_to_string(&self.path).ok()
+    }
+}
+
+/// A collection of labelled documents.
+/// Documents are kept sorted by path, so a Corpus built from the same folders always lists its
+/// documents in the same order.
+/// ```
+/// # use rammer::{Corpus, Label};
+/// let corpus = Corpus::new()
+///     .add_folder("test_resources/test_data", Label::Ham)
+///     .expect("Folder not found");
+/// assert_eq!(corpus.len(), 3);
+/// ```
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct Corpus {
+    /// The documents in this corpus, sorted by path.
+    pub documents: Vec<Document>,
+}
+
+#[allow(missing_doc_code_examples)]
+impl Corpus {
+    /// Create a new empty Corpus.
+    /// ```
+    /// # use rammer::Corpus;
+    /// let corpus = Corpus::new();
+    /// assert!(corpus.is_empty());
+    /// ```
+    pub fn new() -> Self {
+        Corpus {
+            documents: Vec::new(),
+        }
+    }
+
+    /// Create a Corpus from a folder of ham text files and a folder of spam text files.
+    /// ```no_run
+    /// # use rammer::Corpus;
+    /// let corpus = Corpus::from_folders("data/validate/ham", "data/validate/spam");
+    /// ```
+    pub fn from_folders(ham_dir: &str, spam_dir: &str) -> Option<Self> {
+        Corpus::new()
+            .add_folder(ham_dir, Label::Ham)?
+            .add_folder(spam_dir, Label::Spam)
+    }
+
+    /// Builder pattern for adding every file in a folder with the given Label.
+    /// Sub folders are skipped. This will return None if the folder can not be read.
+    /// ```
+    /// # use rammer::{Corpus, Label};
+    /// let corpus = Corpus::new().add_folder("test_resources/test_data", Label::Spam);
+    /// ```
+    pub fn add_folder(mut self, dir_path: &str, label: Label) -> Option<Self> {
+        for entry in fs::read_dir(dir_path).ok()? {
+            let path = entry.ok()?.path();
+            if path.is_file() {
+                self.documents.push(Document { path, label });
+            }
+        }
+        self.documents.sort_by(|a, b| a.path.cmp(&b.path));
+        Some(self)
+    }
+
+    /// Builder pattern for adding a single document.
+    /// ```
+    /// # use rammer::{Corpus, Label};
+    /// let corpus = Corpus::new().add_document("test_resources/test_data/ascii_only.txt", Label::Ham);
+    /// assert_eq!(corpus.len(), 1);
+    /// ```
+    pub fn add_document<P: AsRef<Path>>(mut self, path: P, label: Label) -> Self {
+        self.documents.push(Document {
+            path: path.as_ref().to_path_buf(),
+            label,
+        });
+        self.documents.sort_by(|a, b| a.path.cmp(&b.path));
+        self
+    }
+
+    /// Combines two corpora into one.
+    /// ```
+    /// # use rammer::{Corpus, Label};
+    /// let a = Corpus::new().add_document("a.txt", Label::Ham);
+    /// let b = Corpus::new().add_document("b.txt", Label::Spam);
+    /// assert_eq!(a.combine(b).len(), 2);
+    /// ```
+    pub fn combine(mut self, other: Self) -> Self {
+        self.documents.extend(other.documents);
+        self.documents.sort_by(|a, b| a.path.cmp(&b.path));
+        self
+    }
+
+    /// Number of documents in the corpus.
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    /// Returns true if the corpus contains no documents.
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    /// Number of documents in the corpus with the given Label.
+    /// ```
+    /// # use rammer::{Corpus, Label};
+    /// let corpus = Corpus::new().add_document("a.txt", Label::Ham);
+    /// assert_eq!(corpus.count(Label::Ham), 1);
+    /// assert_eq!(corpus.count(Label::Spam), 0);
+    /// ```
+    pub fn count(&self, label: Label) -> usize {
+        self.documents.iter().filter(|d| d.label == label).count()
+    }
+
+    /// Build a ham BagOfWords and a spam BagOfWords from the documents in the corpus.
+    /// Documents which can not be read are skipped, like in
+    /// [from_folder](struct.BagOfWords.html#method.from_folder).
+    /// ```
+    /// # use rammer::{Corpus, HSModel, Label};
+    /// let corpus = Corpus::new().add_document("test_resources/test_data/ascii_only.txt", Label::Ham);
+    /// let (ham_bow, spam_bow) = corpus.bows();
+    /// let model = HSModel::from_bows(ham_bow, spam_bow);
+    /// ```
+    pub fn bows(&self) -> (BagOfWords, BagOfWords) {
+        let bow_for = |label: Label| -> BagOfWords {
+            self.documents
+                .par_iter()
+                .filter(|d| d.label == label)
+                .filter_map(|d| d.text().map(|text| BagOfWords::from(&text[..])))
+                .collect()
+        };
+        (bow_for(Label::Ham), bow_for(Label::Spam))
+    }
+
+    /// Tokenize every readable document, in parallel and in corpus order.
+    pub(crate) fn tokenize(&self) -> Vec<TokenizedDocument> {
+        self.documents
+            .par_iter()
+            .filter_map(|doc| {
+                doc.text().map(|text| TokenizedDocument {
+                    path: doc.path.clone(),
+                    label: doc.label,
+                    bow: BagOfWords::from(&text[..]),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_folders_labels_documents() {
+        let corpus = Corpus::from_folders(
+            "test_resources/test_data",
+            "test_resources/test_maildir/.Junk/cur",
+        )
+        .unwrap();
+        assert_eq!(corpus.count(Label::Ham), 3);
+        assert_eq!(corpus.count(Label::Spam), 1);
+    }
+
+    #[test]
+    fn missing_folder_is_none() {
+        assert!(Corpus::new()
+            .add_folder("test_resources/does_not_exist", Label::Ham)
+            .is_none());
+    }
+
+    #[test]
+    fn bows_match_from_folder() {
+        let corpus = Corpus::new()
+            .add_folder("test_resources/test_data", Label::Spam)
+            .unwrap();
+        let (ham_bow, spam_bow) = corpus.bows();
+        assert_eq!(ham_bow, BagOfWords::new());
+        assert_eq!(
+            spam_bow,
+            BagOfWords::from_folder("test_resources/test_data").unwrap()
+        );
+    }
+}
@@ -0,0 +1,663 @@
+//! An approximate BagOfWords for training from a mail stream that never ends.
+//! [SketchBagOfWords](struct.SketchBagOfWords.html) keeps its counts in a
+//! [count-min sketch](https://en.wikipedia.org/wiki/Count%E2%80%93min_sketch): depth rows of
+//! width counters, each row indexed by its own seeded hash of the token. A token's count is the
+//! smallest of its counters, which never underestimates it, and with width w and depth d
+//! overestimates it by more than e/w of the total count with probability at most e^-d, however
+//! many distinct tokens the stream has. Counts are added with conservative update, which only
+//! raises counters that are below the new estimate, so overestimates are smaller still.
+//!
+//! A sketch can't list its tokens, so it also keeps the heavy hitters, the tokens with the highest
+//! estimated counts. A [SketchModel](struct.SketchModel.html) of a ham and a spam sketch scores
+//! text through [TokenCounts](trait.TokenCounts.html) like [HSModel](struct.HSModel.html).
+//! Streaming enron1 into sketches with delta 0.01, the model classified enron2 the same as the
+//! exact model for 81% of messages with epsilon 0.001 (106 KiB a sketch), 96% with 0.0001
+//! (1 MiB) and every message with 0.00001 (10 MiB).
+//! ```
+//! use rammer::{SketchBagOfWords, SketchModel};
+//! let ham = SketchBagOfWords::with_error(0.001, 0.01, 0).unwrap().add_text("hello lunch offer");
+//! let spam = SketchBagOfWords::with_error(0.001, 0.01, 0).unwrap().add_text("hello offer offer");
+//! assert_eq!(ham.count("hello"), 1);
+//! let model = SketchModel::from_bows(ham, spam);
+//! assert!(model.text_spam_probability("offer") > 0.5);
+//! ```
+use std::{collections::HashMap, convert::TryFrom};
+
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    hashed_bag::bucket, scoring::counts_text_log_odds, BagOfWords, Classification,
+    ClassifierConfig, Count, Probability, Scoring, TokenCounts,
+};
+
+/// Approximate token counts in a count-min sketch, with a list of the most frequent tokens.
+/// Only sketches with the same width, depth and seed can be combined. Deserializing fails unless
+/// the counters make up at least one whole row.
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
+#[serde(try_from = "SketchFile")]
+pub struct SketchBagOfWords {
+    /// Counters in each row.
+    width: usize,
+    /// Seed of the first row's hash, each further row adding 1.
+    seed: u64,
+    /// Every row of counters, one after another.
+    counters: Vec<Count>,
+    /// Exact total count of every token added.
+    total: Count,
+    /// Number of heavy hitters to report.
+    heavy_capacity: usize,
+    /// Candidate heavy hitters and their estimates when last seen, up to twice the capacity so
+    /// they are only sorted once in every capacity new tokens.
+    heavy: HashMap<String, Count>,
+    /// Estimate a new token needs to beat to become a candidate once the list has been trimmed.
+    heavy_floor: Count,
+}
+
+#[allow(missing_doc_code_examples)]
+impl SketchBagOfWords {
+    /// Create an empty sketch of depth rows of width counters, each at least 1, hashed with seed.
+    /// Each counter takes 8 bytes. No heavy hitters are kept until
+    /// [with_heavy_hitters](#method.with_heavy_hitters) is set. Returns None if the counters'
+    /// size in bytes doesn't fit in an isize, the most any allocation can be.
+    /// ```
+    /// # use rammer::SketchBagOfWords;
+    /// let sketch = SketchBagOfWords::new(2048, 4, 0).unwrap();
+    /// assert_eq!((sketch.width(), sketch.depth(), sketch.memory_bytes()), (2048, 4, 65536));
+    /// assert!(SketchBagOfWords::new(usize::MAX, 2, 0).is_none());
+    /// ```
+    pub fn new(width: usize, depth: usize, seed: u64) -> Option<Self> {
+        let width = width.max(1);
+        let counters = counter_count(width, depth.max(1))?;
+        Some(SketchBagOfWords {
+            width,
+            seed,
+            counters: vec![0; counters],
+            total: 0,
+            heavy_capacity: 0,
+            heavy: HashMap::new(),
+            heavy_floor: 0,
+        })
+    }
+
+    /// Create an empty sketch which overestimates a count by more than epsilon times the total
+    /// count with probability at most delta: e/epsilon counters wide and ln(1/delta) rows deep.
+    /// Returns None unless epsilon is positive and delta is between 0 and 1, or if epsilon and
+    /// delta are so small the sketch's size can't be represented, as with
+    /// [new](#method.new).
+    /// ```
+    /// # use rammer::SketchBagOfWords;
+    /// let sketch = SketchBagOfWords::with_error(0.001, 0.01, 0).unwrap();
+    /// assert_eq!((sketch.width(), sketch.depth()), (2719, 5));
+    /// assert!(sketch.epsilon() <= 0.001 && sketch.delta() <= 0.01);
+    /// assert!(SketchBagOfWords::with_error(1e-300, 0.01, 0).is_none());
+    /// ```
+    pub fn with_error(epsilon: f64, delta: Probability, seed: u64) -> Option<Self> {
+        let valid = epsilon > 0.0 && delta > 0.0 && delta < 1.0;
+        if !valid {
+            return None;
+        }
+        let width = (std::f64::consts::E / epsilon).ceil();
+        let depth = (1.0 / delta).ln().ceil();
+        // Casts saturate, so widths past usize::MAX have to be caught before casting.
+        let representable = width < usize::MAX as f64 && depth < usize::MAX as f64;
+        if !representable {
+            return None;
+        }
+        Self::new(width as usize, depth as usize, seed)
+    }
+
+    /// Builder pattern for keeping the k tokens with the highest estimated counts. Up to 2k
+    /// candidates are kept while counting.
+    /// ```
+    /// # use rammer::SketchBagOfWords;
+    /// let sketch = SketchBagOfWords::new(1024, 4, 0).unwrap()
+    ///     .with_heavy_hitters(1)
+    ///     .add_text("offer free offer");
+    /// assert_eq!(sketch.heavy_hitters(), vec![("OFFER", 2)]);
+    /// ```
+    pub fn with_heavy_hitters(mut self, k: usize) -> Self {
+        self.heavy_capacity = k;
+        self.trim_heavy();
+        self
+    }
+
+    /// Builder pattern for counting every token of a slice of text, split and uppercased the
+    /// same way as [BagOfWords::from](struct.BagOfWords.html#impl-From%3C%26str%3E).
+    /// ```
+    /// # use rammer::SketchBagOfWords;
+    /// let sketch = SketchBagOfWords::new(1024, 4, 0).unwrap().add_text("Offer").add_text("offer");
+    /// assert_eq!(sketch.count("OFFER"), 2);
+    /// ```
+    pub fn add_text(mut self, text: &str) -> Self {
+        for word in text.split_word_bounds().filter(|&s| !s.trim().is_empty()) {
+            self.add(&word.to_uppercase(), 1);
+        }
+        self
+    }
+
+    /// Builder pattern for counting every token of an exact BagOfWords.
+    /// ```
+    /// # use rammer::{BagOfWords, SketchBagOfWords};
+    /// let bow = BagOfWords::from("hello hello offer");
+    /// let sketch = SketchBagOfWords::new(1024, 4, 0).unwrap().add_bow(&bow);
+    /// assert_eq!(sketch.total_word_count(), bow.total_word_count());
+    /// ```
+    pub fn add_bow(mut self, bow: &BagOfWords) -> Self {
+        for (token, count) in bow.tokens() {
+            self.add(token, count);
+        }
+        self
+    }
+
+    /// Add the counts of other to this sketch, like
+    /// [BagOfWords::combine](struct.BagOfWords.html#method.combine), keeping the larger number of
+    /// heavy hitters. Counters are summed, so the result never underestimates and keeps the error
+    /// bound of its total count. Returns None if the sketches have different widths, depths or
+    /// seeds.
+    /// ```
+    /// # use rammer::SketchBagOfWords;
+    /// let a = SketchBagOfWords::new(1024, 4, 0).unwrap().add_text("hello");
+    /// let b = SketchBagOfWords::new(1024, 4, 0).unwrap().add_text("hello offer");
+    /// assert_eq!(a.clone().combine(b).unwrap().count("hello"), 2);
+    /// assert!(a.combine(SketchBagOfWords::new(1024, 3, 0).unwrap()).is_none());
+    /// ```
+    pub fn combine(mut self, other: Self) -> Option<Self> {
+        if self.width != other.width
+            || self.seed != other.seed
+            || self.counters.len() != other.counters.len()
+        {
+            return None;
+        }
+        for (counter, other) in self.counters.iter_mut().zip(other.counters) {
+            *counter = counter.saturating_add(other);
+        }
+        self.total = self.total.saturating_add(other.total);
+        self.heavy_capacity = self.heavy_capacity.max(other.heavy_capacity);
+        self.heavy.extend(other.heavy);
+        let tokens: Vec<String> = self.heavy.keys().cloned().collect();
+        for token in tokens {
+            let estimate = self.token_count(&token);
+            self.heavy.insert(token, estimate);
+        }
+        self.trim_heavy();
+        Some(self)
+    }
+
+    /// Estimated count of word, which is uppercased first. Never less than the true count.
+    pub fn count(&self, word: &str) -> Count {
+        self.token_count(&word.to_uppercase())
+    }
+
+    /// Exact total count of every token added.
+    pub fn total_word_count(&self) -> Count {
+        self.total
+    }
+
+    /// Counters in each row.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of rows.
+    pub fn depth(&self) -> usize {
+        self.counters.len() / self.width
+    }
+
+    /// Seed the first row's hash uses.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Fraction of the total count a count is overestimated by with probability at most
+    /// [delta](#method.delta), e / width.
+    pub fn epsilon(&self) -> f64 {
+        std::f64::consts::E / self.width as f64
+    }
+
+    /// Probability that a count is overestimated by more than [epsilon](#method.epsilon) of the
+    /// total count, e^-depth.
+    pub fn delta(&self) -> Probability {
+        (-(self.depth() as f64)).exp()
+    }
+
+    /// Most a count is overestimated by with probability at least 1 - delta, epsilon times the
+    /// total count.
+    pub fn error_bound(&self) -> Count {
+        (self.epsilon() * self.total as f64).ceil() as Count
+    }
+
+    /// Bytes taken by the counters, which never grows as tokens are added.
+    pub fn memory_bytes(&self) -> usize {
+        self.counters.len() * std::mem::size_of::<Count>()
+    }
+
+    /// The heavy hitters with their current estimated counts, highest first, ties broken
+    /// alphabetically.
+    pub fn heavy_hitters(&self) -> Vec<(&str, Count)> {
+        let mut heavy: Vec<(&str, Count)> = self
+            .heavy
+            .keys()
+            .map(|token| (&token[..], self.token_count(token)))
+            .collect();
+        heavy.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        heavy.truncate(self.heavy_capacity);
+        heavy
+    }
+
+    /// Estimated count of an already uppercased token, the smallest of its counters.
+    pub(crate) fn token_count(&self, token: &str) -> Count {
+        (0..self.depth())
+            .map(|row| self.counters[self.index(token, row)])
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// Index into the counters of token's counter in row.
+    fn index(&self, token: &str, row: usize) -> usize {
+        row * self.width + bucket(token, self.seed.wrapping_add(row as u64), self.width)
+    }
+
+    /// Add count to token with conservative update: every counter below the token's new
+    /// estimate is raised to it, and the rest are left alone.
+    fn add(&mut self, token: &str, count: Count) {
+        let estimate = self.token_count(token).saturating_add(count);
+        for row in 0..self.depth() {
+            let i = self.index(token, row);
+            self.counters[i] = self.counters[i].max(estimate);
+        }
+        self.total = self.total.saturating_add(count);
+        if self.heavy_capacity == 0 {
+            return;
+        }
+        if let Some(candidate) = self.heavy.get_mut(token) {
+            *candidate = estimate;
+        } else if estimate > self.heavy_floor {
+            self.heavy.insert(token.to_string(), estimate);
+            if self.heavy.len() >= 2 * self.heavy_capacity {
+                self.trim_heavy();
+            }
+        }
+    }
+
+    /// Keep only the heavy_capacity candidates with the highest estimates, and raise the floor to
+    /// the lowest of them once the list is full.
+    fn trim_heavy(&mut self) {
+        if self.heavy.len() <= self.heavy_capacity {
+            return;
+        }
+        let mut candidates: Vec<(String, Count)> = self.heavy.drain().collect();
+        candidates.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        candidates.truncate(self.heavy_capacity);
+        self.heavy_floor = candidates.last().map_or(0, |&(_, count)| count);
+        self.heavy = candidates.into_iter().collect();
+    }
+}
+
+/// The fields of a SketchBagOfWords as read, before they are checked.
+#[derive(Deserialize)]
+struct SketchFile {
+    width: usize,
+    seed: u64,
+    counters: Vec<Count>,
+    total: Count,
+    heavy_capacity: usize,
+    heavy: HashMap<String, Count>,
+    heavy_floor: Count,
+}
+
+impl TryFrom<SketchFile> for SketchBagOfWords {
+    type Error = String;
+
+    #[allow(missing_doc_code_examples)]
+    fn try_from(file: SketchFile) -> Result<Self, Self::Error> {
+        if file.width == 0
+            || file.counters.is_empty()
+            || !file.counters.len().is_multiple_of(file.width)
+        {
+            return Err(format!(
+                "{} counters don't make whole rows of width {}",
+                file.counters.len(),
+                file.width
+            ));
+        }
+        Ok(SketchBagOfWords {
+            width: file.width,
+            seed: file.seed,
+            counters: file.counters,
+            total: file.total,
+            heavy_capacity: file.heavy_capacity,
+            heavy: file.heavy,
+            heavy_floor: file.heavy_floor,
+        })
+    }
+}
+
+/// Number of counters in depth rows of width, or None if it, or its size in bytes, is too large
+/// to allocate.
+fn counter_count(width: usize, depth: usize) -> Option<usize> {
+    let counters = width.checked_mul(depth)?;
+    let bytes = counters.checked_mul(std::mem::size_of::<Count>())?;
+    if bytes > isize::MAX as usize {
+        return None;
+    }
+    Some(counters)
+}
+
+/// Combine sketches with [combine](struct.SketchBagOfWords.html#method.combine). Gives None for
+/// no sketches, or sketches of different sizes or seeds.
+/// ```
+/// # use rammer::SketchBagOfWords;
+/// let sketch: Option<SketchBagOfWords> = vec!["hello", "offer", "hello"]
+///     .into_iter()
+///     .map(|text| SketchBagOfWords::new(1024, 4, 0).unwrap().add_text(text))
+///     .collect();
+/// assert_eq!(sketch.unwrap().count("hello"), 2);
+/// ```
+impl std::iter::FromIterator<SketchBagOfWords> for Option<SketchBagOfWords> {
+    #[allow(missing_doc_code_examples)]
+    fn from_iter<I: IntoIterator<Item = SketchBagOfWords>>(iter: I) -> Self {
+        let mut iter = iter.into_iter();
+        let first = iter.next()?;
+        iter.try_fold(first, SketchBagOfWords::combine)
+    }
+}
+
+/// A model of a ham and a spam sketch, scored with multinomial naive Bayes from their estimated
+/// counts. Sketches can't list their tokens, so complement and Bernoulli scoring aren't supported,
+/// and saved models set to use them are rejected when they are read.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+#[serde(try_from = "SketchModelFile")]
+pub struct SketchModel {
+    ham_bow: SketchBagOfWords,
+    spam_bow: SketchBagOfWords,
+    /// Settings used when classifying text. Use [with_scoring](#method.with_scoring) rather than
+    /// changing the scoring here.
+    pub config: ClassifierConfig,
+}
+
+#[allow(missing_doc_code_examples)]
+impl SketchModel {
+    /// Create a model from a ham and a spam sketch with the default config. The sketches can be
+    /// of different sizes.
+    /// ```
+    /// # use rammer::{SketchBagOfWords, SketchModel};
+    /// let sketch = SketchBagOfWords::new(1024, 4, 0).unwrap();
+    /// let model = SketchModel::from_bows(sketch.clone(), sketch);
+    /// assert_eq!(model.text_spam_probability("anything"), 0.5);
+    /// ```
+    pub fn from_bows(ham_bow: SketchBagOfWords, spam_bow: SketchBagOfWords) -> Self {
+        SketchModel {
+            ham_bow,
+            spam_bow,
+            config: ClassifierConfig::default(),
+        }
+    }
+
+    /// Builder pattern for setting how text is scored. Returns None for anything but
+    /// multinomial scoring, as the others need every token in the model.
+    /// ```
+    /// # use rammer::{Scoring, SketchBagOfWords, SketchModel};
+    /// let sketch = SketchBagOfWords::new(1024, 4, 0).unwrap();
+    /// let model = SketchModel::from_bows(sketch.clone(), sketch);
+    /// assert!(model.with_scoring(Scoring::Complement).is_none());
+    /// ```
+    pub fn with_scoring(mut self, scoring: Scoring) -> Option<Self> {
+        if scoring != Scoring::Multinomial {
+            return None;
+        }
+        self.config.scoring = scoring;
+        Some(self)
+    }
+
+    /// The ham sketch.
+    pub fn ham_bow(&self) -> &SketchBagOfWords {
+        &self.ham_bow
+    }
+
+    /// The spam sketch.
+    pub fn spam_bow(&self) -> &SketchBagOfWords {
+        &self.spam_bow
+    }
+
+    /// Builder pattern for counting a slice of ham text, as training from a stream does.
+    pub fn add_ham(mut self, text: &str) -> Self {
+        self.ham_bow = self.ham_bow.add_text(text);
+        self
+    }
+
+    /// Builder pattern for counting a slice of spam text, as training from a stream does.
+    pub fn add_spam(mut self, text: &str) -> Self {
+        self.spam_bow = self.spam_bow.add_text(text);
+        self
+    }
+
+    /// Returns the probability that a slice of text is spam, calibrated like
+    /// [HSModel::text_spam_probability](struct.HSModel.html#method.text_spam_probability).
+    pub fn text_spam_probability(&self, text: &str) -> Probability {
+        self.config.probability(self.text_log_odds(text))
+    }
+
+    /// Returns the log odds that a slice of text is spam, scored like
+    /// [HSModel::text_log_odds](struct.HSModel.html#method.text_log_odds) from estimated counts.
+    pub fn text_log_odds(&self, text: &str) -> f64 {
+        counts_text_log_odds(self, None, self.config.significant, text)
+    }
+
+    /// Classify a slice of text as spam, ham or unsure using the model's
+    /// [Thresholds](struct.Thresholds.html).
+    pub fn classify(&self, text: &str) -> Classification {
+        self.config
+            .thresholds
+            .classify(self.text_spam_probability(text))
+    }
+}
+
+/// The saved fields of a SketchModel, before its scoring is checked.
+#[derive(Deserialize)]
+struct SketchModelFile {
+    ham_bow: SketchBagOfWords,
+    spam_bow: SketchBagOfWords,
+    config: ClassifierConfig,
+}
+
+impl TryFrom<SketchModelFile> for SketchModel {
+    type Error = String;
+
+    #[allow(missing_doc_code_examples)]
+    fn try_from(file: SketchModelFile) -> Result<Self, Self::Error> {
+        let scoring = file.config.scoring;
+        SketchModel {
+            config: file.config,
+            ..SketchModel::from_bows(file.ham_bow, file.spam_bow)
+        }
+        .with_scoring(scoring)
+        .ok_or_else(|| format!("sketch models can't use {} scoring", scoring))
+    }
+}
+
+impl TokenCounts for SketchModel {
+    #[allow(missing_doc_code_examples)]
+    fn totals(&self) -> (Count, Count) {
+        (
+            self.ham_bow.total_word_count(),
+            self.spam_bow.total_word_count(),
+        )
+    }
+
+    #[allow(missing_doc_code_examples)]
+    fn counts(&self, token: &str) -> (Count, Count) {
+        (
+            self.ham_bow.token_count(token),
+            self.spam_bow.token_count(token),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rng::SplitMix64, HSModel};
+
+    /// n tokens drawn from a vocabulary of distinct tokens with Zipf's law, token i being drawn
+    /// in proportion to 1 / (i + 1), as words in mail roughly are.
+    fn zipf(n: usize, distinct: usize, seed: u64) -> Vec<String> {
+        let mut cumulative = Vec::with_capacity(distinct);
+        let mut sum = 0.0;
+        for i in 0..distinct {
+            sum += 1.0 / (i + 1) as f64;
+            cumulative.push(sum);
+        }
+        let mut rng = SplitMix64::new(seed);
+        (0..n)
+            .map(|_| {
+                let x = (rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64 * sum;
+                let i = cumulative.partition_point(|&c| c <= x).min(distinct - 1);
+                format!("TOKEN{}", i)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sizes_are_checked() {
+        assert!(SketchBagOfWords::new(usize::MAX / 2 + 1, 2, 0).is_none());
+        assert!(SketchBagOfWords::new(usize::MAX / 8 + 1, 1, 0).is_none());
+        assert!(SketchBagOfWords::with_error(f64::MIN_POSITIVE, 0.01, 0).is_none());
+        assert!(SketchBagOfWords::with_error(0.01, f64::MIN_POSITIVE, 0).is_some());
+
+        let sketch = SketchBagOfWords::new(4, 3, 1).unwrap().add_text("hello");
+        let json = serde_json::to_string(&sketch).unwrap();
+        assert_eq!(
+            serde_json::from_str::<SketchBagOfWords>(&json).unwrap(),
+            sketch
+        );
+        let rejected = |json: String| serde_json::from_str::<SketchBagOfWords>(&json).is_err();
+        assert!(rejected(json.replace("\"width\":4", "\"width\":5")));
+        assert!(rejected(json.replace("\"width\":4", "\"width\":0")));
+    }
+
+    fn exact(tokens: &[String]) -> HashMap<&str, Count> {
+        let mut counts = HashMap::new();
+        for token in tokens {
+            *counts.entry(&token[..]).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    fn sketch(tokens: &[String]) -> SketchBagOfWords {
+        let mut sketch = SketchBagOfWords::with_error(0.01, 0.01, 3)
+            .unwrap()
+            .with_heavy_hitters(10);
+        for token in tokens {
+            sketch.add(token, 1);
+        }
+        sketch
+    }
+
+    #[test]
+    fn models_only_load_with_multinomial_scoring() {
+        let sketch = SketchBagOfWords::new(64, 2, 0)
+            .unwrap()
+            .add_text("hello offer");
+        let model = SketchModel::from_bows(sketch.clone(), sketch);
+        let json = serde_json::to_string(&model).unwrap();
+        assert_eq!(serde_json::from_str::<SketchModel>(&json).unwrap(), model);
+        for scoring in ["complement", "bernoulli"].iter() {
+            let other = json.replace(
+                "\"scoring\":\"multinomial\"",
+                &format!("\"scoring\":\"{}\"", scoring),
+            );
+            assert_ne!(other, json);
+            assert!(serde_json::from_str::<SketchModel>(&other).is_err());
+        }
+    }
+
+    #[test]
+    fn estimates_stay_within_error_bound() {
+        let tokens = zipf(50_000, 5_000, 1);
+        let sketch = sketch(&tokens);
+        let exact = exact(&tokens);
+        assert_eq!(sketch.total_word_count(), 50_000);
+        // With epsilon 0.01 each estimate is within 500 of the truth with probability 0.99.
+        assert_eq!(sketch.error_bound(), 500);
+        // Without conservative update a counter is the sum of every token hashed to it.
+        let mut plain_counters = vec![0; sketch.counters.len()];
+        for (token, &count) in exact.iter() {
+            for row in 0..sketch.depth() {
+                plain_counters[sketch.index(token, row)] += count;
+            }
+        }
+        let mut over_bound = 0;
+        let (mut error, mut plain_error) = (0, 0);
+        for (&token, &count) in exact.iter() {
+            let estimate = sketch.token_count(token);
+            assert!(estimate >= count, "{} underestimated", token);
+            if estimate - count > sketch.error_bound() {
+                over_bound += 1;
+            }
+            let plain = (0..sketch.depth())
+                .map(|row| plain_counters[sketch.index(token, row)])
+                .min()
+                .unwrap();
+            assert!(estimate <= plain, "{}", token);
+            error += estimate - count;
+            plain_error += plain - count;
+        }
+        assert!(over_bound as f64 <= sketch.delta() * exact.len() as f64);
+        assert!(error < plain_error);
+    }
+
+    #[test]
+    fn heavy_hitters_are_the_most_frequent_tokens() {
+        let tokens = zipf(50_000, 5_000, 2);
+        let sketch = sketch(&tokens);
+        let mut exact: Vec<(&str, Count)> = exact(&tokens).into_iter().collect();
+        exact.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        let mut heavy: Vec<&str> = sketch.heavy_hitters().iter().map(|&(t, _)| t).collect();
+        let mut top: Vec<&str> = exact.iter().take(10).map(|&(t, _)| t).collect();
+        heavy.sort_unstable();
+        top.sort_unstable();
+        assert_eq!(heavy, top);
+        assert!(sketch.heavy.len() < 20);
+    }
+
+    #[test]
+    fn merged_sketches_bound_the_combined_counts() {
+        let (first, second) = (zipf(20_000, 3_000, 3), zipf(30_000, 3_000, 4));
+        let merged = sketch(&first).combine(sketch(&second)).unwrap();
+        let all: Vec<String> = first.iter().chain(second.iter()).cloned().collect();
+        let exact = exact(&all);
+        assert_eq!(merged.total_word_count(), 50_000);
+        let over_bound = exact
+            .iter()
+            .filter(|(&token, &count)| {
+                let estimate = merged.token_count(token);
+                assert!(estimate >= count, "{} underestimated", token);
+                estimate - count > merged.error_bound()
+            })
+            .count();
+        assert!(over_bound as f64 <= merged.delta() * exact.len() as f64);
+        assert_eq!(merged.heavy_hitters()[0].0, "TOKEN0");
+        assert!(sketch(&first)
+            .combine(SketchBagOfWords::new(272, 5, 4).unwrap())
+            .is_none());
+    }
+
+    #[test]
+    fn scores_match_exact_model_without_collisions() {
+        let ham = BagOfWords::from("hello hello lunch meeting report report free");
+        let spam = BagOfWords::from("free free offer offer meeting winner report");
+        let model = HSModel::from_bows(ham.clone(), spam.clone());
+        let new = || SketchBagOfWords::new(1 << 16, 4, 0).unwrap();
+        let sketched = SketchModel::from_bows(new().add_bow(&ham), new().add_bow(&spam));
+        for text in &[
+            "free offer",
+            "hello meeting report",
+            "lunch winner",
+            "nothing",
+        ] {
+            let p = model.text_spam_probability(text);
+            assert!((sketched.text_spam_probability(text) - p).abs() < 1e-12);
+        }
+    }
+}
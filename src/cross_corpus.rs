@@ -0,0 +1,286 @@
+//! How well a model trained on one corpus, such as one user's mail, does on another.
+//! Each named corpus is split once, with a seed, into a training part and a held out test part
+//! with the same share of ham and spam. A model is trained on the training parts of every train
+//! set, which may combine several corpora, and evaluated on the test part of every corpus, giving
+//! a train × test matrix of reports. No model is ever tested on documents it was trained on.
+//! ```no_run
+//! use rammer::{cross_corpus, ClassifierConfig, Corpus};
+//! let enron1 = Corpus::from_folders("data/enron1/ham", "data/enron1/spam").unwrap();
+//! let enron2 = Corpus::from_folders("data/enron2/ham", "data/enron2/spam").unwrap();
+//! let corpora = [("enron1", &enron1), ("enron2", &enron2)];
+//! let train_sets = ["enron1", "enron2", "enron1+enron2"];
+//! let matrix = cross_corpus(&corpora, &train_sets, 0.3, 0, &ClassifierConfig::default()).unwrap();
+//! println!("{}", matrix.to_text("accuracy").unwrap());
+//! ```
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::TokenizedDocument, evaluation::score_tokenized, rng::SplitMix64, BagOfWords,
+    ClassifierConfig, Corpus, EvaluationReport, HSModel, Label, Metrics,
+};
+
+/// The result of [cross_corpus](fn.cross_corpus.html).
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct CrossCorpusMatrix {
+    /// Names of the train sets, one per row.
+    pub train: Vec<String>,
+    /// Names of the corpora tested on, one per column.
+    pub test: Vec<String>,
+    /// Fraction of each corpus held out for testing.
+    pub test_fraction: f64,
+    /// Seed the corpora were split with.
+    pub seed: u64,
+    /// `reports[row][column]` is the model trained on `train[row]` evaluated on `test[column]`.
+    pub reports: Vec<Vec<EvaluationReport>>,
+}
+
+#[allow(missing_doc_code_examples)]
+impl CrossCorpusMatrix {
+    /// The value of one metric, named as in [Metrics::NAMES](struct.Metrics.html#associatedconstant.NAMES),
+    /// for every train and test pair. Returns None for an unknown metric.
+    pub fn metric(&self, name: &str) -> Option<Vec<Vec<f64>>> {
+        let index = Metrics::NAMES.iter().position(|&n| n == name)?;
+        Some(
+            self.reports
+                .iter()
+                .map(|row| row.iter().map(|r| r.metrics.values()[index]).collect())
+                .collect(),
+        )
+    }
+
+    /// One metric as a human readable table, train sets down the side and test corpora across the
+    /// top. Returns None for an unknown metric.
+    pub fn to_text(&self, name: &str) -> Option<String> {
+        let values = self.metric(name)?;
+        let width = self
+            .train
+            .iter()
+            .map(|t| t.len())
+            .chain(std::iter::once("train \\ test".len()))
+            .max()
+            .unwrap_or(0);
+        let mut text = format!("{} (rows train, columns test)\n", name);
+        text.push_str(&format!("{:<width$}", "train \\ test", width = width));
+        for test in &self.test {
+            text.push_str(&format!(" {:>10}", test));
+        }
+        text.push('\n');
+        for (train, row) in self.train.iter().zip(values.iter()) {
+            text.push_str(&format!("{:<width$}", train, width = width));
+            for value in row {
+                text.push_str(&format!(" {:>10.4}", value));
+            }
+            text.push('\n');
+        }
+        Some(text)
+    }
+
+    /// The matrix as csv with a header line, one row per train and test pair.
+    pub fn to_csv(&self) -> String {
+        let mut csv = format!("train,test,{}\n", EvaluationReport::csv_header());
+        for (train, row) in self.train.iter().zip(self.reports.iter()) {
+            for (test, report) in self.test.iter().zip(row.iter()) {
+                csv.push_str(&format!("{},{},{}\n", train, test, report.csv_row()));
+            }
+        }
+        csv
+    }
+
+    /// The matrix as pretty printed json.
+    pub fn to_json(&self) -> Option<String> {
+        serde_json::to_string_pretty(self).ok()
+    }
+}
+
+/// A corpus split into the bags trained on and the documents tested on.
+struct Split {
+    ham_bow: BagOfWords,
+    spam_bow: BagOfWords,
+    test: Vec<TokenizedDocument>,
+}
+
+/// Split corpus into training bags and held out test documents, holding out test_fraction of each
+/// label but always keeping at least one document of each label on both sides.
+fn split(corpus: &Corpus, test_fraction: f64, rng: &mut SplitMix64) -> Option<Split> {
+    let (train, test) = stratified_split(corpus.tokenize(), |d| d.label, test_fraction, rng)?;
+    let (ham, spam): (Vec<TokenizedDocument>, Vec<TokenizedDocument>) =
+        train.into_iter().partition(|d| d.label == Label::Ham);
+    Some(Split {
+        ham_bow: ham.into_iter().map(|d| d.bow).collect(),
+        spam_bow: spam.into_iter().map(|d| d.bow).collect(),
+        test,
+    })
+}
+
+/// Shuffle items with rng and split them into a kept and a held out part, holding out fraction of
+/// each label but always keeping at least one item of each label on both sides. Returns None if
+/// there are fewer than 2 items of either label.
+pub(crate) fn stratified_split<T, F: Fn(&T) -> Label>(
+    items: Vec<T>,
+    label: F,
+    fraction: f64,
+    rng: &mut SplitMix64,
+) -> Option<(Vec<T>, Vec<T>)> {
+    let (ham, spam): (Vec<T>, Vec<T>) = items
+        .into_iter()
+        .partition(|item| label(item) == Label::Ham);
+    let (mut kept, mut held_out) = (Vec::new(), Vec::new());
+    for mut labelled in [ham, spam] {
+        if labelled.len() < 2 {
+            return None;
+        }
+        rng.shuffle(&mut labelled);
+        let n = ((fraction * labelled.len() as f64).round() as usize).clamp(1, labelled.len() - 1);
+        kept.extend(labelled.split_off(n));
+        held_out.extend(labelled);
+    }
+    Some((kept, held_out))
+}
+
+/// Train a model on each of train_sets and evaluate it on the held out part of every corpus,
+/// using config. A train set is a corpus name, or several joined with `+` to train on all of them.
+/// Returns None if test_fraction is not between 0 and 1, if a train set names an unknown corpus,
+/// or if any corpus has fewer than 2 readable documents of either label.
+/// ```
+/// # use rammer::{cross_corpus, ClassifierConfig, Corpus, Label};
+/// let a = Corpus::new()
+///     .add_folder("test_resources/test_data", Label::Ham).unwrap()
+///     .add_folder("test_resources/test_maildir/cur", Label::Spam).unwrap()
+///     .add_folder("test_resources/test_maildir/new", Label::Spam).unwrap();
+/// let matrix = cross_corpus(&[("a", &a)], &["a"], 0.5, 0, &ClassifierConfig::default()).unwrap();
+/// assert_eq!(matrix.reports[0][0].confusion.total(), 3);
+/// assert!(cross_corpus(&[("a", &a)], &["a+b"], 0.5, 0, &ClassifierConfig::default()).is_none());
+/// ```
+pub fn cross_corpus(
+    corpora: &[(&str, &Corpus)],
+    train_sets: &[&str],
+    test_fraction: f64,
+    seed: u64,
+    config: &ClassifierConfig,
+) -> Option<CrossCorpusMatrix> {
+    if !(test_fraction > 0.0 && test_fraction < 1.0) {
+        return None;
+    }
+    let members: Vec<Vec<usize>> = train_sets
+        .iter()
+        .map(|set| {
+            set.split('+')
+                .map(|name| corpora.iter().position(|(n, _)| *n == name.trim()))
+                .collect::<Option<Vec<usize>>>()
+        })
+        .collect::<Option<_>>()?;
+
+    let mut rng = SplitMix64::new(seed);
+    let splits: Vec<Split> = corpora
+        .iter()
+        .map(|(_, corpus)| split(corpus, test_fraction, &mut rng))
+        .collect::<Option<_>>()?;
+    let tests: Vec<Vec<&TokenizedDocument>> =
+        splits.iter().map(|s| s.test.iter().collect()).collect();
+
+    let reports = members
+        .iter()
+        .map(|member| {
+            let mut model = member.iter().fold(HSModel::new(), |model, &i| {
+                model
+                    .add_ham_bow(splits[i].ham_bow.clone())
+                    .add_spam_bow(splits[i].spam_bow.clone())
+            });
+            model.config = config.clone();
+            tests
+                .iter()
+                .map(|test| {
+                    let start = Instant::now();
+                    let scored = score_tokenized(&model, test);
+                    let seconds = start.elapsed().as_secs_f64();
+                    EvaluationReport::from_scores(&scored, &config.thresholds, seconds)
+                })
+                .collect()
+        })
+        .collect();
+
+    Some(CrossCorpusMatrix {
+        train: train_sets.iter().map(|s| s.to_string()).collect(),
+        test: corpora.iter().map(|(name, _)| name.to_string()).collect(),
+        test_fraction,
+        seed,
+        reports,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn corpora() -> (Corpus, Corpus) {
+        let a = Corpus::new()
+            .add_folder("test_resources/test_data", Label::Ham)
+            .and_then(|c| c.add_folder("test_resources/test_maildir/cur", Label::Spam))
+            .and_then(|c| c.add_folder("test_resources/test_maildir/new", Label::Spam))
+            .unwrap();
+        let b = Corpus::new()
+            .add_folder("test_resources/test_maildir/tmp", Label::Ham)
+            .and_then(|c| c.add_folder("test_resources/test_maildir/new", Label::Ham))
+            .and_then(|c| c.add_folder("test_resources/test_maildir/.Junk/cur", Label::Spam))
+            .and_then(|c| c.add_folder("test_resources/test_maildir/.Trash/cur", Label::Spam))
+            .unwrap();
+        (a, b)
+    }
+
+    #[test]
+    fn matrix_has_a_report_per_pair() {
+        let (a, b) = corpora();
+        let matrix = cross_corpus(
+            &[("a", &a), ("b", &b)],
+            &["a", "b", "a+b"],
+            0.4,
+            7,
+            &ClassifierConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(matrix.reports.len(), 3);
+        assert!(matrix.reports.iter().all(|row| row.len() == 2));
+        // a holds out one ham and one spam, b holds out one of each too.
+        assert!(matrix
+            .reports
+            .iter()
+            .all(|row| row[0].confusion.total() == 2));
+        assert_eq!(matrix.metric("accuracy").unwrap().len(), 3);
+        assert!(matrix.metric("unknown").is_none());
+        assert_eq!(matrix.to_csv().lines().count(), 1 + 6);
+        assert!(matrix.to_text("f1").unwrap().contains("a+b"));
+    }
+
+    #[test]
+    fn splits_are_seeded() {
+        let (a, b) = corpora();
+        let run = |seed| {
+            cross_corpus(
+                &[("a", &a), ("b", &b)],
+                &["a+b"],
+                0.5,
+                seed,
+                &ClassifierConfig::default(),
+            )
+            .unwrap()
+            .reports
+        };
+        let strip = |reports: Vec<Vec<EvaluationReport>>| -> Vec<Metrics> {
+            reports.into_iter().flatten().map(|r| r.metrics).collect()
+        };
+        assert_eq!(strip(run(3)), strip(run(3)));
+    }
+
+    #[test]
+    fn corpus_without_both_labels_is_none() {
+        let (a, _) = corpora();
+        let ham_only = Corpus::new()
+            .add_folder("test_resources/test_data", Label::Ham)
+            .unwrap();
+        let config = ClassifierConfig::default();
+        assert!(cross_corpus(&[("a", &a), ("h", &ham_only)], &["a"], 0.5, 0, &config).is_none());
+        assert!(cross_corpus(&[("a", &a)], &["a"], 1.0, 0, &config).is_none());
+    }
+}
@@ -0,0 +1,224 @@
+//! k-fold cross-validation of HSModels over a labelled Corpus.
+//! The corpus is shuffled with a seed and split into k folds, keeping the share of ham and spam
+//! the same in each. Every fold is held out once while a model is trained on the rest, so each
+//! metric is measured k times and reported with its mean and variance. Documents are tokenized
+//! once and the per-fold BagOfWords are reused for every model.
+//! ```no_run
+//! use rammer::{cross_validate, ClassifierConfig, Corpus};
+//! let corpus = Corpus::from_folders("data/train/ham", "data/train/spam").unwrap();
+//! let report = cross_validate(&corpus, 10, 0, &ClassifierConfig::default()).unwrap();
+//! println!("{}", report.to_text());
+//! ```
+use std::time::Instant;
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::TokenizedDocument, evaluation::score_tokenized, rng::SplitMix64, BagOfWords,
+    ClassifierConfig, Corpus, EvaluationReport, HSModel, Label, Metrics,
+};
+
+/// Assign each document a fold in 0..k. Ham and spam are shuffled separately with the seed and
+/// dealt out in turn, so every fold gets as close to the same share of each as possible.
+pub(crate) fn stratified_folds(docs: &[TokenizedDocument], k: usize, seed: u64) -> Vec<usize> {
+    let mut rng = SplitMix64::new(seed);
+    let mut folds = vec![0; docs.len()];
+    for label in [Label::Ham, Label::Spam].iter() {
+        let mut indices: Vec<usize> = (0..docs.len())
+            .filter(|&i| docs[i].label == *label)
+            .collect();
+        rng.shuffle(&mut indices);
+        for (position, i) in indices.into_iter().enumerate() {
+            folds[i] = position % k;
+        }
+    }
+    folds
+}
+
+/// The result of [cross_validate](fn.cross_validate.html).
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct CrossValidationReport {
+    /// Number of folds.
+    pub k: usize,
+    /// Seed the corpus was shuffled with.
+    pub seed: u64,
+    /// Evaluation of each held out fold, in fold order.
+    pub folds: Vec<EvaluationReport>,
+    /// Mean of each metric over the folds.
+    pub mean: Metrics,
+    /// Sample variance of each metric over the folds.
+    pub variance: Metrics,
+}
+
+#[allow(missing_doc_code_examples)]
+impl CrossValidationReport {
+    /// The report as a human readable table of each metric's mean, variance and standard
+    /// deviation.
+    pub fn to_text(&self) -> String {
+        let documents: usize = self.folds.iter().map(|f| f.confusion.total()).sum();
+        let seconds: f64 = self.folds.iter().map(|f| f.timings.scoring_seconds).sum();
+        let mut text = format!(
+            "{}-fold cross-validation of {} documents (seed {})\n\n{:<20} {:>8} {:>10} {:>8}\n",
+            self.k, documents, self.seed, "metric", "mean", "variance", "std dev"
+        );
+        let means = self.mean.values();
+        let variances = self.variance.values();
+        for (i, name) in Metrics::NAMES.iter().enumerate() {
+            text.push_str(&format!(
+                "{:<20} {:>8.4} {:>10.6} {:>8.4}\n",
+                name,
+                means[i],
+                variances[i],
+                variances[i].sqrt()
+            ));
+        }
+        text.push_str(&format!("\nScored held out folds in {:.2}s", seconds));
+        text
+    }
+
+    /// The report as pretty printed json.
+    pub fn to_json(&self) -> Option<String> {
+        serde_json::to_string_pretty(self).ok()
+    }
+
+    /// The metrics of every fold as csv, followed by a `mean` row and a `variance` row.
+    pub fn to_csv(&self) -> String {
+        let mut csv = format!("fold,{}\n", Metrics::NAMES.join(","));
+        let mut row = |name: String, metrics: &Metrics| {
+            let values: Vec<String> = metrics.values().iter().map(|v| v.to_string()).collect();
+            csv.push_str(&format!("{},{}\n", name, values.join(",")));
+        };
+        for (i, fold) in self.folds.iter().enumerate() {
+            row((i + 1).to_string(), &fold.metrics);
+        }
+        row("mean".to_string(), &self.mean);
+        row("variance".to_string(), &self.variance);
+        csv
+    }
+}
+
+/// Cross-validate HSModels over corpus with k folds shuffled by seed. Each model uses config to
+/// classify its held out fold. Returns None if k is less than 2, or if there are fewer than k
+/// readable documents of either ham or spam.
+/// ```
+/// # use rammer::{cross_validate, ClassifierConfig, Corpus, Label};
+/// let corpus = Corpus::new()
+///     .add_folder("test_resources/test_data", Label::Ham).unwrap()
+///     .add_folder("test_resources/test_maildir/.Junk/cur", Label::Spam).unwrap();
+/// assert!(cross_validate(&corpus, 2, 0, &ClassifierConfig::default()).is_none());
+/// ```
+pub fn cross_validate(
+    corpus: &Corpus,
+    k: usize,
+    seed: u64,
+    config: &ClassifierConfig,
+) -> Option<CrossValidationReport> {
+    let docs = corpus.tokenize();
+    let count = |label| docs.iter().filter(|d| d.label == label).count();
+    if k < 2 || count(Label::Ham) < k || count(Label::Spam) < k {
+        return None;
+    }
+    let folds = stratified_folds(&docs, k, seed);
+
+    let bow_for = |fold: usize, label: Label| -> BagOfWords {
+        docs.par_iter()
+            .zip(folds.par_iter())
+            .filter(|(d, &f)| f == fold && d.label == label)
+            .map(|(d, _)| d.bow.clone())
+            .collect()
+    };
+    let fold_bows: Vec<(BagOfWords, BagOfWords)> = (0..k)
+        .map(|fold| (bow_for(fold, Label::Ham), bow_for(fold, Label::Spam)))
+        .collect();
+
+    let reports: Vec<EvaluationReport> = (0..k)
+        .map(|held_out| {
+            let mut model = fold_bows
+                .iter()
+                .enumerate()
+                .filter(|&(fold, _)| fold != held_out)
+                .fold(HSModel::new(), |model, (_, (ham, spam))| {
+                    model.add_ham_bow(ham.clone()).add_spam_bow(spam.clone())
+                });
+            model.config = config.clone();
+
+            let test: Vec<&TokenizedDocument> = docs
+                .iter()
+                .zip(folds.iter())
+                .filter(|&(_, &f)| f == held_out)
+                .map(|(d, _)| d)
+                .collect();
+            let start = Instant::now();
+            let scored = score_tokenized(&model, &test);
+            let seconds = start.elapsed().as_secs_f64();
+            EvaluationReport::from_scores(&scored, &config.thresholds, seconds)
+        })
+        .collect();
+
+    let metrics: Vec<Metrics> = reports.iter().map(|r| r.metrics).collect();
+    Some(CrossValidationReport {
+        k,
+        seed,
+        mean: Metrics::mean(&metrics),
+        variance: Metrics::variance(&metrics),
+        folds: reports,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Three ham and three spam documents. The labels are arbitrary, the tests only need both.
+    fn corpus() -> Corpus {
+        let spam = Corpus::new()
+            .add_folder("test_resources/test_maildir/cur", Label::Spam)
+            .and_then(|c| c.add_folder("test_resources/test_maildir/new", Label::Spam))
+            .and_then(|c| c.add_folder("test_resources/test_maildir/tmp", Label::Spam))
+            .unwrap();
+        Corpus::new()
+            .add_folder("test_resources/test_data", Label::Ham)
+            .unwrap()
+            .combine(spam)
+    }
+
+    #[test]
+    fn folds_are_stratified_and_seeded() {
+        let docs = corpus().tokenize();
+        let folds = stratified_folds(&docs, 3, 5);
+        assert_eq!(folds, stratified_folds(&docs, 3, 5));
+        for fold in 0..3 {
+            let in_fold: Vec<&TokenizedDocument> = docs
+                .iter()
+                .zip(folds.iter())
+                .filter(|&(_, &f)| f == fold)
+                .map(|(d, _)| d)
+                .collect();
+            assert_eq!(in_fold.iter().filter(|d| d.label == Label::Ham).count(), 1);
+            assert_eq!(in_fold.iter().filter(|d| d.label == Label::Spam).count(), 1);
+        }
+    }
+
+    #[test]
+    fn every_document_is_held_out_once() {
+        let report = cross_validate(&corpus(), 3, 1, &ClassifierConfig::default()).unwrap();
+        assert_eq!(report.folds.len(), 3);
+        let total: usize = report.folds.iter().map(|f| f.confusion.total()).sum();
+        assert_eq!(total, 6);
+        let metrics: Vec<Metrics> = report.folds.iter().map(|f| f.metrics).collect();
+        assert_eq!(report.mean, Metrics::mean(&metrics));
+        assert_eq!(report.to_csv().lines().count(), 1 + 3 + 2);
+    }
+
+    #[test]
+    fn bow_scores_match_text_scores() {
+        let docs = corpus().tokenize();
+        let (ham, spam) = corpus().bows();
+        let model = HSModel::from_bows(ham, spam);
+        for (doc, d) in corpus().documents.iter().zip(docs.iter()) {
+            let text = model.text_spam_probability(&doc.text().unwrap());
+            assert!((model.bow_spam_probability(&d.bow) - text).abs() < 1e-9);
+        }
+    }
+}
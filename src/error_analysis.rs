@@ -0,0 +1,203 @@
+//! Which documents a model got wrong, and which tokens made it get them wrong.
+//! An ErrorAnalysis lists the false positives, ham classified as spam, and the false negatives,
+//! spam classified as ham, with the most confident mistakes first. Documents the model was unsure
+//! about are not mistakes and are left out.
+//! ```no_run
+//! use rammer::{score_corpus, Corpus, ErrorAnalysis, HSModel};
+//! let model = HSModel::read_from_json("out/models/enron1_model.json").unwrap();
+//! let corpus = Corpus::from_folders("data/validate/ham", "data/validate/spam").unwrap();
+//! let errors = ErrorAnalysis::from_scores(&model, &score_corpus(&model, &corpus), 10);
+//! std::fs::write("errors.json", errors.to_json().unwrap()).unwrap();
+//! ```
+use std::{fs, path::PathBuf};
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Classification, HSModel, Label, Probability, ScoredDocument, Thresholds, TokenContribution,
+};
+
+/// A document the model classified as the wrong Label.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct Misclassification {
+    /// Location of the document.
+    pub path: PathBuf,
+    /// The known class of the document.
+    pub label: Label,
+    /// Probability the model gave that the document is spam.
+    pub probability: Probability,
+    /// Log odds the model gave that the document is spam, which rank mistakes whose
+    /// probabilities both round to 0.0 or 1.0.
+    pub log_odds: f64,
+    /// The tokens which moved the score the most, as reported by
+    /// [explain](struct.HSModel.html#method.explain). Empty if the document could no longer be
+    /// read.
+    pub tokens: Vec<TokenContribution>,
+}
+
+/// The misclassified documents from a validation run, ranked from the most to the least
+/// confident mistake.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct ErrorAnalysis {
+    /// Thresholds the documents were classified with.
+    pub thresholds: Thresholds,
+    /// Ham classified as spam, highest log odds first.
+    pub false_positives: Vec<Misclassification>,
+    /// Spam classified as ham, lowest log odds first.
+    pub false_negatives: Vec<Misclassification>,
+}
+
+#[allow(missing_doc_code_examples)]
+impl ErrorAnalysis {
+    /// Find the misclassified documents in scored using the model's thresholds, and explain each
+    /// with its top_tokens most influential tokens. Documents are read again to explain them.
+    /// ```
+    /// # use rammer::{logit, BagOfWords, ErrorAnalysis, HSModel, Label, ScoredDocument};
+    /// let model = HSModel::from_bows(BagOfWords::from("hello lunch"), BagOfWords::from("hello offer"));
+    /// let scored = vec![
+    ///     ScoredDocument { path: "test_resources/test_data/ascii_only.txt".into(), label: Label::Ham, probability: 0.9, log_odds: logit(0.9) },
+    ///     ScoredDocument { path: "b".into(), label: Label::Spam, probability: 0.95, log_odds: logit(0.95) },
+    /// ];
+    /// let errors = ErrorAnalysis::from_scores(&model, &scored, 5);
+    /// assert_eq!(errors.false_positives.len(), 1);
+    /// assert!(errors.false_negatives.is_empty());
+    /// ```
+    pub fn from_scores(model: &HSModel, scored: &[ScoredDocument], top_tokens: usize) -> Self {
+        let thresholds = model.config.thresholds;
+        let scorer = model.scorer();
+        let mistakes = |label: Label, wrong: Classification| -> Vec<Misclassification> {
+            let mut found: Vec<Misclassification> = scored
+                .par_iter()
+                .filter(|d| d.label == label && thresholds.classify(d.probability) == wrong)
+                .map(|d| Misclassification {
+                    path: d.path.clone(),
+                    label: d.label,
+                    probability: d.probability,
+                    log_odds: d.log_odds,
+                    tokens: fs::read_to_string(&d.path)
+                        .map(|text| {
+                            let mut tokens = scorer.explain(&text).tokens;
+                            tokens.truncate(top_tokens);
+                            tokens
+                        })
+                        .unwrap_or_default(),
+                })
+                .collect();
+            found.sort_by(|a, b| {
+                let order = a.log_odds.total_cmp(&b.log_odds);
+                match label {
+                    Label::Ham => order.reverse(),
+                    Label::Spam => order,
+                }
+                .then_with(|| a.path.cmp(&b.path))
+            });
+            found
+        };
+        ErrorAnalysis {
+            thresholds,
+            false_positives: mistakes(Label::Ham, Classification::Spam),
+            false_negatives: mistakes(Label::Spam, Classification::Ham),
+        }
+    }
+
+    /// The mistakes as a human readable list.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for (title, mistakes) in [
+            (
+                "False positives (ham classified as spam)",
+                &self.false_positives,
+            ),
+            (
+                "False negatives (spam classified as ham)",
+                &self.false_negatives,
+            ),
+        ]
+        .iter()
+        {
+            text.push_str(&format!("{}: {}\n", title, mistakes.len()));
+            for m in mistakes.iter() {
+                let tokens: Vec<String> = m
+                    .tokens
+                    .iter()
+                    .map(|t| format!("{} {:+.2}", t.token, t.weight))
+                    .collect();
+                text.push_str(&format!(
+                    "  {:.6} ({:+.2})  {}\n    {}\n",
+                    m.probability,
+                    m.log_odds,
+                    m.path.display(),
+                    tokens.join(", ")
+                ));
+            }
+        }
+        text
+    }
+
+    /// The mistakes as pretty printed json.
+    pub fn to_json(&self) -> Option<String> {
+        serde_json::to_string_pretty(self).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BagOfWords;
+
+    fn doc(path: &str, label: Label, probability: Probability) -> ScoredDocument {
+        ScoredDocument {
+            path: path.into(),
+            label,
+            probability,
+            log_odds: crate::logit(probability),
+        }
+    }
+
+    #[test]
+    fn mistakes_are_ranked_by_confidence() {
+        let scored = vec![
+            doc("a", Label::Ham, 0.85),
+            doc("b", Label::Ham, 0.99),
+            doc("c", Label::Ham, 0.5),
+            doc("d", Label::Spam, 0.15),
+            doc("e", Label::Spam, 0.01),
+            doc("f", Label::Spam, 0.9),
+        ];
+        let errors = ErrorAnalysis::from_scores(&HSModel::new(), &scored, 3);
+        let paths = |m: &[Misclassification]| -> Vec<PathBuf> {
+            m.iter().map(|m| m.path.clone()).collect()
+        };
+        assert_eq!(
+            paths(&errors.false_positives),
+            vec![PathBuf::from("b"), "a".into()]
+        );
+        assert_eq!(
+            paths(&errors.false_negatives),
+            vec![PathBuf::from("e"), "d".into()]
+        );
+        assert!(errors.false_positives[0].tokens.is_empty());
+    }
+
+    #[test]
+    fn tokens_are_limited_and_explained() {
+        let model = HSModel::from_bows(
+            BagOfWords::from("hello hello there world"),
+            BagOfWords::from("hello there world world world"),
+        );
+        let path = "test_resources/test_data/ascii_only.txt";
+        let errors = ErrorAnalysis::from_scores(&model, &[doc(path, Label::Ham, 0.9)], 2);
+        let tokens = &errors.false_positives[0].tokens;
+        let text = fs::read_to_string(path).unwrap();
+        let mut expected = model.explain(&text).tokens;
+        expected.truncate(2);
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens, &expected);
+        let json = errors.to_json().unwrap();
+        assert_eq!(
+            serde_json::from_str::<ErrorAnalysis>(&json).unwrap(),
+            errors
+        );
+    }
+}
@@ -0,0 +1,655 @@
+//! Measure how well an HSModel classifies a Corpus of known ham and spam.
+//! An EvaluationReport holds the confusion matrix, the usual classification metrics and how long
+//! scoring took, and can be written as text for people or as json and csv for tools.
+//! ```no_run
+//! use rammer::{Corpus, EvaluationReport, HSModel};
+//! let model = HSModel::read_from_json("out/models/enron1_model.json").unwrap();
+//! let corpus = Corpus::from_folders("data/validate/ham", "data/validate/spam").unwrap();
+//! let report = EvaluationReport::evaluate(&model, &corpus);
+//! println!("{}", report.to_text());
+//! ```
+use std::{path::PathBuf, time::Instant};
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::TokenizedDocument, Classification, Corpus, HSModel, Label, Probability, ReliabilityBin,
+    Scoring, Thresholds, RELIABILITY_BINS,
+};
+
+/// A document's known Label and the spam probability a model gave it.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct ScoredDocument {
+    /// Location of the document.
+    pub path: PathBuf,
+    /// The known class of the document.
+    pub label: Label,
+    /// Probability the model gave that the document is spam.
+    pub probability: Probability,
+    /// Log odds the model gave that the document is spam. Probabilities of long documents round
+    /// to 0.0 or 1.0, so documents are ranked by these instead.
+    pub log_odds: f64,
+}
+
+/// Score every readable document in corpus with model, in parallel. Documents which can not be
+/// read are skipped. The result is in the same order as the corpus.
+/// ```
+/// # use rammer::{score_corpus, Corpus, HSModel, Label};
+/// let corpus = Corpus::new().add_folder("test_resources/test_data", Label::Ham).unwrap();
+/// let scored = score_corpus(&HSModel::new(), &corpus);
+/// assert_eq!(scored.len(), 3);
+/// ```
+pub fn score_corpus(model: &HSModel, corpus: &Corpus) -> Vec<ScoredDocument> {
+    let weights = model.scoring_weights();
+    corpus
+        .documents
+        .par_iter()
+        .filter_map(|doc| {
+            doc.text().map(|text| {
+                let log_odds = model.text_log_odds_with(&text, &weights);
+                ScoredDocument {
+                    path: doc.path.clone(),
+                    label: doc.label,
+                    probability: model.config.probability(log_odds),
+                    log_odds,
+                }
+            })
+        })
+        .collect()
+}
+
+/// Score documents which have already been tokenized, in parallel and in order.
+pub(crate) fn score_tokenized(model: &HSModel, docs: &[&TokenizedDocument]) -> Vec<ScoredDocument> {
+    let weights = model.scoring_weights();
+    docs.par_iter()
+        .map(|doc| {
+            let log_odds = model.bow_log_odds_with(&doc.bow, &weights);
+            ScoredDocument {
+                path: doc.path.clone(),
+                label: doc.label,
+                probability: model.config.probability(log_odds),
+                log_odds,
+            }
+        })
+        .collect()
+}
+
+/// Counts of each true Label against each Classification.
+/// Spam is the positive class, so `ham_as_spam` counts false positives.
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Copy, Default)]
+pub struct ConfusionMatrix {
+    /// Spam classified as spam, true positives.
+    pub spam_as_spam: usize,
+    /// Spam classified as ham, false negatives.
+    pub spam_as_ham: usize,
+    /// Spam the model was unsure about.
+    pub spam_as_unsure: usize,
+    /// Ham classified as spam, false positives.
+    pub ham_as_spam: usize,
+    /// Ham classified as ham, true negatives.
+    pub ham_as_ham: usize,
+    /// Ham the model was unsure about.
+    pub ham_as_unsure: usize,
+}
+
+#[allow(missing_doc_code_examples)]
+impl ConfusionMatrix {
+    /// Create a new ConfusionMatrix with every count zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a ConfusionMatrix by classifying scored documents with thresholds.
+    /// ```
+    /// # use rammer::{logit, ConfusionMatrix, Label, ScoredDocument, Thresholds};
+    /// let scored = vec![
+    ///     ScoredDocument { path: "a".into(), label: Label::Spam, probability: 0.9, log_odds: logit(0.9) },
+    ///     ScoredDocument { path: "b".into(), label: Label::Ham, probability: 0.5, log_odds: 0.0 },
+    /// ];
+    /// let matrix = ConfusionMatrix::from_scores(&scored, &Thresholds::default());
+    /// assert_eq!((matrix.spam_as_spam, matrix.ham_as_unsure), (1, 1));
+    /// ```
+    pub fn from_scores(scored: &[ScoredDocument], thresholds: &Thresholds) -> Self {
+        let mut matrix = Self::new();
+        for doc in scored {
+            matrix.add(doc.label, thresholds.classify(doc.probability));
+        }
+        matrix
+    }
+
+    /// Count one more document with the given true Label and Classification.
+    pub fn add(&mut self, label: Label, classification: Classification) {
+        let count = match (label, classification) {
+            (Label::Spam, Classification::Spam) => &mut self.spam_as_spam,
+            (Label::Spam, Classification::Ham) => &mut self.spam_as_ham,
+            (Label::Spam, Classification::Unsure) => &mut self.spam_as_unsure,
+            (Label::Ham, Classification::Spam) => &mut self.ham_as_spam,
+            (Label::Ham, Classification::Ham) => &mut self.ham_as_ham,
+            (Label::Ham, Classification::Unsure) => &mut self.ham_as_unsure,
+        };
+        *count += 1;
+    }
+
+    /// Number of spam documents counted.
+    pub fn spam(&self) -> usize {
+        self.spam_as_spam + self.spam_as_ham + self.spam_as_unsure
+    }
+
+    /// Number of ham documents counted.
+    pub fn ham(&self) -> usize {
+        self.ham_as_spam + self.ham_as_ham + self.ham_as_unsure
+    }
+
+    /// Number of documents counted.
+    pub fn total(&self) -> usize {
+        self.spam() + self.ham()
+    }
+}
+
+/// Classification metrics with spam as the positive class. Unsure documents count as neither
+/// caught spam nor correctly passed ham, and ratios with a zero denominator are 0.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone, Copy, Default)]
+pub struct Metrics {
+    /// Fraction of documents classified as their true Label.
+    pub accuracy: f64,
+    /// Fraction of documents classified as spam which are spam.
+    pub precision: f64,
+    /// Fraction of spam classified as spam.
+    pub recall: f64,
+    /// Harmonic mean of precision and recall.
+    pub f1: f64,
+    /// Fraction of ham classified as spam.
+    pub false_positive_rate: f64,
+    /// Fraction of spam classified as ham.
+    pub false_negative_rate: f64,
+    /// Fraction of documents classified as unsure.
+    pub unsure_rate: f64,
+}
+
+#[allow(missing_doc_code_examples)]
+impl Metrics {
+    /// Names of the metrics, in the order they are written to csv.
+    pub const NAMES: [&'static str; 7] = [
+        "accuracy",
+        "precision",
+        "recall",
+        "f1",
+        "false_positive_rate",
+        "false_negative_rate",
+        "unsure_rate",
+    ];
+
+    /// Calculate metrics from a ConfusionMatrix.
+    /// ```
+    /// # use rammer::{ConfusionMatrix, Metrics};
+    /// let matrix = ConfusionMatrix { spam_as_spam: 8, ham_as_spam: 2, ham_as_ham: 8, spam_as_ham: 2, ..Default::default() };
+    /// let metrics = Metrics::from_confusion(&matrix);
+    /// assert_eq!(metrics.precision, 0.8);
+    /// assert_eq!(metrics.false_positive_rate, 0.2);
+    /// ```
+    pub fn from_confusion(m: &ConfusionMatrix) -> Self {
+        let precision = ratio(m.spam_as_spam, m.spam_as_spam + m.ham_as_spam);
+        let recall = ratio(m.spam_as_spam, m.spam());
+        Metrics {
+            accuracy: ratio(m.spam_as_spam + m.ham_as_ham, m.total()),
+            precision,
+            recall,
+            f1: if precision + recall > 0.0 {
+                2.0 * precision * recall / (precision + recall)
+            } else {
+                0.0
+            },
+            false_positive_rate: ratio(m.ham_as_spam, m.ham()),
+            false_negative_rate: ratio(m.spam_as_ham, m.spam()),
+            unsure_rate: ratio(m.spam_as_unsure + m.ham_as_unsure, m.total()),
+        }
+    }
+
+    /// The metrics as an array, in the same order as [NAMES](#associatedconstant.NAMES).
+    pub fn values(&self) -> [f64; 7] {
+        [
+            self.accuracy,
+            self.precision,
+            self.recall,
+            self.f1,
+            self.false_positive_rate,
+            self.false_negative_rate,
+            self.unsure_rate,
+        ]
+    }
+
+    /// Build Metrics from an array in the same order as [NAMES](#associatedconstant.NAMES).
+    pub fn from_values(v: [f64; 7]) -> Self {
+        Metrics {
+            accuracy: v[0],
+            precision: v[1],
+            recall: v[2],
+            f1: v[3],
+            false_positive_rate: v[4],
+            false_negative_rate: v[5],
+            unsure_rate: v[6],
+        }
+    }
+
+    /// The mean of each metric over several runs. Empty input gives zeros.
+    /// ```
+    /// # use rammer::Metrics;
+    /// let runs = [Metrics { accuracy: 0.9, ..Default::default() }, Metrics { accuracy: 0.8, ..Default::default() }];
+    /// assert!((Metrics::mean(&runs).accuracy - 0.85).abs() < 1e-12);
+    /// ```
+    pub fn mean(runs: &[Metrics]) -> Self {
+        if runs.is_empty() {
+            return Self::default();
+        }
+        let mut sum = [0.0; 7];
+        for run in runs {
+            for (s, v) in sum.iter_mut().zip(run.values().iter()) {
+                *s += v;
+            }
+        }
+        Self::from_values(sum.map(|s| s / runs.len() as f64))
+    }
+
+    /// The sample variance of each metric over several runs, dividing by one less than the
+    /// number of runs. Fewer than 2 runs gives zeros.
+    /// ```
+    /// # use rammer::Metrics;
+    /// let runs = [Metrics { accuracy: 0.9, ..Default::default() }, Metrics { accuracy: 0.7, ..Default::default() }];
+    /// assert!((Metrics::variance(&runs).accuracy - 0.02).abs() < 1e-12);
+    /// ```
+    pub fn variance(runs: &[Metrics]) -> Self {
+        if runs.len() < 2 {
+            return Self::default();
+        }
+        let mean = Self::mean(runs).values();
+        let mut squares = [0.0; 7];
+        for run in runs {
+            for ((s, v), m) in squares.iter_mut().zip(run.values().iter()).zip(mean.iter()) {
+                *s += (v - m) * (v - m);
+            }
+        }
+        Self::from_values(squares.map(|s| s / (runs.len() - 1) as f64))
+    }
+}
+
+fn ratio(numerator: usize, denominator: usize) -> f64 {
+    if denominator == 0 {
+        0.0
+    } else {
+        numerator as f64 / denominator as f64
+    }
+}
+
+/// How long scoring took.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone, Copy, Default)]
+pub struct Timings {
+    /// Wall clock seconds spent reading and scoring documents.
+    pub scoring_seconds: f64,
+    /// Documents scored per wall clock second.
+    pub documents_per_second: f64,
+}
+
+/// The full result of evaluating a model against a corpus.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct EvaluationReport {
+    /// Thresholds the documents were classified with.
+    pub thresholds: Thresholds,
+    /// Counts of each true Label against each Classification.
+    pub confusion: ConfusionMatrix,
+    /// Metrics calculated from the confusion matrix.
+    pub metrics: Metrics,
+    /// How long scoring took.
+    pub timings: Timings,
+    /// Reliability diagram of the probabilities, in RELIABILITY_BINS equal width bins from 0 to 1.
+    #[serde(default)]
+    pub reliability: Vec<ReliabilityBin>,
+}
+
+#[allow(missing_doc_code_examples)]
+impl EvaluationReport {
+    /// Score every document in corpus with model and report how well it did.
+    /// ```
+    /// # use rammer::{Corpus, EvaluationReport, HSModel, Label};
+    /// let corpus = Corpus::new().add_folder("test_resources/test_data", Label::Ham).unwrap();
+    /// let report = EvaluationReport::evaluate(&HSModel::new(), &corpus);
+    /// assert_eq!(report.confusion.ham_as_unsure, 3);
+    /// ```
+    pub fn evaluate(model: &HSModel, corpus: &Corpus) -> Self {
+        let start = Instant::now();
+        let scored = score_corpus(model, corpus);
+        let seconds = start.elapsed().as_secs_f64();
+        Self::from_scores(&scored, &model.config.thresholds, seconds)
+    }
+
+    /// Build a report from documents which have already been scored in scoring_seconds.
+    pub fn from_scores(
+        scored: &[ScoredDocument],
+        thresholds: &Thresholds,
+        scoring_seconds: f64,
+    ) -> Self {
+        let confusion = ConfusionMatrix::from_scores(scored, thresholds);
+        EvaluationReport {
+            thresholds: *thresholds,
+            confusion,
+            metrics: Metrics::from_confusion(&confusion),
+            timings: Timings {
+                scoring_seconds,
+                documents_per_second: if scoring_seconds > 0.0 {
+                    scored.len() as f64 / scoring_seconds
+                } else {
+                    0.0
+                },
+            },
+            reliability: ReliabilityBin::from_scores(scored, RELIABILITY_BINS),
+        }
+    }
+
+    /// The report as a human readable table.
+    pub fn to_text(&self) -> String {
+        let c = &self.confusion;
+        let mut text = format!(
+            "Confusion matrix (rows are the true label)\n\
+             {:>8} {:>8} {:>8} {:>8}\n\
+             {:>8} {:>8} {:>8} {:>8}\n\
+             {:>8} {:>8} {:>8} {:>8}\n\n",
+            "",
+            "spam",
+            "ham",
+            "unsure",
+            "spam",
+            c.spam_as_spam,
+            c.spam_as_ham,
+            c.spam_as_unsure,
+            "ham",
+            c.ham_as_spam,
+            c.ham_as_ham,
+            c.ham_as_unsure,
+        );
+        for (name, value) in Metrics::NAMES.iter().zip(self.metrics.values().iter()) {
+            text.push_str(&format!("{:<20} {:.4}\n", name, value));
+        }
+        text.push_str(&format!(
+            "\nThresholds: ham < {:.4}, spam > {:.4}\n\
+             Scored {} documents in {:.2}s ({:.1} documents/s)",
+            self.thresholds.ham,
+            self.thresholds.spam,
+            c.total(),
+            self.timings.scoring_seconds,
+            self.timings.documents_per_second
+        ));
+        text.push_str(&format!(
+            "\n\nReliability (expected calibration error {:.4})\n{:>13} {:>9} {:>10} {:>10}\n",
+            ReliabilityBin::calibration_error(&self.reliability),
+            "probability",
+            "documents",
+            "mean",
+            "spam"
+        ));
+        for b in &self.reliability {
+            text.push_str(&format!(
+                "  {:.2} - {:.2} {:>9} {:>10.4} {:>10.4}\n",
+                b.lower, b.upper, b.documents, b.mean_probability, b.spam_fraction
+            ));
+        }
+        text.pop();
+        text
+    }
+
+    /// The report as pretty printed json.
+    pub fn to_json(&self) -> Option<String> {
+        serde_json::to_string_pretty(self).ok()
+    }
+
+    /// The report as a csv header line and a single csv row, so reports from many runs can be
+    /// appended to one file.
+    pub fn to_csv(&self) -> String {
+        format!("{}\n{}\n", Self::csv_header(), self.csv_row())
+    }
+
+    /// The csv column names used by [csv_row](#method.csv_row).
+    pub fn csv_header() -> String {
+        let mut columns = vec![
+            "ham_threshold",
+            "spam_threshold",
+            "spam_as_spam",
+            "spam_as_ham",
+            "spam_as_unsure",
+            "ham_as_spam",
+            "ham_as_ham",
+            "ham_as_unsure",
+        ];
+        columns.extend(Metrics::NAMES.iter());
+        columns.extend(["scoring_seconds", "documents_per_second"].iter());
+        columns.join(",")
+    }
+
+    /// The report as a single csv row, without a header.
+    pub fn csv_row(&self) -> String {
+        let c = &self.confusion;
+        let mut fields = vec![
+            self.thresholds.ham.to_string(),
+            self.thresholds.spam.to_string(),
+            c.spam_as_spam.to_string(),
+            c.spam_as_ham.to_string(),
+            c.spam_as_unsure.to_string(),
+            c.ham_as_spam.to_string(),
+            c.ham_as_ham.to_string(),
+            c.ham_as_unsure.to_string(),
+        ];
+        fields.extend(self.metrics.values().iter().map(|v| v.to_string()));
+        fields.push(self.timings.scoring_seconds.to_string());
+        fields.push(self.timings.documents_per_second.to_string());
+        fields.join(",")
+    }
+}
+
+/// Reports of one model on one corpus under every [Scoring](enum.Scoring.html), each with the
+/// model's thresholds, for comparing scoring methods.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct ScoringComparison {
+    /// Each scoring method and how the model did with it, in the order of
+    /// [Scoring::ALL](enum.Scoring.html#associatedconstant.ALL).
+    pub reports: Vec<(Scoring, EvaluationReport)>,
+}
+
+#[allow(missing_doc_code_examples)]
+impl ScoringComparison {
+    /// Score every document in corpus with model under each scoring method. A model's
+    /// [calibration](enum.Calibration.html) was fitted to the log odds of its own scoring method, so
+    /// it is only used for that method and the others are reported uncalibrated.
+    /// ```
+    /// # use rammer::{BagOfWords, Corpus, HSModel, Label, Scoring, ScoringComparison};
+    /// let model = HSModel::from_bows(BagOfWords::from("hello there"), BagOfWords::from("offer"));
+    /// let corpus = Corpus::new().add_folder("test_resources/test_data", Label::Ham).unwrap();
+    /// let comparison = ScoringComparison::evaluate(&model, &corpus);
+    /// assert_eq!(comparison.reports[1].0, Scoring::Complement);
+    /// assert_eq!(comparison.reports[1].1.confusion.ham(), 3);
+    /// ```
+    pub fn evaluate(model: &HSModel, corpus: &Corpus) -> Self {
+        ScoringComparison {
+            reports: Scoring::ALL
+                .iter()
+                .map(|&scoring| {
+                    let mut other = model.clone().with_scoring(scoring);
+                    if scoring != model.config.scoring {
+                        other.config.calibration = None;
+                    }
+                    (scoring, EvaluationReport::evaluate(&other, corpus))
+                })
+                .collect(),
+        }
+    }
+
+    /// The metrics of every scoring method side by side, as a human readable table.
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{:<20}", "");
+        for (scoring, _) in &self.reports {
+            text.push_str(&format!(" {:>12}", scoring.to_string()));
+        }
+        text.push('\n');
+        for (i, name) in Metrics::NAMES.iter().enumerate() {
+            text.push_str(&format!("{:<20}", name));
+            for (_, report) in &self.reports {
+                text.push_str(&format!(" {:>12.4}", report.metrics.values()[i]));
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    /// The comparison as pretty printed json.
+    pub fn to_json(&self) -> Option<String> {
+        serde_json::to_string_pretty(self).ok()
+    }
+
+    /// The comparison as csv, with a row for each scoring method.
+    pub fn to_csv(&self) -> String {
+        let mut csv = format!("scoring,{}\n", EvaluationReport::csv_header());
+        for (scoring, report) in &self.reports {
+            csv.push_str(&format!("{},{}\n", scoring, report.csv_row()));
+        }
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scored(label: Label, probability: Probability) -> ScoredDocument {
+        ScoredDocument {
+            path: PathBuf::from("x"),
+            label,
+            probability,
+            log_odds: crate::logit(probability),
+        }
+    }
+
+    #[test]
+    fn confusion_counts_unsure() {
+        let docs = vec![
+            scored(Label::Spam, 0.99),
+            scored(Label::Spam, 0.5),
+            scored(Label::Spam, 0.01),
+            scored(Label::Ham, 0.01),
+            scored(Label::Ham, 0.95),
+        ];
+        let m = ConfusionMatrix::from_scores(&docs, &Thresholds::default());
+        assert_eq!(
+            m,
+            ConfusionMatrix {
+                spam_as_spam: 1,
+                spam_as_ham: 1,
+                spam_as_unsure: 1,
+                ham_as_spam: 1,
+                ham_as_ham: 1,
+                ham_as_unsure: 0,
+            }
+        );
+        assert_eq!((m.spam(), m.ham(), m.total()), (3, 2, 5));
+    }
+
+    #[test]
+    fn metrics_from_confusion() {
+        let m = ConfusionMatrix {
+            spam_as_spam: 6,
+            spam_as_ham: 1,
+            spam_as_unsure: 1,
+            ham_as_spam: 2,
+            ham_as_ham: 8,
+            ham_as_unsure: 2,
+        };
+        let metrics = Metrics::from_confusion(&m);
+        assert_eq!(metrics.accuracy, 14.0 / 20.0);
+        assert_eq!(metrics.precision, 6.0 / 8.0);
+        assert_eq!(metrics.recall, 6.0 / 8.0);
+        assert_eq!(metrics.f1, 0.75);
+        assert_eq!(metrics.false_positive_rate, 2.0 / 12.0);
+        assert_eq!(metrics.false_negative_rate, 1.0 / 8.0);
+        assert_eq!(metrics.unsure_rate, 3.0 / 20.0);
+    }
+
+    #[test]
+    fn empty_metrics_are_zero() {
+        assert_eq!(
+            Metrics::from_confusion(&ConfusionMatrix::new()),
+            Metrics::default()
+        );
+    }
+
+    #[test]
+    fn csv_header_matches_row() {
+        let report =
+            EvaluationReport::from_scores(&[scored(Label::Ham, 0.1)], &Thresholds::default(), 2.0);
+        let csv = report.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].split(',').count(), lines[1].split(',').count());
+        assert_eq!(report.timings.documents_per_second, 0.5);
+    }
+
+    #[test]
+    fn comparison_has_a_row_for_each_scoring() {
+        let model = crate::HSModel::from_bows(
+            crate::BagOfWords::from("hello there world"),
+            crate::BagOfWords::from("hello offer"),
+        );
+        let corpus = Corpus::new()
+            .add_folder("test_resources/test_data", Label::Ham)
+            .unwrap();
+        let comparison = ScoringComparison::evaluate(&model, &corpus);
+        let scorings: Vec<Scoring> = comparison.reports.iter().map(|(s, _)| *s).collect();
+        assert_eq!(scorings, Scoring::ALL.to_vec());
+        assert_eq!(
+            comparison.reports[0].1.confusion,
+            EvaluationReport::evaluate(&model, &corpus).confusion
+        );
+        let csv = comparison.to_csv();
+        assert_eq!(csv.lines().count(), 1 + Scoring::ALL.len());
+        assert!(csv.lines().nth(2).unwrap().starts_with("complement,"));
+        assert_eq!(
+            comparison.to_text().lines().count(),
+            1 + Metrics::NAMES.len()
+        );
+    }
+
+    #[test]
+    fn comparison_calibrates_only_the_fitted_scoring() {
+        let model = crate::HSModel::from_bows(
+            crate::BagOfWords::from("hello there world"),
+            crate::BagOfWords::from("hello offer"),
+        );
+        let corpus = Corpus::new()
+            .add_folder("test_resources/test_data", Label::Ham)
+            .unwrap();
+        // Calibrated to call everything spam, whatever its log odds.
+        let calibrated = model
+            .clone()
+            .with_scoring(Scoring::Complement)
+            .with_calibration(crate::Calibration::Platt {
+                slope: 0.0,
+                intercept: 10.0,
+            });
+        let comparison = ScoringComparison::evaluate(&calibrated, &corpus);
+        for (scoring, report) in &comparison.reports {
+            if *scoring == Scoring::Complement {
+                assert_eq!(report.confusion.ham_as_spam, 3);
+            } else {
+                let uncalibrated = model.clone().with_scoring(*scoring);
+                let expected = EvaluationReport::evaluate(&uncalibrated, &corpus);
+                assert_eq!(report.confusion, expected.confusion, "{}", scoring);
+                assert_eq!(report.reliability, expected.reliability, "{}", scoring);
+            }
+        }
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let report =
+            EvaluationReport::from_scores(&[scored(Label::Spam, 0.9)], &Thresholds::default(), 1.0);
+        let json = report.to_json().unwrap();
+        let parsed: EvaluationReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, report);
+    }
+}
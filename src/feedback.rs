@@ -0,0 +1,237 @@
+//! Feedback training updates an existing HSModel with mail a user has already sorted, such as the
+//! messages in their Junk folder. The messages which have been learned are remembered in
+//! LearnedMessages, so running the same training twice does not count a message twice.
+//! ```no_run
+//! use rammer::{HSModel, LearnedMessages, Maildir};
+//! let model = HSModel::read_from_json("out/models/enron1_model.json").unwrap();
+//! let mut learned = LearnedMessages::read_from_json("out/models/enron1_model.learned.json")
+//!     .unwrap_or_default();
+//! let corpus = Maildir::new("/home/dalton/Maildir").corpus().expect("Maildir not found");
+//! let (model, summary) = model.retrain(&corpus, &mut learned);
+//! model.write_to_json("out/models/enron1_model.json").expect("Write unsucessful");
+//! learned.write_to_json("out/models/enron1_model.learned.json").expect("Write unsucessful");
+//! ```
+use std::{collections::HashMap, fs, path::Path, time::UNIX_EPOCH};
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{message_id, BagOfWords, Corpus, HSModel, Label, Timestamp};
+
+/// The message ids an HSModel has been trained with, and the Label each was learned as.
+/// ```
+/// # use rammer::{Label, LearnedMessages};
+/// let learned = LearnedMessages::new();
+/// assert_eq!(learned.label("<1234@example.com>"), None);
+/// ```
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Default)]
+pub struct LearnedMessages {
+    #[serde(serialize_with = "crate::bag_of_words::serialize_sorted")]
+    messages: HashMap<String, Label>,
+}
+
+#[allow(missing_doc_code_examples)]
+impl LearnedMessages {
+    /// Create a new empty set of learned messages.
+    pub fn new() -> Self {
+        LearnedMessages {
+            messages: HashMap::new(),
+        }
+    }
+
+    /// The Label a message was learned as, or None if it has not been learned.
+    pub fn label(&self, id: &str) -> Option<Label> {
+        self.messages.get(id).copied()
+    }
+
+    /// Number of learned messages.
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// Returns true if no messages have been learned.
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Serialize the learned messages to a json string and write it to file_path. This write is
+    /// destructive.
+    /// ```no_run
+    /// # use rammer::LearnedMessages;
+    /// LearnedMessages::new().write_to_json("out/models/model.learned.json").expect("Write unsucessful");
+    /// ```
+    pub fn write_to_json(&self, file_path: &str) -> Option<()> {
+        let serialized = serde_json::to_string(self).ok()?;
+        fs::write(file_path, serialized).ok()
+    }
+
+    /// Read a json string from file_path and deserialize it to LearnedMessages.
+    /// ```no_run
+    /// # use rammer::LearnedMessages;
+    /// let learned = LearnedMessages::read_from_json("out/models/model.learned.json").unwrap_or_default();
+    /// ```
+    pub fn read_from_json(file_path: &str) -> Option<Self> {
+        let serialized = fs::read_to_string(file_path).ok()?;
+        serde_json::from_str(&serialized[..]).ok()
+    }
+}
+
+/// What happened during a [retrain](struct.HSModel.html#method.retrain).
+#[derive(PartialEq, Eq, Debug, Serialize, Clone, Copy, Default)]
+pub struct RetrainSummary {
+    /// Messages learned as ham for the first time.
+    pub learned_ham: usize,
+    /// Messages learned as spam for the first time.
+    pub learned_spam: usize,
+    /// Messages which were learned before with the other Label, and were moved to their new Label.
+    pub relearned: usize,
+    /// Messages which were already learned with the same Label.
+    pub skipped: usize,
+    /// Messages which could not be read.
+    pub unreadable: usize,
+}
+
+#[allow(missing_doc_code_examples)]
+impl HSModel {
+    /// Train the model with every message in corpus which has not been learned yet, recording the
+    /// message ids in learned. A message which was learned before under the other Label, for
+    /// example ham that a user later moved to their Junk folder, has its words moved from the old
+    /// BagOfWords to the new one. Learned tokens are stamped with the time each message was
+    /// delivered, so the model can later be [decayed](struct.HSModel.html#method.decay).
+    /// ```
+    /// # use rammer::{HSModel, LearnedMessages, Maildir};
+    /// let corpus = Maildir::new("test_resources/test_maildir").corpus().unwrap();
+    /// let mut learned = LearnedMessages::new();
+    /// let (model, summary) = HSModel::new().retrain(&corpus, &mut learned);
+    /// assert_eq!(summary.learned_spam, 1);
+    /// let (model, summary) = model.retrain(&corpus, &mut learned);
+    /// assert_eq!(summary.skipped, 4); // nothing is counted twice
+    /// ```
+    pub fn retrain(
+        mut self,
+        corpus: &Corpus,
+        learned: &mut LearnedMessages,
+    ) -> (Self, RetrainSummary) {
+        let mut summary = RetrainSummary::default();
+        let messages: Vec<Option<(String, Label, Option<BagOfWords>)>> = corpus
+            .documents
+            .par_iter()
+            .map(|doc| {
+                let text = doc.text()?;
+                let id = message_id(&doc.path, &text);
+                if learned.label(&id) == Some(doc.label) {
+                    Some((id, doc.label, None))
+                } else {
+                    let bow = BagOfWords::from(&text[..]);
+                    let bow = match delivered_at(&doc.path) {
+                        Some(time) => bow.seen_at(time),
+                        None => bow,
+                    };
+                    Some((id, doc.label, Some(bow)))
+                }
+            })
+            .collect();
+
+        for message in messages {
+            let (id, label, bow) = match message {
+                Some((id, label, Some(bow))) => (id, label, bow),
+                Some(_) => {
+                    summary.skipped += 1;
+                    continue;
+                }
+                None => {
+                    summary.unreadable += 1;
+                    continue;
+                }
+            };
+
+            match learned.messages.insert(id, label) {
+                Some(old) if old == label => {
+                    summary.skipped += 1;
+                    continue;
+                }
+                Some(Label::Ham) => {
+                    self.ham_bow = self.ham_bow.subtract(bow.clone());
+                    summary.relearned += 1;
+                }
+                Some(Label::Spam) => {
+                    self.spam_bow = self.spam_bow.subtract(bow.clone());
+                    summary.relearned += 1;
+                }
+                None if label == Label::Ham => summary.learned_ham += 1,
+                None => summary.learned_spam += 1,
+            }
+
+            self = match label {
+                Label::Ham => self.add_ham_bow(bow),
+                Label::Spam => self.add_spam_bow(bow),
+            };
+        }
+
+        (self, summary)
+    }
+}
+
+/// When a message was delivered, taken from its file's modification time as maildir delivery
+/// never changes it afterwards.
+fn delivered_at(path: &Path) -> Option<Timestamp> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Maildir;
+
+    #[test]
+    fn retrain_learns_each_message_once() {
+        let corpus = Maildir::new("test_resources/test_maildir")
+            .corpus()
+            .unwrap();
+        let mut learned = LearnedMessages::new();
+        let (model, summary) = HSModel::new().retrain(&corpus, &mut learned);
+        assert_eq!(summary.learned_ham, 3);
+        assert_eq!(summary.learned_spam, 1);
+        assert_eq!(learned.len(), 4);
+
+        let once = model.clone();
+        let (model, summary) = model.retrain(&corpus, &mut learned);
+        assert_eq!(summary.skipped, 4);
+        assert_eq!(model.ham_bow, once.ham_bow);
+        assert_eq!(model.spam_bow, once.spam_bow);
+    }
+
+    #[test]
+    fn retrain_moves_relabelled_messages() {
+        let mut learned = LearnedMessages::new();
+        let inbox = Maildir::unlabelled("test_resources/test_maildir")
+            .label_folder(".Junk", Label::Ham)
+            .corpus()
+            .unwrap();
+        let (model, _) = HSModel::new().retrain(&inbox, &mut learned);
+        assert_eq!(learned.label("<spam1@example.net>"), Some(Label::Ham));
+
+        let junk = Maildir::unlabelled("test_resources/test_maildir")
+            .label_folder(".Junk", Label::Spam)
+            .corpus()
+            .unwrap();
+        let (model, summary) = model.retrain(&junk, &mut learned);
+        assert_eq!(summary.relearned, 1);
+        assert_eq!(learned.label("<spam1@example.net>"), Some(Label::Spam));
+        assert_eq!(model.ham_bow, BagOfWords::new());
+        assert_eq!(model.spam_bow, junk.bows().1);
+    }
+
+    #[test]
+    fn retrain_stamps_delivery_time() {
+        let corpus = Maildir::new("test_resources/test_maildir")
+            .corpus()
+            .unwrap();
+        let (model, _) = HSModel::new().retrain(&corpus, &mut LearnedMessages::new());
+        let junk = "test_resources/test_maildir/.Junk/cur/1600000003.M4P4.example:2,S";
+        let delivered = delivered_at(Path::new(junk));
+        assert!(delivered.is_some());
+        assert_eq!(model.spam_bow.last_seen("offer"), delivered);
+    }
+}
@@ -0,0 +1,289 @@
+//! A BagOfWords in a fixed amount of memory, for keeping many models at once.
+//! A [BagOfWords](struct.BagOfWords.html) keeps every distinct token it has seen, so its size
+//! grows with the vocabulary and an adversary can grow it without bound. A HashedBagOfWords
+//! instead adds each token's count to one of a fixed number of buckets, chosen by a seeded hash of
+//! the token, and never stores the token itself. Tokens which hash to the same bucket share a
+//! count, so a model built from hashed bags scores like one where colliding tokens are the same
+//! word. More buckets mean fewer collisions; see [HashedModel](struct.HashedModel.html) for how
+//! many are lost at each size.
+//! ```
+//! use rammer::HashedBagOfWords;
+//! let bag = HashedBagOfWords::new(1 << 16, 7).unwrap().add_text("hello there, hello");
+//! assert_eq!(bag.count("hello"), 2);
+//! assert_eq!(bag.total_word_count(), 4);
+//! ```
+use std::convert::TryFrom;
+
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{BagOfWords, Count};
+
+/// Token counts in a fixed number of buckets, indexed by a seeded hash of each token.
+/// Only bags with the same number of buckets and seed can be combined. Deserializing fails unless
+/// there is at least one bucket and the total is the sum of the counts.
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
+#[serde(try_from = "HashedBagFile")]
+pub struct HashedBagOfWords {
+    /// Seed mixed into every token's hash.
+    seed: u64,
+    /// Count of every bucket.
+    counts: Vec<Count>,
+    /// Sum of the counts, kept so scoring doesn't add up every bucket.
+    total: Count,
+}
+
+#[allow(missing_doc_code_examples)]
+impl HashedBagOfWords {
+    /// Create an empty bag with the given number of buckets, at least 1, whose tokens are hashed
+    /// with seed. Each bucket takes 8 bytes. Returns None if the buckets' size in bytes doesn't
+    /// fit in an isize, the most any allocation can be.
+    /// ```
+    /// # use rammer::HashedBagOfWords;
+    /// let bag = HashedBagOfWords::new(1024, 0).unwrap();
+    /// assert_eq!((bag.buckets(), bag.occupied()), (1024, 0));
+    /// assert!(HashedBagOfWords::new(usize::MAX, 0).is_none());
+    /// ```
+    pub fn new(buckets: usize, seed: u64) -> Option<Self> {
+        let buckets = buckets.max(1);
+        let bytes = buckets.checked_mul(std::mem::size_of::<Count>())?;
+        if bytes > isize::MAX as usize {
+            return None;
+        }
+        Some(HashedBagOfWords {
+            seed,
+            counts: vec![0; buckets],
+            total: 0,
+        })
+    }
+
+    /// Builder pattern for counting every token of a slice of text, split and uppercased the
+    /// same way as [BagOfWords::from](struct.BagOfWords.html#impl-From%3C%26str%3E).
+    /// ```
+    /// # use rammer::HashedBagOfWords;
+    /// let bag = HashedBagOfWords::new(64, 0).unwrap().add_text("Offer").add_text("offer");
+    /// assert_eq!(bag.count("OFFER"), 2);
+    /// ```
+    pub fn add_text(mut self, text: &str) -> Self {
+        for word in text.split_word_bounds().filter(|&s| !s.trim().is_empty()) {
+            self.add(&word.to_uppercase(), 1);
+        }
+        self
+    }
+
+    /// Hash the counts of an exact BagOfWords into a new bag with the given buckets and seed.
+    /// Returns None if there are too many buckets to allocate, like [new](#method.new).
+    /// ```
+    /// # use rammer::{BagOfWords, HashedBagOfWords};
+    /// let bow = BagOfWords::from("hello hello offer");
+    /// let bag = HashedBagOfWords::from_bow(&bow, 4096, 1).unwrap();
+    /// assert_eq!(bag.total_word_count(), bow.total_word_count());
+    /// ```
+    pub fn from_bow(bow: &BagOfWords, buckets: usize, seed: u64) -> Option<Self> {
+        let mut bag = HashedBagOfWords::new(buckets, seed)?;
+        for (token, count) in bow.tokens() {
+            bag.add(token, count);
+        }
+        Some(bag)
+    }
+
+    /// Add the counts of other to this bag, like
+    /// [BagOfWords::combine](struct.BagOfWords.html#method.combine). Returns None if the bags
+    /// have different numbers of buckets or seeds, as their buckets hold different tokens.
+    /// ```
+    /// # use rammer::HashedBagOfWords;
+    /// let a = HashedBagOfWords::new(64, 0).unwrap().add_text("hello");
+    /// let b = HashedBagOfWords::new(64, 0).unwrap().add_text("hello offer");
+    /// assert_eq!(a.clone().combine(b).unwrap().count("hello"), 2);
+    /// assert!(a.combine(HashedBagOfWords::new(64, 1).unwrap()).is_none());
+    /// ```
+    pub fn combine(mut self, other: Self) -> Option<Self> {
+        if self.seed != other.seed || self.counts.len() != other.counts.len() {
+            return None;
+        }
+        for (count, other) in self.counts.iter_mut().zip(other.counts) {
+            *count = count.saturating_add(other);
+        }
+        self.total = self.total.saturating_add(other.total);
+        Some(self)
+    }
+
+    /// Count of the bucket word hashes to, which includes every token colliding with it. The
+    /// word is uppercased first.
+    pub fn count(&self, word: &str) -> Count {
+        self.token_count(&word.to_uppercase())
+    }
+
+    /// Total count of every token added.
+    pub fn total_word_count(&self) -> Count {
+        self.total
+    }
+
+    /// Number of buckets.
+    pub fn buckets(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// Seed the tokens are hashed with.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Number of buckets with a count, at most the number of distinct tokens added.
+    pub fn occupied(&self) -> usize {
+        self.counts.iter().filter(|&&count| count > 0).count()
+    }
+
+    /// Count of an already uppercased token's bucket.
+    pub(crate) fn token_count(&self, token: &str) -> Count {
+        self.counts[self.bucket(token)]
+    }
+
+    /// The count of every bucket, in bucket order.
+    pub(crate) fn bucket_counts(&self) -> &[Count] {
+        &self.counts
+    }
+
+    /// Index of the bucket an already uppercased token is counted in.
+    pub(crate) fn bucket(&self, token: &str) -> usize {
+        bucket(token, self.seed, self.counts.len())
+    }
+
+    fn add(&mut self, token: &str, count: Count) {
+        let i = self.bucket(token);
+        self.counts[i] = self.counts[i].saturating_add(count);
+        self.total = self.total.saturating_add(count);
+    }
+}
+
+/// The fields of a HashedBagOfWords as read, before they are checked.
+#[derive(Deserialize)]
+struct HashedBagFile {
+    seed: u64,
+    counts: Vec<Count>,
+    total: Count,
+}
+
+impl TryFrom<HashedBagFile> for HashedBagOfWords {
+    type Error = String;
+
+    #[allow(missing_doc_code_examples)]
+    fn try_from(file: HashedBagFile) -> Result<Self, Self::Error> {
+        if file.counts.is_empty() {
+            return Err("a hashed bag needs at least one bucket".to_string());
+        }
+        let total = file
+            .counts
+            .iter()
+            .fold(0 as Count, |total, &count| total.saturating_add(count));
+        if total != file.total {
+            return Err(format!(
+                "hashed bag total {} is not the sum of its counts, {}",
+                file.total, total
+            ));
+        }
+        Ok(HashedBagOfWords {
+            seed: file.seed,
+            counts: file.counts,
+            total,
+        })
+    }
+}
+
+/// Combine bags with [combine](struct.HashedBagOfWords.html#method.combine). Gives None for no
+/// bags, or bags with different numbers of buckets or seeds.
+/// ```
+/// # use rammer::HashedBagOfWords;
+/// let bag: Option<HashedBagOfWords> = vec!["hello", "offer", "hello"]
+///     .into_iter()
+///     .map(|text| HashedBagOfWords::new(64, 0).unwrap().add_text(text))
+///     .collect();
+/// assert_eq!(bag.unwrap().count("hello"), 2);
+/// ```
+impl std::iter::FromIterator<HashedBagOfWords> for Option<HashedBagOfWords> {
+    #[allow(missing_doc_code_examples)]
+    fn from_iter<I: IntoIterator<Item = HashedBagOfWords>>(iter: I) -> Self {
+        let mut iter = iter.into_iter();
+        let first = iter.next()?;
+        iter.try_fold(first, HashedBagOfWords::combine)
+    }
+}
+
+/// The bucket of token among buckets, from its 64 bit FNV-1a hash, seeded by hashing the seed's
+/// bytes first and mixed with the SplitMix64 finalizer. Written out rather than using the
+/// standard library's hasher so saved bags hash the same on every platform and release.
+pub(crate) fn bucket(token: &str, seed: u64, buckets: usize) -> usize {
+    let mut h: u64 = 0xCBF2_9CE4_8422_2325;
+    for &byte in seed.to_le_bytes().iter().chain(token.as_bytes()) {
+        h ^= u64::from(byte);
+        h = h.wrapping_mul(0x0000_0100_0000_01B3);
+    }
+    h = (h ^ (h >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    h ^= h >> 31;
+    ((u128::from(h) * buckets as u128) >> 64) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_are_stable() {
+        // Saved bags depend on these never changing.
+        assert_eq!(bucket("HELLO", 0, 1 << 20), bucket("HELLO", 0, 1 << 20));
+        assert_ne!(bucket("HELLO", 0, 1 << 20), bucket("HELLO", 1, 1 << 20));
+        assert_eq!(bucket("", 0, 1), 0);
+        let tokens = ["HELLO", "OFFER", "FREE", "MEETING", "ÜNÏCÖDÉ"];
+        let buckets: Vec<usize> = tokens.iter().map(|t| bucket(t, 42, 1000)).collect();
+        assert_eq!(buckets, vec![833, 45, 356, 732, 672]);
+    }
+
+    #[test]
+    fn matches_exact_counts_without_collisions() {
+        let text = "free offer, free lunch! meeting at noon";
+        let exact = BagOfWords::from(text);
+        let bag = HashedBagOfWords::new(1 << 20, 3).unwrap().add_text(text);
+        assert_eq!(
+            Some(bag.clone()),
+            HashedBagOfWords::from_bow(&exact, 1 << 20, 3)
+        );
+        for (token, count) in exact.tokens() {
+            assert_eq!(bag.token_count(token), count, "{}", token);
+        }
+        assert_eq!(bag.occupied(), exact.len());
+    }
+
+    #[test]
+    fn invalid_bags_do_not_deserialize() {
+        let bag = HashedBagOfWords::new(8, 5)
+            .unwrap()
+            .add_text("hello offer offer");
+        let json = serde_json::to_string(&bag).unwrap();
+        assert_eq!(
+            serde_json::from_str::<HashedBagOfWords>(&json).unwrap(),
+            bag
+        );
+        let rejected = |json: &str| serde_json::from_str::<HashedBagOfWords>(json).is_err();
+        assert!(rejected(r#"{"seed":0,"counts":[],"total":0}"#));
+        assert!(rejected(r#"{"seed":0,"counts":[1,2],"total":4}"#));
+        assert!(!rejected(r#"{"seed":0,"counts":[1,2],"total":3}"#));
+    }
+
+    #[test]
+    fn colliding_tokens_share_a_count() {
+        let bag = HashedBagOfWords::new(1, 0)
+            .unwrap()
+            .add_text("hello offer offer");
+        assert_eq!((bag.count("hello"), bag.count("anything")), (3, 3));
+        let none: Option<HashedBagOfWords> = Vec::new().into_iter().collect();
+        assert!(none.is_none());
+        let mismatched: Option<HashedBagOfWords> = vec![
+            HashedBagOfWords::new(2, 0).unwrap(),
+            HashedBagOfWords::new(3, 0).unwrap(),
+        ]
+        .into_iter()
+        .collect();
+        assert!(mismatched.is_none());
+    }
+}
@@ -0,0 +1,500 @@
+//! A model made of two [HashedBagOfWords](struct.HashedBagOfWords.html), whose memory is fixed by
+//! its number of buckets rather than its vocabulary. Scoring goes through
+//! [TokenCounts](trait.TokenCounts.html) like [HSModel](struct.HSModel.html), with each token's
+//! counts read from its bucket, so a hashed model scores exactly like the exact model when no
+//! tokens collide. When they do, colliding tokens pool their counts, and an unseen token whose
+//! bucket is occupied is scored with the counts of whatever landed there.
+//!
+//! [HashingReport](struct.HashingReport.html) measures how many tokens collide and what that does
+//! to accuracy. A multinomial model trained on enron1 has 50,449 distinct tokens and takes 457 KiB
+//! in the binary model format. Validated on enron2 with the default thresholds, where the exact
+//! model's accuracy is 0.8392, hashing it with seeds 0 to 4 gave:
+//!
+//! | buckets | memory  | colliding tokens | expected | accuracy      |
+//! |---------|---------|------------------|----------|---------------|
+//! | 2^14    | 256 KiB | 95.4%            | 95.4%    | 0.746 - 0.794 |
+//! | 2^16    | 1 MiB   | 53.6%            | 53.7%    | 0.814 - 0.851 |
+//! | 2^18    | 4 MiB   | 17.5%            | 17.5%    | 0.831 - 0.863 |
+//! | 2^20    | 16 MiB  | 4.8%             | 4.7%     | 0.837 - 0.839 |
+//!
+//! Collisions match what a uniformly random hash would give. Until most tokens have a bucket to
+//! themselves the seed matters as much as the size, and pooled counts are sometimes better than
+//! exact ones. Hashing bounds the memory of a model whose vocabulary keeps growing, such as one
+//! retrained from a mailbox forever; to shrink a model of this size,
+//! [pruning](struct.PruneReport.html) does better.
+//! ```
+//! use rammer::{BagOfWords, HSModel};
+//! let model = HSModel::from_bows(BagOfWords::from("hello lunch meeting"), BagOfWords::from("hello free offer"));
+//! let hashed = model.to_hashed(1 << 16, 0).unwrap();
+//! assert_eq!(hashed.text_spam_probability("free lunch"), model.text_spam_probability("free lunch"));
+//! ```
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    fs,
+    time::Instant,
+};
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    complement::ComplementWeights, evaluation::ScoredDocument, scoring::counts_text_log_odds,
+    Classification, ClassifierConfig, Corpus, Count, Document, EvaluationReport, HSModel,
+    HashedBagOfWords, Metrics, Probability, Scoring, TokenCounts,
+};
+
+/// A model of hashed token counts, using a fixed amount of memory however many tokens it sees.
+/// Its complement weights aren't saved, but worked out again when it is read, and reading fails
+/// for bags with different numbers of buckets or seeds, or a scoring hashed models can't use.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+#[serde(try_from = "HashedModelFile")]
+pub struct HashedModel {
+    ham_bow: HashedBagOfWords,
+    spam_bow: HashedBagOfWords,
+    /// Weights for complement scoring, worked out from every bucket when the scoring is set.
+    #[serde(skip)]
+    complement: Option<ComplementWeights>,
+    /// Settings used when classifying text. Use [with_scoring](#method.with_scoring) rather than
+    /// changing the scoring here.
+    pub config: ClassifierConfig,
+}
+
+#[allow(missing_doc_code_examples)]
+impl HSModel {
+    /// Hash the model's counts into a [HashedModel](struct.HashedModel.html) with the given
+    /// number of buckets and seed in each bag, keeping its config. Returns None if the model uses
+    /// [Bernoulli scoring](enum.Scoring.html#variant.Bernoulli), as hashed bags only hold counts
+    /// and not document frequencies, or if there are too many buckets to allocate.
+    /// ```
+    /// # use rammer::{BagOfWords, HSModel, Scoring};
+    /// let model = HSModel::from_bows(BagOfWords::from("hello"), BagOfWords::from("offer"));
+    /// assert_eq!(model.to_hashed(1024, 0).unwrap().ham_bow().buckets(), 1024);
+    /// assert!(model.to_hashed(usize::MAX, 0).is_none());
+    /// assert!(model.with_scoring(Scoring::Bernoulli).to_hashed(1024, 0).is_none());
+    /// ```
+    pub fn to_hashed(&self, buckets: usize, seed: u64) -> Option<HashedModel> {
+        let model = HashedModel::from_bows(
+            HashedBagOfWords::from_bow(&self.ham_bow, buckets, seed)?,
+            HashedBagOfWords::from_bow(&self.spam_bow, buckets, seed)?,
+        )?;
+        let config = self.config.clone();
+        let scoring = config.scoring;
+        HashedModel { config, ..model }.with_scoring(scoring)
+    }
+}
+
+#[allow(missing_doc_code_examples)]
+impl HashedModel {
+    /// Create a model from hashed ham and spam bags with the default config. Returns None if the
+    /// bags have different numbers of buckets or seeds.
+    /// ```
+    /// # use rammer::{HashedBagOfWords, HashedModel};
+    /// let ham = HashedBagOfWords::new(1024, 0).unwrap().add_text("hello lunch offer");
+    /// let spam = HashedBagOfWords::new(1024, 0).unwrap().add_text("hello offer offer");
+    /// let model = HashedModel::from_bows(ham, spam).unwrap();
+    /// assert!(model.text_spam_probability("offer") > 0.5);
+    /// ```
+    pub fn from_bows(ham_bow: HashedBagOfWords, spam_bow: HashedBagOfWords) -> Option<Self> {
+        if ham_bow.buckets() != spam_bow.buckets() || ham_bow.seed() != spam_bow.seed() {
+            return None;
+        }
+        Some(HashedModel {
+            ham_bow,
+            spam_bow,
+            complement: None,
+            config: ClassifierConfig::default(),
+        })
+    }
+
+    /// Builder pattern for setting how text is scored. Complement scoring treats each bucket as a
+    /// token and reads every bucket once to work out its weights. Returns None for Bernoulli
+    /// scoring, which needs document frequencies hashed bags don't have.
+    /// ```
+    /// # use rammer::{HashedBagOfWords, HashedModel, Scoring};
+    /// let bag = HashedBagOfWords::new(1024, 0).unwrap();
+    /// let model = HashedModel::from_bows(bag.clone(), bag).unwrap();
+    /// let model = model.with_scoring(Scoring::Complement).unwrap();
+    /// assert!(model.with_scoring(Scoring::Bernoulli).is_none());
+    /// ```
+    pub fn with_scoring(mut self, scoring: Scoring) -> Option<Self> {
+        self.config.scoring = scoring;
+        self.complement = match scoring {
+            Scoring::Multinomial => None,
+            Scoring::Complement => Some(ComplementWeights::new(
+                self.ham_bow
+                    .bucket_counts()
+                    .iter()
+                    .cloned()
+                    .zip(self.spam_bow.bucket_counts().iter().cloned()),
+                self.totals(),
+            )),
+            Scoring::Bernoulli => return None,
+        };
+        Some(self)
+    }
+
+    /// The hashed ham counts.
+    pub fn ham_bow(&self) -> &HashedBagOfWords {
+        &self.ham_bow
+    }
+
+    /// The hashed spam counts.
+    pub fn spam_bow(&self) -> &HashedBagOfWords {
+        &self.spam_bow
+    }
+
+    /// Bytes taken by the counts of both bags, which never grows as text is added.
+    pub fn memory_bytes(&self) -> usize {
+        (self.ham_bow.buckets() + self.spam_bow.buckets()) * std::mem::size_of::<Count>()
+    }
+
+    /// Returns the probability that a slice of text is spam, calibrated like
+    /// [HSModel::text_spam_probability](struct.HSModel.html#method.text_spam_probability).
+    pub fn text_spam_probability(&self, text: &str) -> Probability {
+        self.config.probability(self.text_log_odds(text))
+    }
+
+    /// Returns the log odds that a slice of text is spam, scored like
+    /// [HSModel::text_log_odds](struct.HSModel.html#method.text_log_odds) with each token's
+    /// counts read from its bucket.
+    pub fn text_log_odds(&self, text: &str) -> f64 {
+        counts_text_log_odds(
+            self,
+            self.complement.as_ref(),
+            self.config.significant,
+            text,
+        )
+    }
+
+    /// Classify a slice of text as spam, ham or unsure using the model's
+    /// [Thresholds](struct.Thresholds.html).
+    pub fn classify(&self, text: &str) -> Classification {
+        self.config
+            .thresholds
+            .classify(self.text_spam_probability(text))
+    }
+
+    /// Serialize the model to json and write it to file_path. This write is destructive.
+    /// Returns None if the model could not be written.
+    /// ```no_run
+    /// # use rammer::{BagOfWords, HSModel};
+    /// # let model = HSModel::from_bows(BagOfWords::from("hello"), BagOfWords::from("offer"));
+    /// let hashed = model.to_hashed(1 << 20, 0).unwrap();
+    /// hashed.write_to_json("out/models/hashed.json").expect("Write unsucessful");
+    /// ```
+    pub fn write_to_json(&self, file_path: &str) -> Option<()> {
+        fs::write(file_path, serde_json::to_string(self).ok()?).ok()
+    }
+
+    /// Read a model written by [write_to_json](#method.write_to_json), working out its complement
+    /// weights again if it uses complement scoring.
+    /// ```no_run
+    /// # use rammer::HashedModel;
+    /// let model = HashedModel::read_from_json("out/models/hashed.json").unwrap();
+    /// ```
+    pub fn read_from_json(file_path: &str) -> Option<Self> {
+        serde_json::from_slice(&fs::read(file_path).ok()?).ok()
+    }
+}
+
+/// The saved fields of a HashedModel, before its bags are checked and its weights worked out.
+#[derive(Deserialize)]
+struct HashedModelFile {
+    ham_bow: HashedBagOfWords,
+    spam_bow: HashedBagOfWords,
+    #[serde(default)]
+    config: ClassifierConfig,
+}
+
+impl TryFrom<HashedModelFile> for HashedModel {
+    type Error = String;
+
+    #[allow(missing_doc_code_examples)]
+    fn try_from(file: HashedModelFile) -> Result<Self, Self::Error> {
+        let model = HashedModel::from_bows(file.ham_bow, file.spam_bow)
+            .ok_or("hashed bags have different numbers of buckets or seeds")?;
+        let scoring = file.config.scoring;
+        HashedModel {
+            config: file.config,
+            ..model
+        }
+        .with_scoring(scoring)
+        .ok_or_else(|| format!("hashed models can't use {} scoring", scoring))
+    }
+}
+
+impl TokenCounts for HashedModel {
+    #[allow(missing_doc_code_examples)]
+    fn totals(&self) -> (Count, Count) {
+        (
+            self.ham_bow.total_word_count(),
+            self.spam_bow.total_word_count(),
+        )
+    }
+
+    #[allow(missing_doc_code_examples)]
+    fn counts(&self, token: &str) -> (Count, Count) {
+        (
+            self.ham_bow.token_count(token),
+            self.spam_bow.token_count(token),
+        )
+    }
+}
+
+/// How many of a model's tokens collide when it is hashed, and how the hashed model does on
+/// validation data compared to the exact one.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct HashingReport {
+    /// Buckets in each hashed bag.
+    pub buckets: usize,
+    /// Seed the tokens were hashed with.
+    pub seed: u64,
+    /// Distinct tokens in the exact model.
+    pub tokens: usize,
+    /// Buckets holding at least one token.
+    pub occupied_buckets: usize,
+    /// Tokens which share their bucket with at least one other token.
+    pub colliding_tokens: usize,
+    /// Colliding tokens expected if every token were put in a uniformly random bucket.
+    pub expected_colliding_tokens: f64,
+    /// Size of the exact model in the binary model format.
+    pub bytes_exact: usize,
+    /// Memory taken by the hashed model's counts.
+    pub bytes_hashed: usize,
+    /// The exact model evaluated on the validation data.
+    pub exact: EvaluationReport,
+    /// The hashed model evaluated on the validation data.
+    pub hashed: EvaluationReport,
+}
+
+#[allow(missing_doc_code_examples)]
+impl HashingReport {
+    /// Hash model into buckets with seed and compare it to the exact model on validation. Both
+    /// are classified with the model's thresholds. Returns None if the model uses Bernoulli
+    /// scoring or can't be encoded.
+    /// ```
+    /// # use rammer::{BagOfWords, Corpus, HashingReport, HSModel, Label};
+    /// let model = HSModel::from_bows(BagOfWords::from("hello there world"), BagOfWords::from("hello offer"));
+    /// let validation = Corpus::new().add_folder("test_resources/test_data", Label::Ham).unwrap();
+    /// let report = HashingReport::new(&model, 1, 0, &validation).unwrap();
+    /// assert_eq!((report.tokens, report.occupied_buckets, report.colliding_tokens), (4, 1, 4));
+    /// assert_eq!(report.collision_rate(), 1.0);
+    /// ```
+    pub fn new(model: &HSModel, buckets: usize, seed: u64, validation: &Corpus) -> Option<Self> {
+        let hashed = model.to_hashed(buckets, seed)?;
+        let tokens: HashSet<&str> = model
+            .ham_bow
+            .tokens()
+            .chain(model.spam_bow.tokens())
+            .map(|(token, _)| token)
+            .collect();
+        let mut per_bucket: HashMap<usize, usize> = HashMap::new();
+        for token in tokens.iter() {
+            *per_bucket.entry(hashed.ham_bow.bucket(token)).or_insert(0) += 1;
+        }
+        let colliding_tokens = per_bucket.values().filter(|&&n| n > 1).sum();
+        let (n, m) = (tokens.len() as f64, hashed.ham_bow.buckets() as f64);
+        let expected_colliding_tokens = if n > 0.0 {
+            n * (1.0 - (1.0 - 1.0 / m).powf(n - 1.0))
+        } else {
+            0.0
+        };
+
+        let texts: Vec<(&Document, String)> = validation
+            .documents
+            .par_iter()
+            .filter_map(|doc| doc.text().map(|text| (doc, text)))
+            .collect();
+        let evaluate = |log_odds: &(dyn Fn(&str) -> f64 + Sync), config: &ClassifierConfig| {
+            let start = Instant::now();
+            let scored: Vec<ScoredDocument> = texts
+                .par_iter()
+                .map(|(doc, text)| {
+                    let log_odds = log_odds(text);
+                    ScoredDocument {
+                        path: doc.path.clone(),
+                        label: doc.label,
+                        probability: config.probability(log_odds),
+                        log_odds,
+                    }
+                })
+                .collect();
+            let seconds = start.elapsed().as_secs_f64();
+            EvaluationReport::from_scores(&scored, &config.thresholds, seconds)
+        };
+        let weights = model.scoring_weights();
+        Some(HashingReport {
+            buckets: hashed.ham_bow.buckets(),
+            seed,
+            tokens: tokens.len(),
+            occupied_buckets: per_bucket.len(),
+            colliding_tokens,
+            expected_colliding_tokens,
+            bytes_exact: model.to_bytes()?.len(),
+            bytes_hashed: hashed.memory_bytes(),
+            exact: evaluate(
+                &|text| model.text_log_odds_with(text, &weights),
+                &model.config,
+            ),
+            hashed: evaluate(&|text| hashed.text_log_odds(text), &hashed.config),
+        })
+    }
+
+    /// Fraction of tokens sharing a bucket with another token, 0 for an empty model.
+    pub fn collision_rate(&self) -> f64 {
+        if self.tokens == 0 {
+            0.0
+        } else {
+            self.colliding_tokens as f64 / self.tokens as f64
+        }
+    }
+
+    /// Fraction of tokens expected to share a bucket if the hash were uniformly random.
+    pub fn expected_collision_rate(&self) -> f64 {
+        if self.tokens == 0 {
+            0.0
+        } else {
+            self.expected_colliding_tokens / self.tokens as f64
+        }
+    }
+
+    /// Change in accuracy from hashing, negative if the hashed model is less accurate.
+    pub fn accuracy_change(&self) -> f64 {
+        self.hashed.metrics.accuracy - self.exact.metrics.accuracy
+    }
+
+    /// The report as a human readable table.
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "{:<20} {:>12}\n{:<20} {:>12}\n{:<20} {:>12}\n{:<20} {:>12}\n\
+             {:<20} {:>11.1}%\n{:<20} {:>11.1}%\n{:<20} {:>12}\n{:<20} {:>12}\n",
+            "buckets",
+            self.buckets,
+            "seed",
+            self.seed,
+            "tokens",
+            self.tokens,
+            "occupied buckets",
+            self.occupied_buckets,
+            "colliding tokens",
+            100.0 * self.collision_rate(),
+            "expected",
+            100.0 * self.expected_collision_rate(),
+            "exact bytes",
+            self.bytes_exact,
+            "hashed bytes",
+            self.bytes_hashed,
+        );
+        text.push_str(&format!(
+            "\n{:<20} {:>12} {:>12} {:>10}\n",
+            "", "exact", "hashed", "change"
+        ));
+        let names = Metrics::NAMES.iter();
+        let exact = self.exact.metrics.values();
+        let hashed = self.hashed.metrics.values();
+        for ((name, e), h) in names.zip(exact.iter()).zip(hashed.iter()) {
+            text.push_str(&format!(
+                "{:<20} {:>12.4} {:>12.4} {:>+10.4}\n",
+                name,
+                e,
+                h,
+                h - e
+            ));
+        }
+        text
+    }
+
+    /// The report as pretty printed json.
+    pub fn to_json(&self) -> Option<String> {
+        serde_json::to_string_pretty(self).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BagOfWords, SignificantTokens, Thresholds};
+
+    fn model() -> HSModel {
+        HSModel::from_bows(
+            BagOfWords::from("hello hello lunch meeting report report free"),
+            BagOfWords::from("free free offer offer meeting winner report"),
+        )
+        .with_thresholds(Thresholds {
+            ham: 0.3,
+            spam: 0.6,
+        })
+    }
+
+    #[test]
+    fn reading_works_out_the_weights_again() {
+        let hashed = model()
+            .with_scoring(Scoring::Complement)
+            .to_hashed(1 << 10, 3)
+            .unwrap();
+        let json = serde_json::to_string(&hashed).unwrap();
+        assert!(!json.contains("\"complement\":"));
+        let read: HashedModel = serde_json::from_str(&json).unwrap();
+        assert_eq!(read, hashed);
+        assert!(read.complement.is_some());
+        assert_eq!(
+            read.text_spam_probability("free lunch"),
+            hashed.text_spam_probability("free lunch")
+        );
+
+        let rejected = |json: String| serde_json::from_str::<HashedModel>(&json).is_err();
+        assert!(rejected(json.replace("complement", "bernoulli")));
+        let other_seed =
+            serde_json::to_string(&HashedBagOfWords::new(1 << 10, 4).unwrap()).unwrap();
+        let ham = serde_json::to_string(hashed.ham_bow()).unwrap();
+        assert!(rejected(json.replace(&ham, &other_seed)));
+    }
+
+    #[test]
+    fn scores_match_exact_model_without_collisions() {
+        let significant = SignificantTokens {
+            count: 2,
+            min_deviation: 0.05,
+        };
+        for &scoring in &[Scoring::Multinomial, Scoring::Complement] {
+            for &significant in &[None, Some(significant)] {
+                let mut model = model().with_scoring(scoring);
+                model.config.significant = significant;
+                let hashed = model.to_hashed(1 << 20, 9).unwrap();
+                assert_eq!(hashed.config, model.config);
+                for text in &[
+                    "free offer",
+                    "hello meeting report",
+                    "lunch winner",
+                    "nothing",
+                ] {
+                    let p = model.text_spam_probability(text);
+                    assert!((hashed.text_spam_probability(text) - p).abs() < 1e-12);
+                    assert_eq!(hashed.classify(text), model.classify(text));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn one_bucket_pools_every_count() {
+        let hashed = model().to_hashed(1, 0).unwrap();
+        assert_eq!(hashed.counts("HELLO"), (7, 7));
+        assert_eq!(hashed.counts("NEVER SEEN"), (7, 7));
+        assert_eq!(hashed.text_spam_probability("free offer winner"), 0.5);
+        assert_eq!(hashed.memory_bytes(), 16);
+    }
+
+    #[test]
+    fn mismatched_bags_are_rejected() {
+        let ham = HashedBagOfWords::new(64, 0).unwrap();
+        assert!(
+            HashedModel::from_bows(ham.clone(), HashedBagOfWords::new(64, 1).unwrap()).is_none()
+        );
+        assert!(
+            HashedModel::from_bows(ham.clone(), HashedBagOfWords::new(32, 0).unwrap()).is_none()
+        );
+        assert!(HashedModel::from_bows(ham.clone(), ham).is_some());
+    }
+}
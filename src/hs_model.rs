@@ -5,12 +5,213 @@
 //! let spam_bow = BagOfWords::from("I have an offer you won't be able to pass up!!!");
 //! let model = HSModel::new().add_spam_bow(spam_bow).add_ham_bow(ham_bow);
 //! ```
-use std::fs;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs,
+    str::FromStr,
+};
 
 use serde::{Deserialize, Serialize};
 use unicode_segmentation::UnicodeSegmentation;
 
-use crate::{BagOfWords, Frequency, Probability};
+use crate::{
+    logistic, scoring::ScoringWeights, BagOfWords, Calibration, Count, Probability,
+    SignificantTokens, Timestamp, TokenCounts,
+};
+
+/// Probability cutoffs used by [classify](struct.HSModel.html#method.classify).
+/// Text scoring above `spam` is spam, text scoring below `ham` is ham, and anything in between is
+/// unsure.
+/// ```
+/// # use rammer::Thresholds;
+/// let thresholds = Thresholds::default();
+/// assert_eq!((thresholds.ham, thresholds.spam), (0.2, 0.8));
+/// ```
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct Thresholds {
+    /// Probabilities below this are classified as ham.
+    pub ham: Probability,
+    /// Probabilities above this are classified as spam.
+    pub spam: Probability,
+}
+
+impl Thresholds {
+    /// Classify a spam probability.
+    /// ```
+    /// # use rammer::{Classification, Thresholds};
+    /// let thresholds = Thresholds { ham: 0.3, spam: 0.7 };
+    /// assert_eq!(thresholds.classify(0.75), Classification::Spam);
+    /// assert_eq!(thresholds.classify(0.7), Classification::Unsure);
+    /// ```
+    pub fn classify(&self, p: Probability) -> Classification {
+        if p > self.spam {
+            Classification::Spam
+        } else if p < self.ham {
+            Classification::Ham
+        } else {
+            Classification::Unsure
+        }
+    }
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Thresholds {
+            ham: 0.2,
+            spam: 0.8,
+        }
+    }
+}
+
+/// How an HSModel turns token counts into a spam probability.
+/// ```
+/// # use rammer::Scoring;
+/// assert_eq!("complement".parse::<Scoring>(), Ok(Scoring::Complement));
+/// assert_eq!(Scoring::default().to_string(), "multinomial");
+/// ```
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Copy, Hash, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Scoring {
+    /// Multinomial naive Bayes over the tokens seen in both ham and spam, as described on the
+    /// [Naive Bayes Spam Filtering Wikipedia Page](https://en.wikipedia.org/wiki/Naive_Bayes_spam_filtering).
+    #[default]
+    Multinomial,
+    /// Complement naive Bayes, which estimates each class's weights from the other class and
+    /// normalizes them, so a much larger ham corpus doesn't drag scores towards ham. Every token
+    /// seen in training counts, not just those seen in both ham and spam.
+    Complement,
+    /// Bernoulli naive Bayes, which only asks whether a text contains each token, from the
+    /// fraction of training documents which did. Repeating a token doesn't add to its weight, and
+    /// tokens missing from the text count too.
+    Bernoulli,
+}
+
+impl Scoring {
+    /// Every scoring method, in the order they are reported.
+    pub const ALL: [Scoring; 3] = [
+        Scoring::Multinomial,
+        Scoring::Complement,
+        Scoring::Bernoulli,
+    ];
+}
+
+impl fmt::Display for Scoring {
+    #[allow(missing_doc_code_examples)]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Scoring::Multinomial => write!(f, "multinomial"),
+            Scoring::Complement => write!(f, "complement"),
+            Scoring::Bernoulli => write!(f, "bernoulli"),
+        }
+    }
+}
+
+impl FromStr for Scoring {
+    type Err = ();
+
+    #[allow(missing_doc_code_examples)]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "multinomial" => Ok(Scoring::Multinomial),
+            "complement" => Ok(Scoring::Complement),
+            "bernoulli" => Ok(Scoring::Bernoulli),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Settings which control how an HSModel classifies text. They are saved with the model.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ClassifierConfig {
+    /// Cutoffs between ham, unsure and spam.
+    pub thresholds: Thresholds,
+    /// How text is scored. Models saved before this existed use multinomial scoring.
+    pub scoring: Scoring,
+    /// Score only a text's most significant tokens, rather than all of them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub significant: Option<SignificantTokens>,
+    /// Mapping from log odds to calibrated probabilities, which the thresholds then apply to.
+    /// Without one, probabilities are the [logistic](fn.logistic.html) of the log odds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub calibration: Option<Calibration>,
+}
+
+#[allow(missing_doc_code_examples)]
+impl ClassifierConfig {
+    /// The spam probability of a text with the given log odds, calibrated if there is a
+    /// calibration.
+    /// ```
+    /// # use rammer::{Calibration, ClassifierConfig};
+    /// let mut config = ClassifierConfig::default();
+    /// assert_eq!(config.probability(0.0), 0.5);
+    /// config.calibration = Some(Calibration::Platt { slope: 1.0, intercept: -100.0 });
+    /// assert!(config.probability(0.0) < 1e-40);
+    /// ```
+    pub fn probability(&self, log_odds: f64) -> Probability {
+        match &self.calibration {
+            Some(calibration) => calibration.probability(log_odds),
+            None => logistic(log_odds),
+        }
+    }
+}
+
+/// The result of [classifying](struct.HSModel.html#method.classify) a text.
+/// ```
+/// # use rammer::Classification;
+/// assert_eq!(Classification::Unsure.to_string(), "unsure");
+/// ```
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Copy, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Classification {
+    /// The text is not spam.
+    Ham,
+    /// The text is spam.
+    Spam,
+    /// The model is not confident either way.
+    Unsure,
+}
+
+impl fmt::Display for Classification {
+    #[allow(missing_doc_code_examples)]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Classification::Ham => write!(f, "ham"),
+            Classification::Spam => write!(f, "spam"),
+            Classification::Unsure => write!(f, "unsure"),
+        }
+    }
+}
+
+/// How much a single token moved a text's score, as reported by
+/// [explain](struct.HSModel.html#method.explain).
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct TokenContribution {
+    /// The uppercased token.
+    pub token: String,
+    /// Number of times the token appears in the text.
+    pub occurrences: Count,
+    /// Probability that a text containing only this token is spam.
+    pub spam_probability: Probability,
+    /// Total log odds this token added to the text, positive values push towards spam and
+    /// negative values push towards ham.
+    pub weight: f64,
+}
+
+/// A text's spam probability along with the tokens which decided it.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct Explanation {
+    /// Probability the text is spam, the same as
+    /// [text_spam_probability](struct.HSModel.html#method.text_spam_probability).
+    pub probability: Probability,
+    /// Log odds the text is spam, the same as
+    /// [text_log_odds](struct.HSModel.html#method.text_log_odds).
+    pub log_odds: f64,
+    /// Classification of the text using the model's thresholds.
+    pub classification: Classification,
+    /// Every known token in the text, with the largest absolute weight first.
+    pub tokens: Vec<TokenContribution>,
+}
 
 /// A model which contains 2 BagOfWords, one containing known spam, and the other known ham.
 /// ```
@@ -25,6 +226,9 @@ pub struct HSModel {
     pub ham_bow: BagOfWords,
     /// BagOfWords that are know to be found in spam text.
     pub spam_bow: BagOfWords,
+    /// Settings used when classifying text. Models saved before this existed use the defaults.
+    #[serde(default)]
+    pub config: ClassifierConfig,
 }
 
 #[allow(missing_doc_code_examples)]
@@ -38,6 +242,7 @@ impl HSModel {
         HSModel {
             ham_bow: BagOfWords::new(),
             spam_bow: BagOfWords::new(),
+            config: ClassifierConfig::default(),
         }
     }
 
@@ -78,7 +283,10 @@ impl HSModel {
 
     /// Returns the probability that a slice of text is spam, based on the model.
     /// Read about how this is calulated here on the
-    /// [Naive Bayes Spam Filtering Wikipedia Page](https://en.wikipedia.org/wiki/Naive_Bayes_spam_filtering)
+    /// [Naive Bayes Spam Filtering Wikipedia Page](https://en.wikipedia.org/wiki/Naive_Bayes_spam_filtering),
+    /// or with the other [Scoring](enum.Scoring.html) methods when one is set in the model's
+    /// config, and calibrated when the config has a [Calibration](enum.Calibration.html). Score many
+    /// texts with a [scorer](#method.scorer) instead, which works out the scoring's weights once.
     /// ```
     /// # use rammer::{BagOfWords, HSModel};
     /// # let ham_bow = BagOfWords::from("How are you today.");
@@ -87,37 +295,235 @@ impl HSModel {
     /// let spam_probability = model.text_spam_probability("Respond fast! I have an offer of a lifetime!"); // return value between [0.0, 1.0]
     /// ```
     pub fn text_spam_probability(&self, text: &str) -> Probability {
-        let n: f64 = text
+        self.scorer().text_spam_probability(text)
+    }
+
+    /// Returns the log odds that a slice of text is spam, positive towards spam, which
+    /// [text_spam_probability](#method.text_spam_probability) turns into a probability with
+    /// [logistic](fn.logistic.html). Long texts' probabilities round to 0.0 or 1.0, but their log
+    /// odds keep growing, so use these to rank texts.
+    /// ```
+    /// # use rammer::{BagOfWords, HSModel};
+    /// # let model = HSModel::from_bows(BagOfWords::from("hello offer"), BagOfWords::from("offer offer hello"));
+    /// let log_odds = model.text_log_odds("offer");
+    /// assert!(log_odds > 0.0);
+    /// assert!(model.text_log_odds(&"offer ".repeat(5000)) > model.text_log_odds(&"offer ".repeat(4000)));
+    /// ```
+    pub fn text_log_odds(&self, text: &str) -> f64 {
+        self.scorer().text_log_odds(text)
+    }
+
+    /// Returns the probability that text already tokenized into a BagOfWords is spam. This is the
+    /// same calculation as [text_spam_probability](#method.text_spam_probability), up to floating
+    /// point rounding, and lets callers which score a document many times tokenize it once.
+    /// ```
+    /// # use rammer::{BagOfWords, HSModel};
+    /// # let ham_bow = BagOfWords::from("How are you today offer.");
+    /// # let spam_bow = BagOfWords::from("I have an offer you won't be able to pass up!!!");
+    /// # let model = HSModel::from_bows(ham_bow, spam_bow);
+    /// let text = "I have an offer for you";
+    /// let from_bow = model.bow_spam_probability(&BagOfWords::from(text));
+    /// assert!((from_bow - model.text_spam_probability(text)).abs() < 1e-9);
+    /// ```
+    pub fn bow_spam_probability(&self, bow: &BagOfWords) -> Probability {
+        self.scorer().bow_spam_probability(bow)
+    }
+
+    /// Returns the log odds that text already tokenized into a BagOfWords is spam, the same
+    /// calculation as [text_log_odds](#method.text_log_odds) up to floating point rounding.
+    /// ```
+    /// # use rammer::{BagOfWords, HSModel};
+    /// # let model = HSModel::from_bows(BagOfWords::from("hello offer"), BagOfWords::from("offer offer hello"));
+    /// let text = "hello offer offer";
+    /// assert!((model.bow_log_odds(&BagOfWords::from(text)) - model.text_log_odds(text)).abs() < 1e-9);
+    /// ```
+    pub fn bow_log_odds(&self, bow: &BagOfWords) -> f64 {
+        self.scorer().bow_log_odds(bow)
+    }
+
+    /// Builder pattern for setting the thresholds used by [classify](#method.classify).
+    /// ```
+    /// # use rammer::{HSModel, Thresholds};
+    /// let model = HSModel::new().with_thresholds(Thresholds { ham: 0.1, spam: 0.9 });
+    /// ```
+    pub fn with_thresholds(mut self, thresholds: Thresholds) -> Self {
+        self.config.thresholds = thresholds;
+        self
+    }
+
+    /// Builder pattern for calibrating the model's probabilities, usually with a
+    /// [Calibration](enum.Calibration.html) fitted on held out data.
+    /// ```
+    /// # use rammer::{BagOfWords, Calibration, HSModel};
+    /// let model = HSModel::from_bows(BagOfWords::from("hello"), BagOfWords::from("hello offer"))
+    ///     .with_calibration(Calibration::Platt { slope: 0.0, intercept: 0.0 });
+    /// assert_eq!(model.text_spam_probability("offer"), 0.5);
+    /// ```
+    pub fn with_calibration(mut self, calibration: Calibration) -> Self {
+        self.config.calibration = Some(calibration);
+        self
+    }
+
+    /// Builder pattern for scoring only each text's most significant tokens, as described in
+    /// [SignificantTokens](struct.SignificantTokens.html).
+    /// ```
+    /// # use rammer::{HSModel, SignificantTokens};
+    /// let model = HSModel::new().with_significant_tokens(SignificantTokens::default());
+    /// assert_eq!(model.config.significant, Some(SignificantTokens::default()));
+    /// ```
+    pub fn with_significant_tokens(mut self, significant: SignificantTokens) -> Self {
+        self.config.significant = Some(significant);
+        self
+    }
+
+    /// Decay both BagOfWords as of now with [BagOfWords::decay](struct.BagOfWords.html#method.decay),
+    /// so tokens which stopped appearing lose their weight and eventually expire.
+    /// ```
+    /// # use rammer::{BagOfWords, HSModel};
+    /// let model = HSModel::from_bows(BagOfWords::from("hello").seen_at(0), BagOfWords::from("offer offer").seen_at(0));
+    /// let model = model.decay(1000, 1000);
+    /// assert_eq!((model.ham_bow.len(), model.spam_bow.len()), (1, 1));
+    /// ```
+    pub fn decay(mut self, now: Timestamp, half_life: Timestamp) -> Self {
+        self.ham_bow = self.ham_bow.decay(now, half_life);
+        self.spam_bow = self.spam_bow.decay(now, half_life);
+        self
+    }
+
+    /// Remove stamped tokens not seen in the max_age seconds before now from both BagOfWords, with
+    /// [BagOfWords::expire](struct.BagOfWords.html#method.expire).
+    /// ```
+    /// # use rammer::{BagOfWords, HSModel};
+    /// let model = HSModel::from_bows(BagOfWords::from("hello").seen_at(0), BagOfWords::from("offer").seen_at(900));
+    /// let model = model.expire(1000, 500);
+    /// assert!(model.ham_bow.is_empty());
+    /// ```
+    pub fn expire(mut self, now: Timestamp, max_age: Timestamp) -> Self {
+        self.ham_bow = self.ham_bow.expire(now, max_age);
+        self.spam_bow = self.spam_bow.expire(now, max_age);
+        self
+    }
+
+    /// Classify a slice of text as spam, ham or unsure using the model's
+    /// [Thresholds](struct.Thresholds.html).
+    /// ```
+    /// # use rammer::{BagOfWords, Classification, HSModel};
+    /// # let ham_bow = BagOfWords::from("How are you today.");
+    /// # let spam_bow = BagOfWords::from("I have an offer you won't be able to pass up!!!");
+    /// # let model = HSModel::from_bows(ham_bow, spam_bow);
+    /// let class = model.classify("Respond fast! I have an offer of a lifetime!");
+    /// ```
+    pub fn classify(&self, text: &str) -> Classification {
+        self.scorer().classify(text)
+    }
+
+    /// Classify an already calculated spam probability using the model's
+    /// [Thresholds](struct.Thresholds.html).
+    /// ```
+    /// # use rammer::{Classification, HSModel};
+    /// let model = HSModel::new();
+    /// assert_eq!(model.classify_probability(0.99), Classification::Spam);
+    /// assert_eq!(model.classify_probability(0.5), Classification::Unsure);
+    /// ```
+    pub fn classify_probability(&self, p: Probability) -> Classification {
+        self.config.thresholds.classify(p)
+    }
+
+    /// Score a slice of text and report which of its tokens contributed to the score.
+    /// Tokens which do not affect the score are left out: with multinomial scoring those not in
+    /// both BagOfWords, and otherwise those in neither. With Bernoulli scoring a token's weight
+    /// counts once however often it occurs, and the tokens missing from the text also move the
+    /// score but are not listed. A model scoring only its
+    /// [significant tokens](struct.SignificantTokens.html) lists just the tokens it scored, each
+    /// weighted once.
+    /// ```
+    /// # use rammer::{BagOfWords, HSModel};
+    /// # let ham_bow = BagOfWords::from("How are you today offer.");
+    /// # let spam_bow = BagOfWords::from("I have an offer you won't be able to pass up!!!");
+    /// # let model = HSModel::from_bows(ham_bow, spam_bow);
+    /// let explanation = model.explain("Respond fast! I have an offer of a lifetime!");
+    /// for t in explanation.tokens.iter().take(5) {
+    ///     println!("{} {:.4}", t.token, t.spam_probability);
+    /// }
+    /// ```
+    pub fn explain(&self, text: &str) -> Explanation {
+        self.scorer().explain(text)
+    }
+
+    /// Explain a slice of text, scored with weights.
+    pub(crate) fn explain_with(&self, text: &str, weights: &ScoringWeights) -> Explanation {
+        let totals = self.totals();
+        let per_occurrence =
+            self.config.significant.is_none() && !matches!(weights, ScoringWeights::Bernoulli(_));
+        let mut seen: HashMap<String, (Count, Probability, f64)> = HashMap::new();
+        for word in text
             .to_uppercase()
             .split_word_bounds()
             .filter(|&s| !s.trim().is_empty())
-            .filter_map(|word| {
-                if let (Some(spam_freq), Some(ham_freq)) = (
-                    self.spam_bow.word_frequency(word),
-                    self.ham_bow.word_frequency(word),
-                ) {
-                    let p = spam_freq / (spam_freq + ham_freq);
-                    Some(Frequency::ln(1.0 - p) - Frequency::ln(p))
+        {
+            if let Some(entry) = seen.get_mut(word) {
+                entry.0 = entry.0.saturating_add(1);
+            } else if let Some((p, log_odds)) = self.token_score(word, weights, totals) {
+                seen.insert(word.to_string(), (1, p, log_odds));
+            }
+        }
+        if let Some(significant) = self.config.significant {
+            let scored = seen
+                .iter()
+                .map(|(token, &(_, _, log_odds))| (token.clone(), log_odds))
+                .collect();
+            let kept: HashSet<String> = significant
+                .select(scored)
+                .into_iter()
+                .map(|(token, _)| token)
+                .collect();
+            seen.retain(|token, _| kept.contains(token));
+        }
+
+        let mut tokens: Vec<TokenContribution> = seen
+            .into_iter()
+            .map(|(token, (occurrences, p, log_odds))| TokenContribution {
+                token,
+                occurrences,
+                spam_probability: p,
+                weight: if per_occurrence {
+                    occurrences as f64 * log_odds
                 } else {
-                    None
-                }
+                    log_odds
+                },
             })
-            .sum();
-        1.0 / (1.0 + std::f64::consts::E.powf(n))
+            .collect();
+        tokens.sort_by(|a, b| {
+            b.weight
+                .abs()
+                .partial_cmp(&a.weight.abs())
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.token.cmp(&b.token))
+        });
+
+        let log_odds = self.text_log_odds_with(text, weights);
+        let probability = self.config.probability(log_odds);
+        Explanation {
+            probability,
+            log_odds,
+            classification: self.classify_probability(probability),
+            tokens,
+        }
     }
 
     /// Serializse HSModel to a compact json string and write it to file_path. This write is
-    /// destructive.
+    /// destructive. Returns None if the model could not be written.
+    /// The output is canonical: words are sorted, fields are always in the same order and floats
+    /// use the shortest representation which reads back exactly, so models trained on the same
+    /// data are byte-identical.
     /// ```no_run
     /// # use rammer::{HSModel, BagOfWords};
     /// # let model = HSModel::from_bows(BagOfWords::from("hi greetings afternoon well"), BagOfWords::from("buy pay sell free"));
     /// model.write_to_json("test_resources/test_models/model.json").expect("Write unsucessful");
     /// ```
     pub fn write_to_json(&self, file_path: &str) -> Option<()> {
-        if let Ok(serialized) = serde_json::to_string(self) {
-            fs::write(file_path, serialized).ok()?;
-        }
-        Some(())
+        let serialized = serde_json::to_string(self).ok()?;
+        fs::write(file_path, serialized).ok()
     }
 
     /// read a json string from file_path and deserialize it to HSModel.
@@ -136,6 +542,24 @@ impl HSModel {
     }
 }
 
+impl TokenCounts for HSModel {
+    #[allow(missing_doc_code_examples)]
+    fn totals(&self) -> (Count, Count) {
+        (
+            self.ham_bow.total_word_count(),
+            self.spam_bow.total_word_count(),
+        )
+    }
+
+    #[allow(missing_doc_code_examples)]
+    fn counts(&self, token: &str) -> (Count, Count) {
+        (
+            self.ham_bow.token_count(token).unwrap_or(0),
+            self.spam_bow.token_count(token).unwrap_or(0),
+        )
+    }
+}
+
 impl Default for HSModel {
     fn default() -> Self {
         Self::new()
@@ -144,8 +568,7 @@ impl Default for HSModel {
 
 #[cfg(test)]
 mod tests {
-    use super::HSModel;
-    use crate::BagOfWords;
+    use super::*;
 
     /*****************************************/
     /* HSModel TESTS                         */
@@ -159,4 +582,124 @@ mod tests {
         assert!(model.text_spam_probability("spam") >= 0.0);
         assert!(model.text_spam_probability("spam") <= 1.0);
     }
+
+    #[test]
+    fn probability_matches_word_frequency() {
+        let spam_bow = BagOfWords::from("spam spam spam spam ham offer");
+        let ham_bow = BagOfWords::from("spam ham ham hello");
+        let model = HSModel::from_bows(ham_bow.clone(), spam_bow.clone());
+        let p = |w: &str| {
+            let s = spam_bow.word_frequency(w).unwrap();
+            let h = ham_bow.word_frequency(w).unwrap();
+            s / (s + h)
+        };
+        let n: f64 = ["spam", "ham", "spam"]
+            .iter()
+            .map(|&w| (1.0 - p(w)).ln() - p(w).ln())
+            .sum();
+        let expected = logistic(-n);
+        assert_eq!(model.text_spam_probability("spam ham offer spam"), expected);
+    }
+
+    #[test]
+    fn classify_uses_thresholds() {
+        let spam_bow = BagOfWords::from("spam spam spam spam ham");
+        let ham_bow = BagOfWords::from("spam ham ham ham ham");
+        let model = HSModel::from_bows(ham_bow, spam_bow);
+        assert_eq!(model.classify("spam spam"), Classification::Spam);
+        assert_eq!(model.classify("ham ham"), Classification::Ham);
+        assert_eq!(model.classify("spam ham"), Classification::Unsure);
+        let model = model.with_thresholds(Thresholds {
+            ham: 0.6,
+            spam: 0.99,
+        });
+        assert_eq!(model.classify("spam ham"), Classification::Ham);
+    }
+
+    #[test]
+    fn explain_orders_tokens_by_weight() {
+        let spam_bow = BagOfWords::from("offer offer offer free hello");
+        let ham_bow = BagOfWords::from("hello hello hello free offer");
+        let model = HSModel::from_bows(ham_bow, spam_bow);
+        let explanation = model.explain("free offer offer hello unknown");
+        let tokens: Vec<&str> = explanation.tokens.iter().map(|t| &t.token[..]).collect();
+        assert_eq!(tokens, vec!["OFFER", "HELLO", "FREE"]);
+        assert_eq!(explanation.tokens[0].occurrences, 2);
+        assert!(explanation.tokens[0].weight > 0.0);
+        assert!(explanation.tokens[1].weight < 0.0);
+        assert_eq!(
+            explanation.probability,
+            model.text_spam_probability("free offer offer hello unknown")
+        );
+    }
+
+    #[test]
+    fn explain_lists_only_significant_tokens() {
+        let spam_bow = BagOfWords::from("offer offer offer free hello meeting");
+        let ham_bow = BagOfWords::from("hello hello hello free offer meeting");
+        let model =
+            HSModel::from_bows(ham_bow, spam_bow).with_significant_tokens(SignificantTokens {
+                count: 2,
+                min_deviation: 0.0,
+            });
+        let text = "free offer offer hello meeting unknown";
+        let explanation = model.explain(text);
+        let tokens: Vec<&str> = explanation.tokens.iter().map(|t| &t.token[..]).collect();
+        // OFFER and HELLO are as far from 0.5 as each other, and each is weighted once.
+        assert_eq!(tokens, vec!["HELLO", "OFFER"]);
+        assert!((explanation.tokens[1].weight - (3.0f64).ln()).abs() < 1e-12);
+        assert_eq!(explanation.tokens[1].occurrences, 2);
+        assert!((explanation.probability - 0.5).abs() < 1e-12);
+        assert_eq!(explanation.probability, model.text_spam_probability(text));
+    }
+
+    #[test]
+    fn legacy_json_uses_default_config() {
+        let json = r#"{"ham_bow":{"bow":{"HI":1}},"spam_bow":{"bow":{"BUY":1}}}"#;
+        let model: HSModel = serde_json::from_str(json).unwrap();
+        assert_eq!(model.config, ClassifierConfig::default());
+    }
+
+    #[test]
+    fn legacy_json_counts_are_widened() {
+        // Models written when counts were 32 bits read as they are, and can now grow past
+        // u32::MAX instead of wrapping.
+        let json = r#"{"ham_bow":{"bow":{"THE":4294967295,"HI":1}},"spam_bow":{"bow":{"THE":7}}}"#;
+        let model: HSModel = serde_json::from_str(json).unwrap();
+        let model = model.clone().add_ham_bow(model.ham_bow);
+        assert_eq!(model.counts("THE"), (2 * 4_294_967_295, 7));
+        assert_eq!(model.totals(), (2 * 4_294_967_296, 7));
+        let json = serde_json::to_string(&model).unwrap();
+        assert!(json.contains(r#""THE":8589934590"#));
+        let read: HSModel = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.ham_bow, model.ham_bow);
+    }
+
+    #[test]
+    fn serialization_is_canonical() {
+        let texts = [
+            "free offer winner click now",
+            "hello lunch meeting tomorrow",
+            "report attached thanks regards",
+            "cheap meds free shipping",
+        ];
+        let forward =
+            |range: &[&str]| -> BagOfWords { range.iter().map(|&t| BagOfWords::from(t)).collect() };
+        let backward = |range: &[&str]| -> BagOfWords {
+            range.iter().rev().map(|&t| BagOfWords::from(t)).collect()
+        };
+        let thresholds = Thresholds {
+            ham: 0.1 + 0.2,
+            spam: 0.9,
+        };
+        let a = HSModel::from_bows(forward(&texts[..2]), forward(&texts[2..]))
+            .with_thresholds(thresholds);
+        let b = HSModel::from_bows(backward(&texts[..2]), backward(&texts[2..]))
+            .with_thresholds(thresholds);
+        let json = serde_json::to_string(&a).unwrap();
+        assert_eq!(json, serde_json::to_string(&b).unwrap());
+        assert!(json.starts_with(r#"{"ham_bow":{"bow":{"CLICK":1,"FREE":1,"#));
+        assert!(json.contains(r#""ham":0.30000000000000004"#));
+        assert_eq!(a.to_bytes(), b.to_bytes());
+    }
 }
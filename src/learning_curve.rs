@@ -0,0 +1,241 @@
+//! How a model's metrics change with the amount of training data.
+//! Models are trained on growing fractions of a training corpus and each is scored against the
+//! same validation corpus. The subsets are drawn with a seed, keep the training corpus' share of
+//! ham and spam, and are nested, so every larger subset contains the smaller ones and differences
+//! between points come from the extra data rather than from a different draw.
+//! ```no_run
+//! use rammer::{learning_curve, ClassifierConfig, Corpus};
+//! let train = Corpus::from_folders("data/train/ham", "data/train/spam").unwrap();
+//! let validation = Corpus::from_folders("data/validate/ham", "data/validate/spam").unwrap();
+//! let fractions = [0.1, 0.25, 0.5, 1.0];
+//! let curve = learning_curve(&train, &validation, &fractions, 0, &ClassifierConfig::default()).unwrap();
+//! print!("{}", curve.to_csv());
+//! ```
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::TokenizedDocument, evaluation::score_tokenized, rng::SplitMix64, BagOfWords,
+    ClassifierConfig, Corpus, EvaluationReport, HSModel, Label,
+};
+
+/// The evaluation of a model trained on one fraction of the training corpus.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct LearningCurvePoint {
+    /// Fraction of the training corpus used.
+    pub fraction: f64,
+    /// Number of ham documents trained on.
+    pub ham_documents: usize,
+    /// Number of spam documents trained on.
+    pub spam_documents: usize,
+    /// How the model did on the validation corpus.
+    pub report: EvaluationReport,
+}
+
+/// The result of [learning_curve](fn.learning_curve.html), one point per fraction from the
+/// smallest to the largest.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct LearningCurve {
+    /// Seed the training subsets were drawn with.
+    pub seed: u64,
+    /// Points ordered by fraction.
+    pub points: Vec<LearningCurvePoint>,
+}
+
+#[allow(missing_doc_code_examples)]
+impl LearningCurve {
+    /// The curve as csv with a header line, one row per point.
+    pub fn to_csv(&self) -> String {
+        let mut csv = format!(
+            "fraction,ham_documents,spam_documents,{}\n",
+            EvaluationReport::csv_header()
+        );
+        for p in &self.points {
+            csv.push_str(&format!(
+                "{},{},{},{}\n",
+                p.fraction,
+                p.ham_documents,
+                p.spam_documents,
+                p.report.csv_row()
+            ));
+        }
+        csv
+    }
+
+    /// The curve as a human readable table of the main metrics.
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "{:>8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8}\n",
+            "fraction", "ham", "spam", "accuracy", "f1", "fpr", "fnr"
+        );
+        for p in &self.points {
+            let m = &p.report.metrics;
+            text.push_str(&format!(
+                "{:>8.3} {:>8} {:>8} {:>8.4} {:>8.4} {:>8.4} {:>8.4}\n",
+                p.fraction,
+                p.ham_documents,
+                p.spam_documents,
+                m.accuracy,
+                m.f1,
+                m.false_positive_rate,
+                m.false_negative_rate
+            ));
+        }
+        text
+    }
+
+    /// The curve as pretty printed json.
+    pub fn to_json(&self) -> Option<String> {
+        serde_json::to_string_pretty(self).ok()
+    }
+}
+
+/// Train a model on each fraction of train, drawn with seed, and evaluate it on validation using
+/// config. At least one document of each label is always trained on. Returns None if fractions is
+/// empty or has a value outside (0, 1], or if train lacks readable ham or spam, or if validation
+/// has no readable documents.
+/// ```
+/// # use rammer::{learning_curve, ClassifierConfig, Corpus, Label};
+/// let train = Corpus::new()
+///     .add_folder("test_resources/test_data", Label::Ham).unwrap()
+///     .add_folder("test_resources/test_maildir/cur", Label::Spam).unwrap();
+/// let validation = Corpus::new().add_folder("test_resources/test_maildir/new", Label::Ham).unwrap();
+/// let curve = learning_curve(&train, &validation, &[1.0, 0.5], 0, &ClassifierConfig::default()).unwrap();
+/// assert_eq!(curve.points[0].fraction, 0.5);
+/// assert_eq!(curve.points[1].ham_documents, 3);
+/// ```
+pub fn learning_curve(
+    train: &Corpus,
+    validation: &Corpus,
+    fractions: &[f64],
+    seed: u64,
+    config: &ClassifierConfig,
+) -> Option<LearningCurve> {
+    if fractions.is_empty() || fractions.iter().any(|f| !(*f > 0.0 && *f <= 1.0)) {
+        return None;
+    }
+    let mut fractions = fractions.to_vec();
+    fractions.sort_by(f64::total_cmp);
+    fractions.dedup();
+
+    let train = train.tokenize();
+    let validation = validation.tokenize();
+    let validation: Vec<&TokenizedDocument> = validation.iter().collect();
+    if validation.is_empty() {
+        return None;
+    }
+
+    // Each label is shuffled once, so a larger fraction is always a longer prefix of the same
+    // order and the subsets are nested.
+    let mut rng = SplitMix64::new(seed);
+    let mut shuffled = |label: Label| -> Vec<&TokenizedDocument> {
+        let mut docs: Vec<&TokenizedDocument> = train.iter().filter(|d| d.label == label).collect();
+        rng.shuffle(&mut docs);
+        docs
+    };
+    let ham = shuffled(Label::Ham);
+    let spam = shuffled(Label::Spam);
+    if ham.is_empty() || spam.is_empty() {
+        return None;
+    }
+
+    let mut ham_bow = BagOfWords::new();
+    let mut spam_bow = BagOfWords::new();
+    let (mut ham_used, mut spam_used) = (0, 0);
+    let mut points = Vec::new();
+    for fraction in fractions {
+        let size = |n: usize| ((fraction * n as f64).round() as usize).clamp(1, n);
+        let (ham_size, spam_size) = (size(ham.len()), size(spam.len()));
+        ham_bow = ham[ham_used..ham_size]
+            .iter()
+            .fold(ham_bow, |bow, d| bow.combine(d.bow.clone()));
+        spam_bow = spam[spam_used..spam_size]
+            .iter()
+            .fold(spam_bow, |bow, d| bow.combine(d.bow.clone()));
+        ham_used = ham_size;
+        spam_used = spam_size;
+
+        let mut model = HSModel::from_bows(ham_bow.clone(), spam_bow.clone());
+        model.config = config.clone();
+        let start = Instant::now();
+        let scored = score_tokenized(&model, &validation);
+        let seconds = start.elapsed().as_secs_f64();
+        points.push(LearningCurvePoint {
+            fraction,
+            ham_documents: ham_size,
+            spam_documents: spam_size,
+            report: EvaluationReport::from_scores(&scored, &config.thresholds, seconds),
+        });
+    }
+    Some(LearningCurve { seed, points })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn corpora() -> (Corpus, Corpus) {
+        let train = Corpus::new()
+            .add_folder("test_resources/test_data", Label::Ham)
+            .and_then(|c| c.add_folder("test_resources/test_maildir/cur", Label::Spam))
+            .and_then(|c| c.add_folder("test_resources/test_maildir/new", Label::Spam))
+            .and_then(|c| c.add_folder("test_resources/test_maildir/tmp", Label::Spam))
+            .unwrap();
+        let validation = Corpus::new()
+            .add_folder("test_resources/test_maildir/.Junk/cur", Label::Spam)
+            .unwrap();
+        (train, validation)
+    }
+
+    #[test]
+    fn points_grow_with_fraction() {
+        let (train, validation) = corpora();
+        let curve = learning_curve(
+            &train,
+            &validation,
+            &[1.0, 0.1, 0.5, 0.5],
+            3,
+            &ClassifierConfig::default(),
+        )
+        .unwrap();
+        let sizes: Vec<(f64, usize, usize)> = curve
+            .points
+            .iter()
+            .map(|p| (p.fraction, p.ham_documents, p.spam_documents))
+            .collect();
+        assert_eq!(sizes, vec![(0.1, 1, 1), (0.5, 2, 2), (1.0, 3, 3)]);
+        assert!(curve.points.iter().all(|p| p.report.confusion.total() == 1));
+        assert_eq!(curve.to_csv().lines().count(), 4);
+    }
+
+    #[test]
+    fn full_fraction_matches_full_model() {
+        let (train, validation) = corpora();
+        let curve =
+            learning_curve(&train, &validation, &[1.0], 9, &ClassifierConfig::default()).unwrap();
+        let (ham, spam) = train.bows();
+        let text = validation.documents[0].text().unwrap();
+        let p = HSModel::from_bows(ham, spam).text_spam_probability(&text);
+        let expected = EvaluationReport::from_scores(
+            &[crate::ScoredDocument {
+                path: validation.documents[0].path.clone(),
+                label: Label::Spam,
+                probability: p,
+                log_odds: crate::logit(p),
+            }],
+            &ClassifierConfig::default().thresholds,
+            0.0,
+        );
+        assert_eq!(curve.points[0].report.confusion, expected.confusion);
+    }
+
+    #[test]
+    fn invalid_fractions_are_none() {
+        let (train, validation) = corpora();
+        let config = ClassifierConfig::default();
+        assert!(learning_curve(&train, &validation, &[], 0, &config).is_none());
+        assert!(learning_curve(&train, &validation, &[0.0], 0, &config).is_none());
+        assert!(learning_curve(&train, &validation, &[1.5], 0, &config).is_none());
+    }
+}
@@ -14,54 +14,78 @@
 //! }
 //! ```
 //!
-//! Here is an Example program using an existing model.
+//! Here is an Example program measuring an existing model against held out data.
 //! ```no_run
-//! use rammer::HSModel;
-//! use std::fs;
-//! use rayon::prelude::*;
-//! fn main() {
-//!    let model = HSModel::read_from_json("out/models/enron1_model.json").unwrap();
-//!    let spam_answers = validate(&model, "data/validate/spam", "spam", |p| p > 0.8);
-//!    let ham_answers = validate(&model, "data/validate/ham", "ham", |p| p < 0.2);
-//!
-//!    println!("Spam Correctly Classified: {}/{} = {:.4}", spam_answers.0, spam_answers.1, spam_answers.2);
-//!    println!("Ham Correctly Classified: {}/{} = {:.4}", ham_answers.0, ham_answers.1, ham_answers.2);
-//! }
-//!
-//! fn validate<F>(model: &HSModel, dir: &str, class: &str, is_correct: F) -> (u32, usize, f64)
-//!     where F: Fn(f64) -> bool + Sync
-//! {
-//!     let ps: Vec<bool> = fs::read_dir(dir)
-//!         .expect("folder exists")
-//!         .par_bridge()
-//!         .filter_map(|maybe_entry| {
-//!             maybe_entry.ok().and_then(|entry| {
-//!                 fs::read_to_string(entry.path())
-//!                     .ok()
-//!                     .and_then(|text| Some(model.text_spam_probability(&text[..])))
-//!             })
-//!         })
-//!         .map(|p| { println!("Probability: {:.8}\t\t({})", p, class); is_correct(p) })
-//!         .collect();
-//!
-//!     let num_classified_correctly: u32 = ps
-//!         .iter()
-//!         .filter_map(|&b| if b { Some(1) } else { None })
-//!         .sum();
-//!
-//!     (
-//!         num_classified_correctly,
-//!         ps.len(),
-//!         num_classified_correctly as f64 / ps.len() as f64
-//!     )
+//! use rammer::{Corpus, EvaluationReport, HSModel};
 //!
+//! fn main() {
+//!     let model = HSModel::read_from_json("out/models/enron1_model.json").unwrap();
+//!     let corpus = Corpus::from_folders("data/validate/ham", "data/validate/spam").unwrap();
+//!     let report = EvaluationReport::evaluate(&model, &corpus);
+//!     println!("{}", report.to_text());
 //! }
-//! ```  
+//! ```
 
 mod bag_of_words;
+mod bernoulli;
+mod calibration;
+mod complement;
+mod corpus;
+mod count_min;
+mod cross_corpus;
+mod cross_validation;
+mod error_analysis;
+mod evaluation;
+mod feedback;
+mod hashed_bag;
+mod hashed_model;
 mod hs_model;
+mod learning_curve;
+mod log_odds;
+mod maildir;
+mod mapped_model;
+mod message;
+mod model_diff;
+mod model_file;
+mod model_summary;
+mod multi_class;
+mod pruning;
+mod rng;
+mod roc;
+mod scoring;
+mod significant;
+mod token_counts;
 pub use bag_of_words::BagOfWords;
-pub use hs_model::HSModel;
+pub use calibration::{Calibration, CalibrationMethod, ReliabilityBin, RELIABILITY_BINS};
+pub use corpus::{Corpus, Document, Label};
+pub use count_min::{SketchBagOfWords, SketchModel};
+pub use cross_corpus::{cross_corpus, CrossCorpusMatrix};
+pub use cross_validation::{cross_validate, CrossValidationReport};
+pub use error_analysis::{ErrorAnalysis, Misclassification};
+pub use evaluation::{
+    score_corpus, ConfusionMatrix, EvaluationReport, Metrics, ScoredDocument, ScoringComparison,
+    Timings,
+};
+pub use feedback::{LearnedMessages, RetrainSummary};
+pub use hashed_bag::HashedBagOfWords;
+pub use hashed_model::{HashedModel, HashingReport};
+pub use hs_model::{
+    Classification, ClassifierConfig, Explanation, HSModel, Scoring, Thresholds,
+    TokenContribution,
+};
+pub use learning_curve::{learning_curve, LearningCurve, LearningCurvePoint};
+pub use log_odds::{logistic, logit};
+pub use maildir::{message_id, Maildir, INBOX};
+pub use mapped_model::MappedModel;
+pub use message::{annotate, SPAM_HEADERS};
+pub use model_diff::{ClassificationFlip, ModelDiff, TokenShift, TotalsChange};
+pub use model_summary::{CountBucket, ModelSummary, TokenStats};
+pub use multi_class::MultiClassModel;
+pub use pruning::PruneReport;
+pub use roc::{optimize_thresholds, CurvePoint, RocCurve};
+pub use scoring::Scorer;
+pub use significant::SignificantTokens;
+pub use token_counts::TokenCounts;
 
 /// Type alias for rate of occurences of a value.
 /// This type should always be between [0,1].
@@ -72,4 +96,8 @@ pub type Frequency = f64;
 pub type Probability = f64;
 
 /// Type alias for number of times a word is found in a BagOfWords.
-pub type Count = u32;
+/// Counts are added with saturating arithmetic, so they stop at Count::MAX instead of wrapping.
+pub type Count = u64;
+
+/// Type alias for a point in time, in seconds since the unix epoch.
+pub type Timestamp = u64;
@@ -0,0 +1,69 @@
+//! Converting between log odds and probabilities.
+//! Every scoring method adds up log odds, the log of how many times likelier a text is to be spam
+//! than ham, and only turns the sum into a probability at the end. A long document's log odds
+//! easily reach hundreds, where its probability rounds to exactly 0.0 or 1.0 and can no longer be
+//! told apart from other documents', so anything which ranks documents should compare their log
+//! odds instead. [logistic](fn.logistic.html) does the final conversion without overflowing.
+//! ```
+//! use rammer::{logistic, logit, BagOfWords, HSModel};
+//! let model = HSModel::from_bows(BagOfWords::from("hello hello offer"), BagOfWords::from("hello offer offer"));
+//! let (long, longer) = ("offer ".repeat(2000), "offer ".repeat(3000));
+//! assert_eq!(model.text_spam_probability(&long), model.text_spam_probability(&longer));
+//! assert!(model.text_log_odds(&long) < model.text_log_odds(&longer));
+//! assert_eq!(logistic(model.text_log_odds("offer")), model.text_spam_probability("offer"));
+//! assert!((logit(0.75) - 3f64.ln()).abs() < 1e-12);
+//! ```
+use crate::Probability;
+
+/// The probability with the given log odds, 1 / (1 + e^-log_odds). Only ever exponentiates a
+/// non-positive number, so it never overflows, and very negative log odds give tiny probabilities
+/// rather than 0.0 until they are below what an f64 can hold.
+/// ```
+/// # use rammer::logistic;
+/// assert_eq!(logistic(0.0), 0.5);
+/// assert!(logistic(-700.0) > 0.0);
+/// assert_eq!(logistic(f64::INFINITY), 1.0);
+/// ```
+pub fn logistic(log_odds: f64) -> Probability {
+    if log_odds >= 0.0 {
+        1.0 / (1.0 + (-log_odds).exp())
+    } else {
+        let e = log_odds.exp();
+        e / (1.0 + e)
+    }
+}
+
+/// The log odds of a probability, ln(p / (1 - p)), the inverse of
+/// [logistic](fn.logistic.html). Infinite at 0 and 1.
+/// ```
+/// # use rammer::logit;
+/// assert_eq!(logit(0.5), 0.0);
+/// assert_eq!(logit(1.0), f64::INFINITY);
+/// ```
+pub fn logit(p: Probability) -> f64 {
+    p.ln() - (1.0 - p).ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logistic_is_stable_and_symmetric() {
+        for &n in &[0.5, 3.0, 36.0, 300.0, 1000.0] {
+            let (high, low) = (logistic(n), logistic(-n));
+            assert!(high.is_finite() && low.is_finite());
+            assert!((high - (1.0 - low)).abs() < 1e-15);
+        }
+        assert!(logistic(-700.0) > 0.0);
+        assert!(logistic(-700.0) > logistic(-701.0));
+        assert!((logistic(-700.0).ln() + 700.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn logit_inverts_logistic() {
+        for &p in &[0.001, 0.2, 0.5, 0.75, 0.999] {
+            assert!((logistic(logit(p)) - p).abs() < 1e-12);
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//! Read labelled training mail out of a [Maildir](https://cr.yp.to/proto/maildir.html).
+//! Each Maildir folder is mapped to a Label, so a user's "Junk" folder can be used as known spam
+//! and their inbox as known ham.
+//! ```no_run
+//! use rammer::{HSModel, Label, Maildir};
+//! let corpus = Maildir::new("/home/dalton/Maildir")
+//!     .label_folder(".Archive", Label::Ham)
+//!     .corpus()
+//!     .expect("Maildir not found");
+//! let (ham_bow, spam_bow) = corpus.bows();
+//! let model = HSModel::from_bows(ham_bow, spam_bow);
+//! ```
+use std::path::{Path, PathBuf};
+
+use crate::{message::header_value, Corpus, Label};
+
+/// The sub directories of a Maildir folder which contain messages.
+const MAILDIR_SUBDIRS: [&str; 3] = ["cur", "new", "tmp"];
+
+/// The name used for the top level folder of a Maildir.
+pub const INBOX: &str = "INBOX";
+
+/// A Maildir on disk and the Labels given to its folders.
+/// By default the inbox is labelled ham, and the Maildir++ `.Junk` folder is labelled spam.
+/// Folders without a Label are not read.
+/// ```
+/// # use rammer::{Label, Maildir};
+/// let corpus = Maildir::new("test_resources/test_maildir").corpus().unwrap();
+/// assert_eq!(corpus.count(Label::Ham), 3);
+/// assert_eq!(corpus.count(Label::Spam), 1);
+/// ```
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Maildir {
+    root: PathBuf,
+    folders: Vec<(String, Label)>,
+}
+
+#[allow(missing_doc_code_examples)]
+impl Maildir {
+    /// Create a Maildir rooted at root_path with the default folder Labels.
+    /// ```
+    /// # use rammer::Maildir;
+    /// let maildir = Maildir::new("test_resources/test_maildir");
+    /// ```
+    pub fn new(root_path: &str) -> Self {
+        Maildir {
+            root: PathBuf::from(root_path),
+            folders: vec![
+                (INBOX.to_string(), Label::Ham),
+                (".Junk".to_string(), Label::Spam),
+            ],
+        }
+    }
+
+    /// Create a Maildir rooted at root_path where no folder has a Label yet.
+    /// ```
+    /// # use rammer::{Label, Maildir};
+    /// let maildir = Maildir::unlabelled("test_resources/test_maildir").label_folder(".Junk", Label::Spam);
+    /// ```
+    pub fn unlabelled(root_path: &str) -> Self {
+        Maildir {
+            root: PathBuf::from(root_path),
+            folders: Vec::new(),
+        }
+    }
+
+    /// Builder pattern for giving a folder a Label, replacing any Label it had before.
+    /// Folders are named relative to the Maildir root, use [INBOX](constant.INBOX.html) for the
+    /// root itself.
+    /// ```
+    /// # use rammer::{Label, Maildir};
+    /// let maildir = Maildir::new("test_resources/test_maildir").label_folder(".Trash", Label::Spam);
+    /// ```
+    pub fn label_folder(mut self, folder: &str, label: Label) -> Self {
+        self.folders.retain(|(f, _)| f != folder);
+        self.folders.push((folder.to_string(), label));
+        self
+    }
+
+    /// Path of a folder in this Maildir.
+    /// ```
+    /// # use rammer::Maildir;
+    /// let maildir = Maildir::new("mail");
+    /// assert_eq!(maildir.folder_path("INBOX"), std::path::PathBuf::from("mail"));
+    /// assert_eq!(maildir.folder_path(".Junk"), std::path::PathBuf::from("mail/.Junk"));
+    /// ```
+    pub fn folder_path(&self, folder: &str) -> PathBuf {
+        if folder == INBOX {
+            self.root.clone()
+        } else {
+            self.root.join(folder)
+        }
+    }
+
+    /// Collect every message in the cur, new and tmp directories of each labelled folder into a
+    /// Corpus. Labelled folders which do not exist are skipped.
+    /// This will return None if the Maildir root is not a directory.
+    /// ```no_run
+    /// # use rammer::Maildir;
+    /// let corpus = Maildir::new("/home/dalton/Maildir").corpus().expect("Maildir not found");
+    /// ```
+    pub fn corpus(&self) -> Option<Corpus> {
+        if !self.root.is_dir() {
+            return None;
+        }
+
+        let mut corpus = Corpus::new();
+        for (folder, label) in &self.folders {
+            let folder_path = self.folder_path(folder);
+            for sub in MAILDIR_SUBDIRS.iter() {
+                if let Some(dir) = folder_path.join(sub).to_str() {
+                    if let Some(messages) = Corpus::new().add_folder(dir, *label) {
+                        corpus = corpus.combine(messages);
+                    }
+                }
+            }
+        }
+        Some(corpus)
+    }
+}
+
+/// Identify a message by its Message-ID header.
+/// Messages without a Message-ID are identified by their Maildir unique name, which is the file
+/// name without the `:2,` flags a mail client appends.
+/// ```
+/// # use rammer::message_id;
+/// let text = "Subject: hi\nMessage-ID: <1234@example.com>\n\nhello";
+/// assert_eq!(message_id("cur/1600000000.M1P1.host:2,S", text), "<1234@example.com>");
+/// assert_eq!(message_id("cur/1600000000.M1P1.host:2,S", "hello"), "1600000000.M1P1.host");
+/// ```
+pub fn message_id<P: AsRef<Path>>(path: P, text: &str) -> String {
+    header_value(text, "Message-ID")
+        .filter(|id| !id.is_empty())
+        .unwrap_or_else(|| {
+            let name = path
+                .as_ref()
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            name.split(':').next().unwrap_or_default().to_string()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_labels() {
+        let corpus = Maildir::new("test_resources/test_maildir")
+            .corpus()
+            .unwrap();
+        assert_eq!(corpus.count(Label::Ham), 3);
+        assert_eq!(corpus.count(Label::Spam), 1);
+    }
+
+    #[test]
+    fn relabel_folder() {
+        let corpus = Maildir::new("test_resources/test_maildir")
+            .label_folder(INBOX, Label::Spam)
+            .label_folder(".Trash", Label::Ham)
+            .corpus()
+            .unwrap();
+        assert_eq!(corpus.count(Label::Ham), 1);
+        assert_eq!(corpus.count(Label::Spam), 4);
+    }
+
+    #[test]
+    fn missing_root_is_none() {
+        assert!(Maildir::new("test_resources/not_a_maildir")
+            .corpus()
+            .is_none());
+    }
+
+    #[test]
+    fn folded_message_id() {
+        let text = "From: bob\nMessage-Id:\n <ham3@example.com>\nSubject: hi\n\nbody";
+        assert_eq!(message_id("x", text), "<ham3@example.com>");
+    }
+
+    #[test]
+    fn message_id_in_body_ignored() {
+        let text = "From: bob\n\nMessage-ID: <body@example.com>";
+        assert_eq!(message_id("new/123.host", text), "123.host");
+    }
+}
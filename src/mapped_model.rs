@@ -0,0 +1,468 @@
+//! A read-only model which is memory-mapped from disk and never deserialized, so a long running
+//! classifier can start, or reload its model, without building any HashMaps. Tokens are looked
+//! up with a binary search directly against the mapping and give the same scores as the
+//! [HSModel](struct.HSModel.html) the file was written from.
+//!
+//! The file is a sorted string table, with integers in little endian:
+//!
+//! | bytes           | contents                                                     |
+//! |-----------------|--------------------------------------------------------------|
+//! | 0..4            | magic bytes `RMMT`                                           |
+//! | 4..6            | format version, currently 2                                  |
+//! | 6..8            | reserved, written as 0                                       |
+//! | 8..16           | number of distinct tokens, n                                 |
+//! | 16..24          | total ham count                                              |
+//! | 24..32          | total spam count                                             |
+//! | 32..40          | length of the json metadata, m                               |
+//! | 40..40+m        | json metadata, the tokenizer and ClassifierConfig            |
+//! | next 8(n+1)     | u64 offset of each token into the string data, and the end   |
+//! | next 16n        | u64 ham count and u64 spam count of each token               |
+//! | rest            | string data, the UTF-8 bytes of every token in byte order    |
+//!
+//! Version 1 files, whose counts are u32, are still read.
+//! ```no_run
+//! use rammer::{HSModel, MappedModel};
+//! let model = HSModel::read_from_file("out/models/enron1_model.json").unwrap();
+//! model.write_to_mapped("out/models/enron1_model.rmt").unwrap();
+//! let mapped = MappedModel::open("out/models/enron1_model.rmt").unwrap();
+//! println!("{}", mapped.classify("hello i have an offer for you"));
+//! ```
+use std::{
+    convert::{TryFrom, TryInto},
+    fs,
+    fs::File,
+};
+
+use memmap2::Mmap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    complement::ComplementWeights, model_file::TOKENIZER, scoring::counts_text_log_odds,
+    Classification, ClassifierConfig, Count, HSModel, Probability, Scoring, TokenCounts,
+};
+
+const MAGIC: &[u8; 4] = b"RMMT";
+const VERSION: u16 = 2;
+const HEADER_LEN: usize = 40;
+
+#[derive(Serialize, Deserialize)]
+struct Metadata {
+    tokenizer: String,
+    config: ClassifierConfig,
+}
+
+/// A read-only model backed by a memory-mapped sorted string table.
+/// Scoring goes through [TokenCounts](trait.TokenCounts.html), like HSModel.
+pub struct MappedModel {
+    map: Mmap,
+    tokens: usize,
+    totals: (Count, Count),
+    offsets_start: usize,
+    counts_start: usize,
+    /// Bytes in each of a token's two counts, 4 in version 1 files and 8 after.
+    count_width: usize,
+    strings_start: usize,
+    /// Weights for complement scoring, worked out from every token when the model is opened.
+    complement: Option<ComplementWeights>,
+    /// Settings used when classifying text, read from the file. Use
+    /// [with_scoring](#method.with_scoring) rather than changing the scoring here.
+    pub config: ClassifierConfig,
+}
+
+#[allow(missing_doc_code_examples)]
+impl HSModel {
+    /// Write the model to file_path as a sorted string table for
+    /// [MappedModel::open](struct.MappedModel.html#method.open). This write is destructive.
+    /// Returns None if the model could not be written, or uses
+    /// [Bernoulli scoring](enum.Scoring.html#variant.Bernoulli), as the table only holds counts
+    /// and not the document frequencies it needs.
+    /// ```no_run
+    /// # use rammer::{BagOfWords, HSModel};
+    /// # let model = HSModel::from_bows(BagOfWords::from("hi greetings afternoon well"), BagOfWords::from("buy pay sell free"));
+    /// model.write_to_mapped("test_resources/test_models/model.rmt").expect("Write unsucessful");
+    /// ```
+    pub fn write_to_mapped(&self, file_path: &str) -> Option<()> {
+        fs::write(file_path, self.to_mapped_bytes()?).ok()
+    }
+
+    /// Encode the model as a sorted string table.
+    fn to_mapped_bytes(&self) -> Option<Vec<u8>> {
+        if self.config.scoring == Scoring::Bernoulli {
+            return None;
+        }
+        let mut tokens: Vec<&str> = self
+            .ham_bow
+            .tokens()
+            .chain(self.spam_bow.tokens())
+            .map(|(token, _)| token)
+            .collect();
+        tokens.sort_unstable();
+        tokens.dedup();
+        let metadata = serde_json::to_vec(&Metadata {
+            tokenizer: TOKENIZER.to_string(),
+            config: self.config.clone(),
+        })
+        .ok()?;
+        let (ham_total, spam_total) = self.totals();
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&0u16.to_le_bytes());
+        bytes.extend_from_slice(&(tokens.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&ham_total.to_le_bytes());
+        bytes.extend_from_slice(&spam_total.to_le_bytes());
+        bytes.extend_from_slice(&(metadata.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&metadata);
+        let mut offset = 0u64;
+        for token in tokens.iter() {
+            bytes.extend_from_slice(&offset.to_le_bytes());
+            offset += token.len() as u64;
+        }
+        bytes.extend_from_slice(&offset.to_le_bytes());
+        for token in tokens.iter() {
+            let (ham, spam) = self.counts(token);
+            bytes.extend_from_slice(&ham.to_le_bytes());
+            bytes.extend_from_slice(&spam.to_le_bytes());
+        }
+        for token in tokens.iter() {
+            bytes.extend_from_slice(token.as_bytes());
+        }
+        Some(bytes)
+    }
+}
+
+#[allow(missing_doc_code_examples)]
+impl MappedModel {
+    /// Memory-map a model written by
+    /// [write_to_mapped](struct.HSModel.html#method.write_to_mapped). Only the header and
+    /// metadata are read up front. Returns None if the file can not be mapped, is not a mapped
+    /// model, is from a newer format version or was made with a different tokenizer.
+    ///
+    /// The file must not be modified while it is mapped; replace it with a rename instead.
+    /// ```no_run
+    /// # use rammer::MappedModel;
+    /// let model = MappedModel::open("test_resources/test_models/model.rmt").unwrap();
+    /// ```
+    pub fn open(file_path: &str) -> Option<Self> {
+        let file = File::open(file_path).ok()?;
+        // Safety: mapping is unsafe because the file could change while it is mapped. The mapping
+        // is only read, every read is bounds checked, and models are replaced by renaming, as
+        // documented above.
+        let map = unsafe { Mmap::map(&file) }.ok()?;
+        Self::from_map(map)
+    }
+
+    fn from_map(map: Mmap) -> Option<Self> {
+        if map.len() < HEADER_LEN || &map[..4] != MAGIC {
+            return None;
+        }
+        let version = u16::from_le_bytes(map[4..6].try_into().ok()?);
+        if version == 0 || version > VERSION {
+            return None;
+        }
+        let field = |at: usize| -> Option<usize> {
+            usize::try_from(u64::from_le_bytes(map[at..at + 8].try_into().ok()?)).ok()
+        };
+        let tokens = field(8)?;
+        let totals = (
+            Count::try_from(field(16)?).ok()?,
+            Count::try_from(field(24)?).ok()?,
+        );
+        let metadata_len = field(32)?;
+
+        let offsets_start = HEADER_LEN.checked_add(metadata_len)?;
+        let count_width = if version == 1 { 4 } else { 8 };
+        let counts_start = offsets_start.checked_add(tokens.checked_add(1)?.checked_mul(8)?)?;
+        let strings_start = counts_start.checked_add(tokens.checked_mul(2 * count_width)?)?;
+        if strings_start > map.len() {
+            return None;
+        }
+        let metadata: Metadata = serde_json::from_slice(&map[HEADER_LEN..offsets_start]).ok()?;
+        if metadata.tokenizer != TOKENIZER {
+            return None;
+        }
+        let model = MappedModel {
+            map,
+            tokens,
+            totals,
+            offsets_start,
+            counts_start,
+            count_width,
+            strings_start,
+            complement: None,
+            config: metadata.config,
+        };
+        let scoring = model.config.scoring;
+        model.with_scoring(scoring)
+    }
+
+    /// Builder pattern for setting how text is scored. Complement scoring reads the counts of
+    /// every token once to work out its weights, so opening a model saved with it takes longer.
+    /// Returns None for Bernoulli scoring, which needs document frequencies the file doesn't have.
+    /// ```no_run
+    /// # use rammer::{MappedModel, Scoring};
+    /// let model = MappedModel::open("test_resources/test_models/model.rmt").unwrap();
+    /// let model = model.with_scoring(Scoring::Complement).unwrap();
+    /// ```
+    pub fn with_scoring(mut self, scoring: Scoring) -> Option<Self> {
+        self.config.scoring = scoring;
+        self.complement = match scoring {
+            Scoring::Multinomial => None,
+            Scoring::Complement => Some(ComplementWeights::new(
+                (0..self.tokens).filter_map(|index| self.counts_at_index(index)),
+                self.totals,
+            )),
+            Scoring::Bernoulli => return None,
+        };
+        Some(self)
+    }
+
+    /// Number of distinct tokens in the model.
+    pub fn len(&self) -> usize {
+        self.tokens
+    }
+
+    /// Returns true if the model has no tokens.
+    pub fn is_empty(&self) -> bool {
+        self.tokens == 0
+    }
+
+    /// Returns the probability that a slice of text is spam, the same as
+    /// [HSModel::text_spam_probability](struct.HSModel.html#method.text_spam_probability).
+    pub fn text_spam_probability(&self, text: &str) -> Probability {
+        self.config.probability(self.text_log_odds(text))
+    }
+
+    /// Returns the log odds that a slice of text is spam, the same as
+    /// [HSModel::text_log_odds](struct.HSModel.html#method.text_log_odds).
+    pub fn text_log_odds(&self, text: &str) -> f64 {
+        counts_text_log_odds(
+            self,
+            self.complement.as_ref(),
+            self.config.significant,
+            text,
+        )
+    }
+
+    /// Classify a slice of text as spam, ham or unsure using the model's
+    /// [Thresholds](struct.Thresholds.html).
+    pub fn classify(&self, text: &str) -> Classification {
+        self.config
+            .thresholds
+            .classify(self.text_spam_probability(text))
+    }
+
+    fn u64_at(&self, at: usize) -> Option<u64> {
+        Some(u64::from_le_bytes(
+            self.map.get(at..at + 8)?.try_into().ok()?,
+        ))
+    }
+
+    /// The little endian count, 4 or 8 bytes wide, at the byte offset at.
+    fn count_at(&self, at: usize) -> Option<Count> {
+        let mut bytes = [0; 8];
+        bytes[..self.count_width].copy_from_slice(self.map.get(at..at + self.count_width)?);
+        Some(Count::from_le_bytes(bytes))
+    }
+
+    /// Ham and spam counts of the token at index, or None if the file is inconsistent.
+    fn counts_at_index(&self, index: usize) -> Option<(Count, Count)> {
+        let at = self.counts_start + index * 2 * self.count_width;
+        Some((self.count_at(at)?, self.count_at(at + self.count_width)?))
+    }
+
+    /// Bytes of the token at index, or None if the file is inconsistent.
+    fn token(&self, index: usize) -> Option<&[u8]> {
+        let start = usize::try_from(self.u64_at(self.offsets_start + index * 8)?).ok()?;
+        let end = usize::try_from(self.u64_at(self.offsets_start + index * 8 + 8)?).ok()?;
+        self.map
+            .get(self.strings_start.checked_add(start)?..self.strings_start.checked_add(end)?)
+    }
+}
+
+impl TokenCounts for MappedModel {
+    #[allow(missing_doc_code_examples)]
+    fn totals(&self) -> (Count, Count) {
+        self.totals
+    }
+
+    #[allow(missing_doc_code_examples)]
+    fn counts(&self, token: &str) -> (Count, Count) {
+        let (mut low, mut high) = (0, self.tokens);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let found = match self.token(mid) {
+                Some(found) => found,
+                None => return (0, 0),
+            };
+            match found.cmp(token.as_bytes()) {
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal => return self.counts_at_index(mid).unwrap_or((0, 0)),
+            }
+        }
+        (0, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BagOfWords, SignificantTokens, Thresholds};
+
+    fn write(model: &HSModel, name: &str) -> std::path::PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("rammer-mapped-{}-{}.rmt", name, std::process::id()));
+        model.write_to_mapped(path.to_str().unwrap()).unwrap();
+        path
+    }
+
+    #[test]
+    fn scores_match_in_memory_model() {
+        let model = HSModel::from_bows(
+            BagOfWords::from("hello hello lunch meeting ünïcödé free"),
+            BagOfWords::from("free free offer offer meeting winner ünïcödé"),
+        )
+        .with_thresholds(Thresholds {
+            ham: 0.3,
+            spam: 0.6,
+        });
+        let path = write(&model, "scores");
+        let mapped = MappedModel::open(path.to_str().unwrap()).unwrap();
+        assert_eq!(mapped.len(), 7);
+        assert_eq!(mapped.config, model.config);
+        assert_eq!(mapped.totals(), model.totals());
+        for token in &["HELLO", "FREE", "ÜNÏCÖDÉ", "WINNER", "MISSING", "", "ZZZ"] {
+            assert_eq!(mapped.counts(token), model.counts(token), "{}", token);
+        }
+        for text in &[
+            "free offer",
+            "hello meeting",
+            "ünïcödé free lunch",
+            "nothing",
+        ] {
+            assert_eq!(
+                mapped.text_spam_probability(text),
+                model.text_spam_probability(text)
+            );
+            assert_eq!(mapped.classify(text), model.classify(text));
+        }
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn significant_scores_match_in_memory_model() {
+        let ham = BagOfWords::from("hello hello lunch meeting report report free");
+        let spam = BagOfWords::from("free free offer offer meeting report lunch");
+        let significant = SignificantTokens {
+            count: 2,
+            min_deviation: 0.05,
+        };
+        for &scoring in &[Scoring::Multinomial, Scoring::Complement] {
+            let model = HSModel::from_bows(ham.clone(), spam.clone())
+                .with_scoring(scoring)
+                .with_significant_tokens(significant);
+            let path = write(&model, "significant");
+            let mapped = MappedModel::open(path.to_str().unwrap()).unwrap();
+            assert_eq!(mapped.config, model.config);
+            for text in &["free offer hello lunch", "report meeting lunch", "nothing"] {
+                let p = model.text_spam_probability(text);
+                assert!((mapped.text_spam_probability(text) - p).abs() < 1e-12);
+            }
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn complement_scores_match_in_memory_model() {
+        let model = HSModel::from_bows(
+            BagOfWords::from("hello hello lunch meeting report report"),
+            BagOfWords::from("free offer offer meeting"),
+        )
+        .with_scoring(Scoring::Complement);
+        let path = write(&model, "complement");
+        let mapped = MappedModel::open(path.to_str().unwrap()).unwrap();
+        assert_eq!(mapped.config.scoring, Scoring::Complement);
+        let multinomial = MappedModel::open(path.to_str().unwrap())
+            .unwrap()
+            .with_scoring(Scoring::Multinomial)
+            .unwrap();
+        for text in &[
+            "free offer",
+            "hello meeting",
+            "report lunch offer",
+            "nothing",
+        ] {
+            let p = model.text_spam_probability(text);
+            assert!((mapped.text_spam_probability(text) - p).abs() < 1e-12);
+            let p = model.multinomial_text_spam_probability(text);
+            assert_eq!(multinomial.text_spam_probability(text), p);
+        }
+        assert!(mapped.with_scoring(Scoring::Bernoulli).is_none());
+        let bernoulli = model.with_scoring(Scoring::Bernoulli);
+        assert!(bernoulli.to_mapped_bytes().is_none());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn reads_version_1() {
+        let model = HSModel::from_bows(
+            BagOfWords::from("hello hello lunch offer"),
+            BagOfWords::from("offer offer winner"),
+        );
+        // Rewrite the version 2 file with the u32 counts of version 1.
+        let v2 = model.to_mapped_bytes().unwrap();
+        let tokens = 4;
+        let counts_start = v2.len() - "HELLOLUNCHOFFERWINNER".len() - 16 * tokens;
+        let mut v1 = v2[..counts_start].to_vec();
+        v1[4..6].copy_from_slice(&1u16.to_le_bytes());
+        for count in v2[counts_start..counts_start + 16 * tokens].chunks(8) {
+            v1.extend_from_slice(&count[..4]);
+        }
+        v1.extend_from_slice(&v2[counts_start + 16 * tokens..]);
+        let path = std::env::temp_dir().join(format!("rammer-mapped-v1-{}", std::process::id()));
+        fs::write(&path, &v1).unwrap();
+        let mapped = MappedModel::open(path.to_str().unwrap()).unwrap();
+        for token in &["HELLO", "LUNCH", "OFFER", "WINNER", "MISSING"] {
+            assert_eq!(mapped.counts(token), model.counts(token), "{}", token);
+        }
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn large_counts() {
+        let big = BagOfWords::from_counts(vec![("THE".to_string(), 1 << 40)]);
+        let model = HSModel::from_bows(big.clone(), big.combine(BagOfWords::from("the")));
+        let path = write(&model, "large");
+        let mapped = MappedModel::open(path.to_str().unwrap()).unwrap();
+        assert_eq!(mapped.counts("THE"), (1 << 40, (1 << 40) + 1));
+        assert_eq!(mapped.totals(), model.totals());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn empty_model() {
+        let path = write(&HSModel::new(), "empty");
+        let mapped = MappedModel::open(path.to_str().unwrap()).unwrap();
+        assert!(mapped.is_empty());
+        assert_eq!(mapped.counts("ANY"), (0, 0));
+        assert_eq!(mapped.text_spam_probability("any"), 0.5);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let path = std::env::temp_dir().join(format!("rammer-mapped-bad-{}", std::process::id()));
+        let bytes = HSModel::new().to_bytes().unwrap();
+        fs::write(&path, &bytes).unwrap();
+        assert!(MappedModel::open(path.to_str().unwrap()).is_none());
+        let mut truncated = HSModel::from_bows(BagOfWords::from("a b"), BagOfWords::from("b c"))
+            .to_mapped_bytes()
+            .unwrap();
+        truncated.truncate(HEADER_LEN + 10);
+        fs::write(&path, &truncated).unwrap();
+        assert!(MappedModel::open(path.to_str().unwrap()).is_none());
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,302 @@
+//! Reading and writing the headers of mail messages, so an HSModel can be used as a mail filter.
+//! [annotate](fn.annotate.html) scores a message and adds `X-Spam-*` headers to it, leaving the
+//! body byte for byte unchanged.
+//! ```
+//! use rammer::{annotate, BagOfWords, Classification, HSModel};
+//! let ham_bow = BagOfWords::from("hello hello hello hello lunch lunch free offer");
+//! let model = HSModel::from_bows(ham_bow, BagOfWords::from("free offer"));
+//! let message = b"Subject: hi\n\nFree offer!\n";
+//! let (annotated, class) = annotate(message, &model);
+//! assert!(String::from_utf8_lossy(&annotated).contains("X-Spam-Status: Yes"));
+//! assert_eq!(class, Classification::Spam);
+//! ```
+use crate::{Classification, HSModel, Scorer};
+
+/// Names of the headers written by [annotate](fn.annotate.html). Existing headers with these
+/// names are removed first, so a sender can not forge them.
+pub const SPAM_HEADERS: [&str; 3] = ["X-Spam-Status", "X-Spam-Score", "X-Spam-Tokens"];
+
+/// Number of tokens listed in the X-Spam-Tokens header.
+const REPORTED_TOKENS: usize = 10;
+
+/// Header lines longer than this are folded.
+const MAX_LINE_LENGTH: usize = 78;
+
+/// Score a message with model and add X-Spam-Status, X-Spam-Score and X-Spam-Tokens headers to
+/// the end of its header section. The message does not need to be valid UTF-8; only its headers
+/// are rewritten, and its line endings are kept.
+/// ```
+/// # use rammer::{annotate, HSModel};
+/// let (annotated, class) = annotate(b"Subject: hi\r\n\r\nhello\r\n", &HSModel::new());
+/// assert!(annotated.starts_with(b"Subject: hi\r\nX-Spam-Status: Unsure"));
+/// assert!(annotated.ends_with(b"\r\n\r\nhello\r\n"));
+/// ```
+pub fn annotate(message: &[u8], model: &HSModel) -> (Vec<u8>, Classification) {
+    model.scorer().annotate(message)
+}
+
+#[allow(missing_doc_code_examples)]
+impl Scorer<'_> {
+    /// The same as [annotate](fn.annotate.html) with the scorer's model, for filtering many
+    /// messages without working out the model's weights for each.
+    /// ```
+    /// # use rammer::{BagOfWords, HSModel, Scoring};
+    /// let model = HSModel::from_bows(BagOfWords::from("hello lunch"), BagOfWords::from("free offer"))
+    ///     .with_scoring(Scoring::Bernoulli);
+    /// let scorer = model.scorer();
+    /// for message in &[&b"Subject: hi\n\nlunch?\n"[..], &b"Subject: free\n\noffer\n"[..]] {
+    ///     let (annotated, class) = scorer.annotate(message);
+    /// }
+    /// ```
+    pub fn annotate(&self, message: &[u8]) -> (Vec<u8>, Classification) {
+        let explanation = self.explain(&String::from_utf8_lossy(message));
+        let thresholds = &self.model().config.thresholds;
+
+        let status = match explanation.classification {
+            Classification::Spam => "Yes",
+            Classification::Ham => "No",
+            Classification::Unsure => "Unsure",
+        };
+        let tokens: Vec<String> = explanation
+            .tokens
+            .iter()
+            .take(REPORTED_TOKENS)
+            .map(|t| format!("\"{}\" {:.4},", escape(&t.token), t.spam_probability))
+            .collect();
+        let headers = [
+            (
+                SPAM_HEADERS[0],
+                format!(
+                    "{}, score={:.4} ham={:.4} spam={:.4}",
+                    status, explanation.probability, thresholds.ham, thresholds.spam
+                ),
+            ),
+            (SPAM_HEADERS[1], format!("{:.6}", explanation.probability)),
+            (
+                SPAM_HEADERS[2],
+                tokens.join(" ").trim_end_matches(',').to_string(),
+            ),
+        ];
+
+        (
+            insert_headers(message, &headers),
+            explanation.classification,
+        )
+    }
+}
+
+/// Add headers to the end of a message's header section, replacing any headers with the same
+/// names.
+fn insert_headers(message: &[u8], headers: &[(&str, String)]) -> Vec<u8> {
+    let newline: &[u8] = match message.iter().position(|&b| b == b'\n') {
+        Some(i) if i > 0 && message[i - 1] == b'\r' => b"\r\n",
+        _ => b"\n",
+    };
+
+    let mut out = Vec::with_capacity(message.len() + 256);
+    let mut rest = message;
+    let mut dropping = false;
+    let mut first = true;
+    let mut found_separator = false;
+    while !rest.is_empty() {
+        let end = rest
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(rest.len(), |i| i + 1);
+        let line = &rest[..end];
+        let content = trim_newline(line);
+
+        if content.is_empty() {
+            found_separator = true;
+            break;
+        }
+        let is_continuation = content[0] == b' ' || content[0] == b'\t';
+        let is_envelope = first && content.starts_with(b"From ");
+        if !is_continuation && !is_envelope && header_name(content).is_none() {
+            break;
+        }
+        if !is_continuation {
+            dropping = header_name(content)
+                .is_some_and(|name| headers.iter().any(|(h, _)| h.eq_ignore_ascii_case(name)));
+        }
+        if !dropping {
+            out.extend_from_slice(line);
+            if end == rest.len() && !line.ends_with(b"\n") {
+                out.extend_from_slice(newline);
+            }
+        }
+        first = false;
+        rest = &rest[end..];
+    }
+
+    for (name, value) in headers {
+        out.extend_from_slice(fold(name, value, newline).as_bytes());
+    }
+    if !found_separator {
+        out.extend_from_slice(newline);
+    }
+    out.extend_from_slice(rest);
+    out
+}
+
+/// The name of a header line, or None if line is not a header.
+fn header_name(line: &[u8]) -> Option<&str> {
+    let colon = line.iter().position(|&b| b == b':')?;
+    let name = std::str::from_utf8(&line[..colon]).ok()?;
+    if name.is_empty() || name.bytes().any(|b| b <= b' ' || b > b'~') {
+        None
+    } else {
+        Some(name)
+    }
+}
+
+fn trim_newline(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+/// Format a header, folding it on spaces so lines stay under MAX_LINE_LENGTH where possible.
+fn fold(name: &str, value: &str, newline: &[u8]) -> String {
+    let newline = String::from_utf8_lossy(newline);
+    let mut header = format!("{}:", name);
+    let mut line_length = header.len();
+    for word in value.split(' ').filter(|w| !w.is_empty()) {
+        let word_length = word.chars().count();
+        if line_length + 1 + word_length > MAX_LINE_LENGTH && line_length > name.len() + 1 {
+            header.push_str(&newline);
+            line_length = 0;
+        }
+        header.push(' ');
+        header.push_str(word);
+        line_length += 1 + word_length;
+    }
+    header.push_str(&newline);
+    header
+}
+
+/// Escape a token so it can be quoted in a header.
+fn escape(token: &str) -> String {
+    token
+        .chars()
+        .flat_map(|c| match c {
+            '"' | '\\' => vec!['\\', c],
+            c if c.is_control() => vec![],
+            c => vec![c],
+        })
+        .collect()
+}
+
+/// Find the unfolded value of the first header called name. Header names are case insensitive.
+pub(crate) fn header_value(text: &str, name: &str) -> Option<String> {
+    let mut value: Option<String> = None;
+    for line in text.lines() {
+        let line = line.trim_end_matches('\r');
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with(' ') || line.starts_with('\t') {
+            if let Some(v) = value.as_mut() {
+                if !v.is_empty() {
+                    v.push(' ');
+                }
+                v.push_str(line.trim());
+            }
+            continue;
+        }
+        if value.is_some() {
+            break;
+        }
+        if let Some((n, v)) = line.split_once(':') {
+            if n.trim().eq_ignore_ascii_case(name) {
+                value = Some(v.trim().to_string());
+            }
+        }
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BagOfWords, Scoring};
+
+    fn model() -> HSModel {
+        HSModel::from_bows(
+            BagOfWords::from("hello hello hello hello lunch meeting free offer"),
+            BagOfWords::from("free offer offer offer offer offer offer buy hello"),
+        )
+    }
+
+    #[test]
+    fn headers_added_before_body() {
+        let (annotated, class) = annotate(b"From: a@b.c\nSubject: offer\n\nfree offer\n", &model());
+        let annotated = String::from_utf8(annotated).unwrap();
+        assert_eq!(class, Classification::Spam);
+        assert!(annotated.starts_with("From: a@b.c\nSubject: offer\nX-Spam-Status: Yes, score="));
+        assert!(annotated.contains("\nX-Spam-Score: "));
+        assert!(annotated.contains("\nX-Spam-Tokens: \"OFFER\" "));
+        assert!(annotated.ends_with("\n\nfree offer\n"));
+    }
+
+    #[test]
+    fn scorer_annotates_like_the_model() {
+        let model = model().with_scoring(Scoring::Bernoulli);
+        let scorer = model.scorer();
+        for message in &[
+            &b"Subject: offer\n\nfree offer offer\n"[..],
+            &b"Subject: lunch\r\n\r\nhello lunch meeting\r\n"[..],
+        ] {
+            assert_eq!(scorer.annotate(message), annotate(message, &model));
+        }
+    }
+
+    #[test]
+    fn existing_spam_headers_replaced() {
+        let message = b"Subject: lunch\nX-Spam-Status: No,\n  forged=1\nx-spam-score: 0.0\nTo: me\n\nhello lunch\n";
+        let (annotated, class) = annotate(message, &model());
+        let annotated = String::from_utf8(annotated).unwrap();
+        assert_eq!(class, Classification::Ham);
+        assert!(!annotated.contains("forged"));
+        assert!(!annotated.contains("x-spam-score"));
+        assert_eq!(annotated.matches("X-Spam-Status:").count(), 1);
+        assert!(annotated.starts_with("Subject: lunch\nTo: me\nX-Spam-Status: No"));
+    }
+
+    #[test]
+    fn body_bytes_preserved() {
+        let mut message = b"From someone Mon Jan  1 00:00:00 2021\r\nSubject: x\r\n\r\n".to_vec();
+        message.extend_from_slice(&[0xff, 0xfe, b'o', b'k', b'\r', b'\n']);
+        let (annotated, _) = annotate(&message, &model());
+        assert!(annotated.starts_with(
+            b"From someone Mon Jan  1 00:00:00 2021\r\nSubject: x\r\nX-Spam-Status: "
+        ));
+        assert!(annotated
+            .ends_with(&[b'\r', b'\n', b'\r', b'\n', 0xff, 0xfe, b'o', b'k', b'\r', b'\n']));
+    }
+
+    #[test]
+    fn message_without_headers() {
+        let (annotated, _) = annotate(b"just some text\n", &model());
+        let annotated = String::from_utf8(annotated).unwrap();
+        assert!(annotated.starts_with("X-Spam-Status: "));
+        assert!(annotated.ends_with("\n\njust some text\n"));
+    }
+
+    #[test]
+    fn long_headers_are_folded() {
+        let value = vec!["\"ABCDEFGHIJ\" 0.5000,"; 20].join(" ");
+        let folded = fold("X-Spam-Tokens", &value, b"\n");
+        assert!(folded.lines().count() > 1);
+        assert!(folded.lines().all(|l| l.len() <= MAX_LINE_LENGTH));
+        assert!(folded.lines().skip(1).all(|l| l.starts_with(' ')));
+    }
+
+    #[test]
+    fn header_value_unfolds() {
+        let text = "From: bob\nMessage-Id:\n <ham3@example.com>\nSubject: hi\n\nbody";
+        assert_eq!(
+            header_value(text, "message-id").unwrap(),
+            "<ham3@example.com>"
+        );
+    }
+}
@@ -0,0 +1,290 @@
+//! What changed between two HSModels, such as a model before and after retraining.
+//! A ModelDiff lists the tokens which were added or removed, the tokens whose spam probability
+//! moved the most and how the total counts changed. Given a validation corpus it also lists the
+//! documents the two models classify differently.
+//! ```no_run
+//! use rammer::{Corpus, HSModel, ModelDiff};
+//! let old = HSModel::read_from_file("out/models/enron1_model.json").unwrap();
+//! let new = HSModel::read_from_file("out/models/enron1_retrained.json").unwrap();
+//! let validation = Corpus::from_folders("data/validate/ham", "data/validate/spam").unwrap();
+//! println!("{}", ModelDiff::new(&old, &new, 20, Some(&validation)).to_text());
+//! ```
+use std::{collections::BTreeSet, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    score_corpus, Classification, Corpus, Count, HSModel, Label, Probability, TokenCounts,
+};
+
+/// A token whose spam probability differs between two models.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct TokenShift {
+    /// The uppercased token.
+    pub token: String,
+    /// Probability that a text containing only this token is spam, under the old model.
+    pub before: Probability,
+    /// The same probability under the new model.
+    pub after: Probability,
+}
+
+/// Total ham and spam counts of two models.
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct TotalsChange {
+    /// Total ham count of the old model.
+    pub ham_before: Count,
+    /// Total ham count of the new model.
+    pub ham_after: Count,
+    /// Total spam count of the old model.
+    pub spam_before: Count,
+    /// Total spam count of the new model.
+    pub spam_after: Count,
+}
+
+/// A validation document the two models classify differently.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct ClassificationFlip {
+    /// Location of the document.
+    pub path: PathBuf,
+    /// The known class of the document.
+    pub label: Label,
+    /// Classification by the old model.
+    pub before: Classification,
+    /// Classification by the new model.
+    pub after: Classification,
+    /// Spam probability under the old model.
+    pub probability_before: Probability,
+    /// Spam probability under the new model.
+    pub probability_after: Probability,
+}
+
+/// The differences between an old and a new HSModel.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct ModelDiff {
+    /// Tokens in the new model but not the old, sorted.
+    pub added: Vec<String>,
+    /// Tokens in the old model but not the new, sorted.
+    pub removed: Vec<String>,
+    /// Tokens scored by both models whose spam probability changed, largest change first.
+    pub shifts: Vec<TokenShift>,
+    /// Total counts before and after.
+    pub totals: TotalsChange,
+    /// Validation documents classified differently, each by its own model's thresholds, or None
+    /// if no validation corpus was given.
+    pub flips: Option<Vec<ClassificationFlip>>,
+}
+
+#[allow(missing_doc_code_examples)]
+impl ModelDiff {
+    /// Compare old and new, keeping the top_shifts largest probability changes. If validation is
+    /// given, its documents are scored with both models to find classification flips.
+    /// ```
+    /// # use rammer::{BagOfWords, HSModel, ModelDiff};
+    /// let old = HSModel::from_bows(BagOfWords::from("hello lunch offer"), BagOfWords::from("free offer"));
+    /// let new = old.clone().add_spam_bow(BagOfWords::from("offer offer winner"));
+    /// let diff = ModelDiff::new(&old, &new, 10, None);
+    /// assert_eq!(diff.added, vec!["WINNER"]);
+    /// assert_eq!(diff.shifts[0].token, "OFFER");
+    /// assert!(diff.shifts[0].after > diff.shifts[0].before);
+    /// ```
+    pub fn new(
+        old: &HSModel,
+        new: &HSModel,
+        top_shifts: usize,
+        validation: Option<&Corpus>,
+    ) -> Self {
+        let vocabulary = |model: &HSModel| -> BTreeSet<String> {
+            model
+                .ham_bow
+                .tokens()
+                .chain(model.spam_bow.tokens())
+                .map(|(token, _)| token.to_string())
+                .collect()
+        };
+        let (old_tokens, new_tokens) = (vocabulary(old), vocabulary(new));
+
+        let (old_totals, new_totals) = (old.totals(), new.totals());
+        let mut shifts: Vec<TokenShift> = old_tokens
+            .intersection(&new_tokens)
+            .filter_map(|token| {
+                let before = old.token_spam_probability(token, old_totals)?;
+                let after = new.token_spam_probability(token, new_totals)?;
+                if before == after {
+                    return None;
+                }
+                Some(TokenShift {
+                    token: token.clone(),
+                    before,
+                    after,
+                })
+            })
+            .collect();
+        shifts.sort_by(|a, b| {
+            (b.after - b.before)
+                .abs()
+                .total_cmp(&(a.after - a.before).abs())
+                .then_with(|| a.token.cmp(&b.token))
+        });
+        shifts.truncate(top_shifts);
+
+        ModelDiff {
+            added: new_tokens.difference(&old_tokens).cloned().collect(),
+            removed: old_tokens.difference(&new_tokens).cloned().collect(),
+            shifts,
+            totals: TotalsChange {
+                ham_before: old_totals.0,
+                ham_after: new_totals.0,
+                spam_before: old_totals.1,
+                spam_after: new_totals.1,
+            },
+            flips: validation.map(|corpus| flips(old, new, corpus)),
+        }
+    }
+
+    /// The diff as a human readable summary, listing at most 20 added and removed tokens.
+    pub fn to_text(&self) -> String {
+        let t = &self.totals;
+        let mut text = format!(
+            "Total ham count:  {} -> {} ({:+})\n\
+             Total spam count: {} -> {} ({:+})\n\n",
+            t.ham_before,
+            t.ham_after,
+            t.ham_after as i64 - t.ham_before as i64,
+            t.spam_before,
+            t.spam_after,
+            t.spam_after as i64 - t.spam_before as i64,
+        );
+        for (title, tokens) in [("Added", &self.added), ("Removed", &self.removed)].iter() {
+            text.push_str(&format!("{} tokens: {}\n", title, tokens.len()));
+            if !tokens.is_empty() {
+                let shown: Vec<&str> = tokens.iter().take(20).map(|t| &t[..]).collect();
+                let more = if tokens.len() > 20 { ", ..." } else { "" };
+                text.push_str(&format!("  {}{}\n", shown.join(", "), more));
+            }
+        }
+        text.push_str("\nLargest spam probability shifts:\n");
+        for s in &self.shifts {
+            text.push_str(&format!(
+                "  {:<24} {:.4} -> {:.4} ({:+.4})\n",
+                s.token,
+                s.before,
+                s.after,
+                s.after - s.before
+            ));
+        }
+        if let Some(flips) = &self.flips {
+            let correct = |label: Label, class: Classification| {
+                matches!(
+                    (label, class),
+                    (Label::Ham, Classification::Ham) | (Label::Spam, Classification::Spam)
+                )
+            };
+            let fixed = flips.iter().filter(|f| correct(f.label, f.after)).count();
+            let broken = flips.iter().filter(|f| correct(f.label, f.before)).count();
+            text.push_str(&format!(
+                "\nClassification flips: {} ({} now correct, {} no longer correct)\n",
+                flips.len(),
+                fixed,
+                broken
+            ));
+            for f in flips {
+                text.push_str(&format!(
+                    "  {} ({}): {} {:.4} -> {} {:.4}\n",
+                    f.path.display(),
+                    f.label,
+                    f.before,
+                    f.probability_before,
+                    f.after,
+                    f.probability_after
+                ));
+            }
+        }
+        text
+    }
+
+    /// The diff as pretty printed json.
+    pub fn to_json(&self) -> Option<String> {
+        serde_json::to_string_pretty(self).ok()
+    }
+}
+
+/// Documents in corpus which old and new classify differently, in corpus order.
+fn flips(old: &HSModel, new: &HSModel, corpus: &Corpus) -> Vec<ClassificationFlip> {
+    score_corpus(old, corpus)
+        .into_iter()
+        .zip(score_corpus(new, corpus))
+        .filter_map(|(before, after)| {
+            let (class_before, class_after) = (
+                old.classify_probability(before.probability),
+                new.classify_probability(after.probability),
+            );
+            if class_before == class_after {
+                return None;
+            }
+            Some(ClassificationFlip {
+                path: before.path,
+                label: before.label,
+                before: class_before,
+                after: class_after,
+                probability_before: before.probability,
+                probability_after: after.probability,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BagOfWords, Thresholds};
+
+    #[test]
+    fn identical_models_have_no_differences() {
+        let model = HSModel::from_bows(
+            BagOfWords::from("hello there world"),
+            BagOfWords::from("hello offer"),
+        );
+        let corpus = Corpus::new()
+            .add_folder("test_resources/test_data", Label::Ham)
+            .unwrap();
+        let diff = ModelDiff::new(&model, &model, 10, Some(&corpus));
+        assert!(diff.added.is_empty() && diff.removed.is_empty() && diff.shifts.is_empty());
+        assert_eq!(diff.totals.ham_before, diff.totals.ham_after);
+        assert_eq!(diff.flips, Some(vec![]));
+    }
+
+    #[test]
+    fn reports_removed_tokens_and_totals() {
+        let old = HSModel::from_bows(
+            BagOfWords::from("hello there world"),
+            BagOfWords::from("hello offer"),
+        );
+        let new = HSModel::from_bows(BagOfWords::from("hello world"), BagOfWords::from("hello"));
+        let diff = ModelDiff::new(&old, &new, 10, None);
+        assert_eq!(diff.removed, vec!["OFFER", "THERE"]);
+        assert_eq!((diff.totals.ham_before, diff.totals.ham_after), (3, 2));
+        assert_eq!((diff.totals.spam_before, diff.totals.spam_after), (2, 1));
+        assert_eq!(diff.flips, None);
+    }
+
+    #[test]
+    fn finds_flipped_documents() {
+        let ham_bow = BagOfWords::from("hello hello hello there world");
+        let spam_bow = BagOfWords::from("hello there world world world world");
+        let new = HSModel::from_bows(ham_bow.clone(), spam_bow.clone());
+        let old = HSModel::from_bows(ham_bow, spam_bow).with_thresholds(Thresholds {
+            ham: 0.5,
+            spam: 0.5,
+        });
+        let corpus = Corpus::new()
+            .add_document("test_resources/test_data/ascii_only.txt", Label::Ham)
+            .add_document("test_resources/test_data/unicode_only.txt", Label::Ham);
+        let flips = ModelDiff::new(&old, &new, 10, Some(&corpus)).flips.unwrap();
+        // ascii_only.txt scores about 0.44, ham under the old thresholds but unsure under the new,
+        // while unicode_only.txt has no known tokens and scores 0.5, unsure under both.
+        assert_eq!(flips.len(), 1);
+        assert_eq!(flips[0].before, Classification::Ham);
+        assert_eq!(flips[0].after, Classification::Unsure);
+        assert_eq!(flips[0].probability_before, flips[0].probability_after);
+    }
+}
@@ -0,0 +1,521 @@
+//! A compact, versioned binary file format for HSModels.
+//! Every file starts with a 20 byte header, with integers in little endian:
+//!
+//! | bytes  | contents                                          |
+//! |--------|---------------------------------------------------|
+//! | 0..4   | magic bytes `RMMR`                                |
+//! | 4..6   | format version, currently 4                       |
+//! | 6..8   | reserved, written as 0                            |
+//! | 8..16  | length of the payload in bytes                    |
+//! | 16..20 | CRC-32 (IEEE) checksum of the payload             |
+//!
+//! The payload is a json metadata block holding the tokenizer the model was trained with and its
+//! [ClassifierConfig](struct.ClassifierConfig.html), followed by the ham and then the spam
+//! BagOfWords. Lengths and counts are LEB128 varints. Each bag is its number of tokens followed by
+//! the tokens in byte order, each written as the length of the prefix it shares with the previous
+//! token, the length of the rest, the rest of its UTF-8 bytes, its count, one more than the
+//! [Timestamp](type.Timestamp.html) it was last seen at, or 0 if it was never stamped, and the
+//! number of documents it was found in. Each bag ends with one more than the Timestamp it was last
+//! decayed at, or 0, the number of documents it was made from, and what rounding left when the
+//! bag was [decayed](struct.BagOfWords.html#method.decay): the number of tokens with a remainder,
+//! then for each the index of the token in the bag, and the remainders of its count and of its
+//! number of documents as little endian f64s, and finally the remainder of the number of
+//! documents. Version 1 files, which have no Timestamps, version 2 files, which have no document
+//! counts, and version 3 files, which have no remainders, are still read.
+//!
+//! [read_from_file](struct.HSModel.html#method.read_from_file) reads this format and the legacy
+//! json written by [write_to_json](struct.HSModel.html#method.write_to_json).
+//! ```no_run
+//! use rammer::HSModel;
+//! let model = HSModel::read_from_file("out/models/enron1_model.json").unwrap();
+//! model.write_to_file("out/models/enron1_model.rmr").unwrap();
+//! ```
+use std::{convert::TryFrom, fs};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{bag_of_words::Remainder, BagOfWords, ClassifierConfig, Count, HSModel, Timestamp};
+
+const MAGIC: &[u8; 4] = b"RMMR";
+const VERSION: u16 = 4;
+const HEADER_LEN: usize = 20;
+
+/// Identifies how text is split into tokens, so a model is never used with a tokenizer other than
+/// the one it was trained with.
+pub(crate) const TOKENIZER: &str = "uax29-word-bounds-uppercase";
+
+/// Everything about a model besides its BagOfWords.
+#[derive(Serialize, Deserialize)]
+struct Metadata {
+    tokenizer: String,
+    config: ClassifierConfig,
+}
+
+#[allow(missing_doc_code_examples)]
+impl HSModel {
+    /// Encode the model in the binary model format. Returns None if the metadata could not be
+    /// serialized.
+    /// ```
+    /// # use rammer::{BagOfWords, HSModel};
+    /// let model = HSModel::from_bows(BagOfWords::from("hello lunch"), BagOfWords::from("free offer"));
+    /// let bytes = model.to_bytes().unwrap();
+    /// assert_eq!(&bytes[..4], b"RMMR");
+    /// ```
+    pub fn to_bytes(&self) -> Option<Vec<u8>> {
+        let metadata = serde_json::to_vec(&Metadata {
+            tokenizer: TOKENIZER.to_string(),
+            config: self.config.clone(),
+        })
+        .ok()?;
+        let mut payload = Vec::new();
+        write_varint(&mut payload, metadata.len() as u64);
+        payload.extend_from_slice(&metadata);
+        write_bag(&mut payload, &self.ham_bow);
+        write_bag(&mut payload, &self.spam_bow);
+
+        let mut bytes = Vec::with_capacity(HEADER_LEN + payload.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&0u16.to_le_bytes());
+        bytes.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&crc32(&payload).to_le_bytes());
+        bytes.extend_from_slice(&payload);
+        Some(bytes)
+    }
+
+    /// Decode a model from the binary model format. Returns None if the bytes are not a model,
+    /// are from a newer format version, fail the checksum, were made with a different tokenizer or
+    /// have a [Calibration](enum.Calibration.html) which isn't valid.
+    /// ```
+    /// # use rammer::{BagOfWords, HSModel};
+    /// let model = HSModel::from_bows(BagOfWords::from("hello lunch"), BagOfWords::from("free offer"));
+    /// let mut bytes = model.to_bytes().unwrap();
+    /// assert_eq!(HSModel::from_bytes(&bytes).unwrap().spam_bow, model.spam_bow);
+    /// let last = bytes.len() - 1;
+    /// bytes[last] ^= 1;
+    /// assert!(HSModel::from_bytes(&bytes).is_none());
+    /// ```
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < HEADER_LEN || &bytes[..4] != MAGIC {
+            return None;
+        }
+        let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+        let mut length = [0; 8];
+        length.copy_from_slice(&bytes[8..16]);
+        let mut checksum = [0; 4];
+        checksum.copy_from_slice(&bytes[16..20]);
+        let payload = &bytes[HEADER_LEN..];
+        if version == 0
+            || version > VERSION
+            || u64::from_le_bytes(length) != payload.len() as u64
+            || u32::from_le_bytes(checksum) != crc32(payload)
+        {
+            return None;
+        }
+
+        let mut reader = Reader {
+            bytes: payload,
+            version,
+        };
+        let metadata_len = reader.varint()? as usize;
+        let metadata: Metadata = serde_json::from_slice(reader.take(metadata_len)?).ok()?;
+        if metadata.tokenizer != TOKENIZER {
+            return None;
+        }
+        let ham_bow = reader.bag()?;
+        let spam_bow = reader.bag()?;
+        if !reader.bytes.is_empty() {
+            return None;
+        }
+        Some(HSModel {
+            ham_bow,
+            spam_bow,
+            config: metadata.config,
+        })
+    }
+
+    /// Write the model to file_path in the binary model format. This write is destructive.
+    /// Returns None if the model could not be written.
+    /// ```no_run
+    /// # use rammer::{HSModel, BagOfWords};
+    /// # let model = HSModel::from_bows(BagOfWords::from("hi greetings afternoon well"), BagOfWords::from("buy pay sell free"));
+    /// model.write_to_file("test_resources/test_models/model.rmr").expect("Write unsucessful");
+    /// ```
+    pub fn write_to_file(&self, file_path: &str) -> Option<()> {
+        fs::write(file_path, self.to_bytes()?).ok()
+    }
+
+    /// Read a model from file_path, in either the binary model format or legacy json.
+    /// ```no_run
+    /// # use rammer::HSModel;
+    /// let model = HSModel::read_from_file("test_resources/test_models/model.rmr").unwrap();
+    /// ```
+    pub fn read_from_file(file_path: &str) -> Option<Self> {
+        let bytes = fs::read(file_path).ok()?;
+        if bytes.starts_with(MAGIC) {
+            Self::from_bytes(&bytes)
+        } else {
+            serde_json::from_slice(&bytes).ok()
+        }
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn write_bag(out: &mut Vec<u8>, bow: &BagOfWords) {
+    let mut tokens: Vec<(&str, Count)> = bow.tokens().collect();
+    tokens.sort_unstable();
+    write_varint(out, tokens.len() as u64);
+    let mut previous: &[u8] = &[];
+    for &(token_str, count) in tokens.iter() {
+        let token = token_str.as_bytes();
+        let shared = previous
+            .iter()
+            .zip(token.iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+        write_varint(out, shared as u64);
+        write_varint(out, (token.len() - shared) as u64);
+        out.extend_from_slice(&token[shared..]);
+        write_varint(out, count);
+        write_varint(out, stamp(bow.token_last_seen(token_str)));
+        write_varint(out, bow.token_document_frequency(token_str).unwrap_or(0));
+        previous = token;
+    }
+    write_varint(out, stamp(bow.decayed_at()));
+    write_varint(out, bow.documents());
+    let mut remainders: Vec<(usize, Remainder)> = bow
+        .remainders()
+        .filter_map(|(token, remainder)| {
+            let index = tokens.binary_search_by(|&(t, _)| t.cmp(token)).ok()?;
+            Some((index, remainder))
+        })
+        .collect();
+    remainders.sort_unstable_by_key(|&(index, _)| index);
+    write_varint(out, remainders.len() as u64);
+    for (index, remainder) in remainders {
+        write_varint(out, index as u64);
+        out.extend_from_slice(&remainder.count.to_le_bytes());
+        out.extend_from_slice(&remainder.documents.to_le_bytes());
+    }
+    out.extend_from_slice(&bow.documents_remainder().to_le_bytes());
+}
+
+/// A Timestamp plus one, or 0 for None.
+fn stamp(time: Option<Timestamp>) -> u64 {
+    time.map_or(0, |t| t.saturating_add(1))
+}
+
+/// Reads values from the front of a payload, returning None when it runs out.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    version: u16,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if n > self.bytes.len() {
+            return None;
+        }
+        let (taken, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Some(taken)
+    }
+
+    fn varint(&mut self) -> Option<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn bag(&mut self) -> Option<BagOfWords> {
+        let len = self.varint()? as usize;
+        // Every token takes at least 3 bytes, so a corrupt length can't reserve huge amounts.
+        let mut counts = Vec::with_capacity(len.min(self.bytes.len() / 3));
+        let mut last_seen = Vec::new();
+        let mut document_frequency = Vec::new();
+        let mut token: Vec<u8> = Vec::new();
+        for _ in 0..len {
+            let shared = self.varint()? as usize;
+            let rest = self.varint()? as usize;
+            if shared > token.len() {
+                return None;
+            }
+            token.truncate(shared);
+            token.extend_from_slice(self.take(rest)?);
+            let count = Count::try_from(self.varint()?).ok()?;
+            let token = String::from_utf8(token.clone()).ok()?;
+            if let Some(seen) = self.stamp()? {
+                last_seen.push((token.clone(), seen));
+            }
+            let frequency = self.document_count()?;
+            if frequency > 0 {
+                document_frequency.push((token.clone(), frequency));
+            }
+            counts.push((token, count));
+        }
+        let decayed_at = self.stamp()?;
+        let documents = self.document_count()?;
+        let mut remainders = Vec::new();
+        let mut documents_remainder = 0.0;
+        if self.version >= 4 {
+            let len = self.varint()? as usize;
+            for _ in 0..len {
+                let index = self.varint()? as usize;
+                let token = counts.get(index)?.0.clone();
+                let count = self.float()?;
+                let documents = self.float()?;
+                remainders.push((token, Remainder { count, documents }));
+            }
+            documents_remainder = self.float()?;
+        }
+        Some(
+            BagOfWords::from_counts(counts)
+                .with_aging(last_seen, decayed_at)
+                .with_documents(documents, document_frequency)
+                .with_remainders(remainders, documents_remainder),
+        )
+    }
+
+    /// A little endian f64, which must be finite.
+    fn float(&mut self) -> Option<f64> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Some(f64::from_le_bytes(bytes)).filter(|f| f.is_finite())
+    }
+
+    /// A number of documents, which files before version 3 don't have.
+    fn document_count(&mut self) -> Option<Count> {
+        if self.version < 3 {
+            return Some(0);
+        }
+        Count::try_from(self.varint()?).ok()
+    }
+
+    /// A Timestamp written by [stamp](fn.stamp.html), which version 1 files don't have.
+    fn stamp(&mut self) -> Option<Option<Timestamp>> {
+        if self.version < 2 {
+            return Some(None);
+        }
+        Some(self.varint()?.checked_sub(1))
+    }
+}
+
+/// Lookup table for the reflected IEEE CRC-32 polynomial.
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC-32 checksum, as used by zip and png.
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |crc, &b| {
+        CRC_TABLE[((crc ^ b as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Calibration, Thresholds};
+
+    fn model() -> HSModel {
+        HSModel::from_bows(
+            BagOfWords::from("hello hello lunch lunchtime ünïcödé 42"),
+            BagOfWords::from("free offer offer offers lunch"),
+        )
+        .with_thresholds(Thresholds {
+            ham: 0.1,
+            spam: 0.95,
+        })
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn round_trip() {
+        let model = model();
+        let decoded = HSModel::from_bytes(&model.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.ham_bow, model.ham_bow);
+        assert_eq!(decoded.spam_bow, model.spam_bow);
+        assert_eq!(decoded.config, model.config);
+    }
+
+    #[test]
+    fn rejects_bad_headers() {
+        let bytes = model().to_bytes().unwrap();
+        let mut newer = bytes.clone();
+        newer[4] = VERSION as u8 + 1;
+        assert!(HSModel::from_bytes(&newer).is_none());
+        assert!(HSModel::from_bytes(&bytes[..bytes.len() - 1]).is_none());
+        assert!(HSModel::from_bytes(b"{\"ham_bow\":{}}").is_none());
+    }
+
+    #[test]
+    fn round_trip_keeps_timestamps() {
+        let mut model = model();
+        model.spam_bow = model
+            .spam_bow
+            .seen_at(1_600_000_000)
+            .decay(1_600_000_100, 3600);
+        model.ham_bow = model.ham_bow.combine(BagOfWords::from("late").seen_at(7));
+        let decoded = HSModel::from_bytes(&model.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.spam_bow.last_seen("offer"), Some(1_600_000_000));
+        assert_eq!(decoded.spam_bow.decayed_at(), Some(1_600_000_100));
+        assert_eq!(decoded.ham_bow.last_seen("late"), Some(7));
+        assert_eq!(decoded.ham_bow.last_seen("hello"), None);
+        assert_eq!(decoded.ham_bow.decayed_at(), None);
+        let remainders: Vec<(&str, Remainder)> = decoded.spam_bow.remainders().collect();
+        assert!(!remainders.is_empty());
+        assert_eq!(remainders.len(), model.spam_bow.remainders().count());
+        for (token, remainder) in remainders {
+            let original = model.spam_bow.remainders().find(|&(t, _)| t == token);
+            assert_eq!(original, Some((token, remainder)));
+        }
+        assert_eq!(
+            decoded.spam_bow.documents_remainder(),
+            model.spam_bow.documents_remainder()
+        );
+    }
+
+    #[test]
+    fn reads_version_1() {
+        let metadata = serde_json::to_vec(&Metadata {
+            tokenizer: TOKENIZER.to_string(),
+            config: ClassifierConfig::default(),
+        })
+        .unwrap();
+        let mut payload = Vec::new();
+        write_varint(&mut payload, metadata.len() as u64);
+        payload.extend_from_slice(&metadata);
+        // A ham bag of {HI: 3} and a spam bag of {HI: 1, HO: 2}, without timestamps.
+        payload.extend_from_slice(&[1, 0, 2, b'H', b'I', 3]);
+        payload.extend_from_slice(&[2, 0, 2, b'H', b'I', 1, 1, 1, b'O', 2]);
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&0u16.to_le_bytes());
+        bytes.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&crc32(&payload).to_le_bytes());
+        bytes.extend_from_slice(&payload);
+        let model = HSModel::from_bytes(&bytes).unwrap();
+        assert_eq!(model.ham_bow, BagOfWords::from("hi hi hi"));
+        assert_eq!(model.spam_bow, BagOfWords::from("hi ho ho"));
+        assert_eq!(model.spam_bow.last_seen("ho"), None);
+    }
+
+    #[test]
+    fn round_trip_keeps_document_counts() {
+        let model = HSModel::from_bows(
+            BagOfWords::from("hello lunch").combine(BagOfWords::from("hello hello")),
+            BagOfWords::from("free offer"),
+        );
+        let decoded = HSModel::from_bytes(&model.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.ham_bow.documents(), 2);
+        assert_eq!(decoded.ham_bow.document_frequency("hello"), Some(2));
+        assert_eq!(decoded.ham_bow.document_frequency("lunch"), Some(1));
+        assert_eq!(decoded.spam_bow.documents(), 1);
+
+        // Version 2 bags have no document counts: an empty ham bag is its length and decay stamp,
+        // and a spam bag of {HI: 3} is its length, the token, its count, stamp and decay stamp.
+        let mut bytes = model.to_bytes().unwrap();
+        let metadata = serde_json::to_vec(&Metadata {
+            tokenizer: TOKENIZER.to_string(),
+            config: ClassifierConfig::default(),
+        })
+        .unwrap();
+        let mut payload = Vec::new();
+        write_varint(&mut payload, metadata.len() as u64);
+        payload.extend_from_slice(&metadata);
+        let v2: Vec<u8> = payload
+            .iter()
+            .chain(&[0, 0])
+            .chain(&[1, 0, 2, b'H', b'I', 3, 0, 0])
+            .cloned()
+            .collect();
+        bytes.truncate(HEADER_LEN);
+        bytes[4..6].copy_from_slice(&2u16.to_le_bytes());
+        bytes[8..16].copy_from_slice(&(v2.len() as u64).to_le_bytes());
+        bytes[16..20].copy_from_slice(&crc32(&v2).to_le_bytes());
+        bytes.extend_from_slice(&v2);
+        let read = HSModel::from_bytes(&bytes).unwrap();
+        assert_eq!(read.spam_bow, BagOfWords::from("hi hi hi"));
+        assert_eq!(read.spam_bow.documents(), 0);
+        assert_eq!(read.spam_bow.document_frequency("hi"), None);
+    }
+
+    #[test]
+    fn rejects_other_tokenizers() {
+        let bytes = model().to_bytes().unwrap();
+        let at = bytes
+            .windows(TOKENIZER.len())
+            .position(|w| w == TOKENIZER.as_bytes())
+            .unwrap();
+        let mut other = bytes.clone();
+        other[at] = b'x';
+        let checksum = crc32(&other[HEADER_LEN..]);
+        other[16..20].copy_from_slice(&checksum.to_le_bytes());
+        assert!(HSModel::from_bytes(&other).is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_calibrations() {
+        let calibrated = |calibration| model().with_calibration(calibration).to_bytes().unwrap();
+        let valid = calibrated(Calibration::Isotonic {
+            log_odds: vec![-1.0, 1.0],
+            probabilities: vec![0.2, 0.6],
+        });
+        assert!(HSModel::from_bytes(&valid).is_some());
+        let mismatched = calibrated(Calibration::Isotonic {
+            log_odds: vec![-1.0, 1.0],
+            probabilities: vec![0.2],
+        });
+        assert!(HSModel::from_bytes(&mismatched).is_none());
+    }
+
+    #[test]
+    fn reads_legacy_json() {
+        let dir = std::env::temp_dir().join(format!("rammer-model-file-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let json = dir.join("model.json");
+        let binary = dir.join("model.rmr");
+        let model = model();
+        model.write_to_json(json.to_str().unwrap()).unwrap();
+        model.write_to_file(binary.to_str().unwrap()).unwrap();
+        for path in [json, binary].iter() {
+            let read = HSModel::read_from_file(path.to_str().unwrap()).unwrap();
+            assert_eq!(read.spam_bow, model.spam_bow);
+            assert_eq!(read.config, model.config);
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,333 @@
+//! Statistics about a trained model, for models far too large to read as json.
+//! A ModelSummary gives the vocabulary sizes and total counts of each BagOfWords, a histogram of
+//! how often tokens were seen and the tokens which push hardest towards spam and towards ham.
+//! [token_stats](struct.HSModel.html#method.token_stats) looks up a single token.
+//! ```no_run
+//! use rammer::{HSModel, ModelSummary};
+//! let model = HSModel::read_from_file("out/models/enron1_model.json").unwrap();
+//! println!("{}", ModelSummary::new(&model, 20, 5).to_text());
+//! println!("{:?}", model.token_stats("free"));
+//! ```
+use serde::{Deserialize, Serialize};
+
+use crate::{Count, HSModel, Probability, TokenCounts};
+
+/// The counts of a single token in a model.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct TokenStats {
+    /// The uppercased token.
+    pub token: String,
+    /// Times the token was seen in ham.
+    pub ham: Count,
+    /// Times the token was seen in spam.
+    pub spam: Count,
+    /// Probability that a text containing only this token is spam under the model's
+    /// [Scoring](enum.Scoring.html), or None if the scoring ignores it. Multinomial scoring only
+    /// counts tokens seen in both ham and spam, while the others count any token seen in training.
+    pub probability: Option<Probability>,
+}
+
+/// The number of distinct tokens seen between min and max times, inclusive.
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct CountBucket {
+    /// Smallest count in the bucket.
+    pub min: Count,
+    /// Largest count in the bucket.
+    pub max: Count,
+    /// Number of ham tokens with a count in the bucket.
+    pub ham_tokens: usize,
+    /// Number of spam tokens with a count in the bucket.
+    pub spam_tokens: usize,
+}
+
+/// Vocabulary sizes, totals and the most telling tokens of an HSModel.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct ModelSummary {
+    /// Distinct tokens seen in ham.
+    pub ham_vocabulary: usize,
+    /// Distinct tokens seen in spam.
+    pub spam_vocabulary: usize,
+    /// Distinct tokens seen in both, the only ones multinomial scoring counts.
+    pub shared_vocabulary: usize,
+    /// Total count of every ham token.
+    pub ham_total: Count,
+    /// Total count of every spam token.
+    pub spam_total: Count,
+    /// Token counts bucketed by powers of two: 1, 2-3, 4-7 and so on, up to the largest count.
+    pub histogram: Vec<CountBucket>,
+    /// Smallest combined ham and spam count of the tokens listed below.
+    pub min_count: Count,
+    /// Tokens with the highest spam probability, highest first.
+    pub spammiest: Vec<TokenStats>,
+    /// Tokens with the lowest spam probability, lowest first.
+    pub hammiest: Vec<TokenStats>,
+}
+
+#[allow(missing_doc_code_examples)]
+impl HSModel {
+    /// The ham and spam counts and spam probability of a single token, under the model's scoring.
+    /// The token is uppercased, like text is when it is scored.
+    /// ```
+    /// # use rammer::{BagOfWords, HSModel};
+    /// let model = HSModel::from_bows(BagOfWords::from("hello offer"), BagOfWords::from("offer offer free free"));
+    /// let stats = model.token_stats("offer");
+    /// assert_eq!((stats.ham, stats.spam), (1, 2));
+    /// assert_eq!(stats.probability, Some(0.5));
+    /// assert_eq!(model.token_stats("hello").probability, None);
+    /// ```
+    pub fn token_stats(&self, token: &str) -> TokenStats {
+        let token = token.to_uppercase();
+        let (ham, spam) = self.counts(&token);
+        let probability = self
+            .token_score(&token, &self.scoring_weights(), self.totals())
+            .map(|(p, _)| p);
+        TokenStats {
+            token,
+            ham,
+            spam,
+            probability,
+        }
+    }
+}
+
+#[allow(missing_doc_code_examples)]
+impl ModelSummary {
+    /// Summarize model, listing the top spammiest and hammiest tokens among those its scoring
+    /// counts which were seen at least min_count times in ham and spam combined.
+    /// ```
+    /// # use rammer::{BagOfWords, HSModel, ModelSummary};
+    /// let model = HSModel::from_bows(
+    ///     BagOfWords::from("hello hello lunch offer"),
+    ///     BagOfWords::from("offer offer offer hello free"),
+    /// );
+    /// let summary = ModelSummary::new(&model, 1, 1);
+    /// assert_eq!((summary.ham_vocabulary, summary.spam_vocabulary, summary.shared_vocabulary), (3, 3, 2));
+    /// assert_eq!(summary.spammiest[0].token, "OFFER");
+    /// assert_eq!(summary.hammiest[0].token, "HELLO");
+    /// ```
+    pub fn new(model: &HSModel, top: usize, min_count: Count) -> Self {
+        let totals = model.totals();
+        let mut histogram: Vec<CountBucket> = Vec::new();
+        let mut add = |count: Count, spam: bool| {
+            let bucket = (Count::BITS - 1 - count.leading_zeros()) as usize;
+            while histogram.len() <= bucket {
+                let min: Count = 1 << histogram.len();
+                histogram.push(CountBucket {
+                    min,
+                    max: min.saturating_sub(1).saturating_add(min),
+                    ham_tokens: 0,
+                    spam_tokens: 0,
+                });
+            }
+            if spam {
+                histogram[bucket].spam_tokens += 1;
+            } else {
+                histogram[bucket].ham_tokens += 1;
+            }
+        };
+        for (_, count) in model.ham_bow.tokens().filter(|&(_, c)| c > 0) {
+            add(count, false);
+        }
+        for (_, count) in model.spam_bow.tokens().filter(|&(_, c)| c > 0) {
+            add(count, true);
+        }
+
+        let weights = model.scoring_weights();
+        let mut scored: Vec<TokenStats> = model
+            .ham_bow
+            .tokens()
+            .chain(
+                model
+                    .spam_bow
+                    .tokens()
+                    .filter(|(token, _)| model.ham_bow.token_count(token).is_none()),
+            )
+            .filter_map(|(token, _)| {
+                let (ham, spam) = model.counts(token);
+                if ham.saturating_add(spam) < min_count {
+                    return None;
+                }
+                let (probability, _) = model.token_score(token, &weights, totals)?;
+                Some(TokenStats {
+                    token: token.to_string(),
+                    ham,
+                    spam,
+                    probability: Some(probability),
+                })
+            })
+            .collect();
+        let shared_vocabulary = model
+            .ham_bow
+            .tokens()
+            .filter(|(token, _)| model.spam_bow.token_count(token).is_some())
+            .count();
+        // Ties are broken by how often the token was seen, then alphabetically, so the order is
+        // stable.
+        scored.sort_by(|a, b| {
+            b.probability
+                .unwrap_or(0.5)
+                .total_cmp(&a.probability.unwrap_or(0.5))
+                .then_with(|| {
+                    b.ham
+                        .saturating_add(b.spam)
+                        .cmp(&a.ham.saturating_add(a.spam))
+                })
+                .then_with(|| a.token.cmp(&b.token))
+        });
+        let spammiest = scored.iter().take(top).cloned().collect();
+        scored.sort_by(|a, b| {
+            a.probability
+                .unwrap_or(0.5)
+                .total_cmp(&b.probability.unwrap_or(0.5))
+                .then_with(|| {
+                    b.ham
+                        .saturating_add(b.spam)
+                        .cmp(&a.ham.saturating_add(a.spam))
+                })
+                .then_with(|| a.token.cmp(&b.token))
+        });
+        scored.truncate(top);
+
+        ModelSummary {
+            ham_vocabulary: model.ham_bow.len(),
+            spam_vocabulary: model.spam_bow.len(),
+            shared_vocabulary,
+            ham_total: totals.0,
+            spam_total: totals.1,
+            histogram,
+            min_count,
+            spammiest,
+            hammiest: scored,
+        }
+    }
+
+    /// The summary as a human readable report.
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "{:<18} {:>12} {:>12}\n\
+             {:<18} {:>12} {:>12}\n\
+             {:<18} {:>12} {:>12}\n\
+             {:<18} {:>12}\n\n\
+             Token counts:\n{:>12} {:>12} {:>12}\n",
+            "",
+            "ham",
+            "spam",
+            "vocabulary",
+            self.ham_vocabulary,
+            self.spam_vocabulary,
+            "total count",
+            self.ham_total,
+            self.spam_total,
+            "shared vocabulary",
+            self.shared_vocabulary,
+            "seen",
+            "ham tokens",
+            "spam tokens",
+        );
+        for bucket in &self.histogram {
+            let seen = if bucket.min == bucket.max {
+                bucket.min.to_string()
+            } else {
+                format!("{}-{}", bucket.min, bucket.max)
+            };
+            text.push_str(&format!(
+                "{:>12} {:>12} {:>12}\n",
+                seen, bucket.ham_tokens, bucket.spam_tokens
+            ));
+        }
+        for (title, tokens) in [("Spammiest", &self.spammiest), ("Hammiest", &self.hammiest)].iter()
+        {
+            text.push_str(&format!(
+                "\n{} tokens seen at least {} times:\n{:<24} {:>10} {:>10} {:>12}\n",
+                title, self.min_count, "token", "ham", "spam", "probability"
+            ));
+            for t in tokens.iter() {
+                text.push_str(&t.to_row());
+            }
+        }
+        text
+    }
+
+    /// The summary as pretty printed json.
+    pub fn to_json(&self) -> Option<String> {
+        serde_json::to_string_pretty(self).ok()
+    }
+}
+
+#[allow(missing_doc_code_examples)]
+impl TokenStats {
+    /// The token as a line of a human readable table, with columns token, ham, spam and
+    /// probability.
+    pub fn to_row(&self) -> String {
+        let probability = self
+            .probability
+            .map_or_else(|| "-".to_string(), |p| format!("{:.6}", p));
+        format!(
+            "{:<24} {:>10} {:>10} {:>12}\n",
+            self.token, self.ham, self.spam, probability
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BagOfWords, Scoring};
+
+    fn model() -> HSModel {
+        HSModel::from_bows(
+            BagOfWords::from("a a a a a b b c d d d d d d d d"),
+            BagOfWords::from("a b b b c e e"),
+        )
+    }
+
+    #[test]
+    fn histogram_buckets_by_powers_of_two() {
+        let summary = ModelSummary::new(&model(), 10, 0);
+        let buckets: Vec<(Count, Count, usize, usize)> = summary
+            .histogram
+            .iter()
+            .map(|b| (b.min, b.max, b.ham_tokens, b.spam_tokens))
+            .collect();
+        // ham counts are a 5, b 2, c 1, d 8; spam counts are a 1, b 3, c 1, e 2.
+        assert_eq!(
+            buckets,
+            vec![(1, 1, 1, 2), (2, 3, 1, 2), (4, 7, 1, 0), (8, 15, 1, 0)]
+        );
+        assert_eq!((summary.ham_total, summary.spam_total), (16, 7));
+    }
+
+    #[test]
+    fn min_count_filters_listed_tokens() {
+        let summary = ModelSummary::new(&model(), 10, 4);
+        let spammiest: Vec<&str> = summary.spammiest.iter().map(|t| &t.token[..]).collect();
+        let hammiest: Vec<&str> = summary.hammiest.iter().map(|t| &t.token[..]).collect();
+        assert_eq!(spammiest, vec!["B", "A"]);
+        assert_eq!(hammiest, vec!["A", "B"]);
+        assert_eq!(summary.shared_vocabulary, 3);
+        assert!(summary.to_text().contains("seen at least 4 times"));
+    }
+
+    #[test]
+    fn unknown_tokens_have_no_probability() {
+        let stats = model().token_stats("missing");
+        assert_eq!(
+            (stats.token.as_str(), stats.ham, stats.spam),
+            ("MISSING", 0, 0)
+        );
+        assert_eq!(stats.probability, None);
+        assert!(stats.to_row().ends_with(" -\n"));
+    }
+
+    #[test]
+    fn probabilities_follow_the_model_scoring() {
+        // E is only seen in spam, so multinomial scoring ignores it but complement scoring doesn't.
+        let complement = model().with_scoring(Scoring::Complement);
+        let stats = complement.token_stats("e");
+        assert!(stats.probability.unwrap() > 0.5);
+        assert_eq!(model().token_stats("e").probability, None);
+        let summary = ModelSummary::new(&complement, 1, 1);
+        assert_eq!(summary.spammiest[0].token, "E");
+        assert_eq!(summary.shared_vocabulary, 3);
+    }
+}
@@ -0,0 +1,352 @@
+//! A naive Bayes model for any number of classes, such as routing mail into newsletters,
+//! notifications, personal mail and spam. Each class is a label with its own BagOfWords.
+//!
+//! A class starts out as likely as the share of training documents it was given, and each token
+//! of a text multiplies in its Laplace smoothed frequency in the class, (count + 1) / (total +
+//! vocabulary), where the vocabulary is every token seen in any class. Tokens seen in only one
+//! class count, which are usually the ones that tell categories apart, and tokens never seen in
+//! training are ignored. Priors are smoothed the same way, so a class with no counted documents
+//! isn't ruled out.
+//!
+//! [HSModel](struct.HSModel.html)'s multinomial scoring is unsmoothed, without priors, and only
+//! counts tokens seen in both ham and spam, so existing models keep their scores. A model
+//! [converted](struct.HSModel.html#method.to_multi_class) from an HSModel classifies the same
+//! training data this way instead.
+//! ```no_run
+//! use rammer::{BagOfWords, MultiClassModel};
+//! let model = MultiClassModel::new()
+//!     .add_bow("newsletter", BagOfWords::from_folder("data/newsletter").unwrap())
+//!     .add_bow("personal", BagOfWords::from_folder("data/personal").unwrap())
+//!     .add_bow("spam", BagOfWords::from_folder("data/spam").unwrap());
+//! let text = "Hey, are we still on for lunch?";
+//! println!("{:?} {:?}", model.classify(text), model.text_posterior(text));
+//! ```
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{BagOfWords, Frequency, HSModel, Probability, Scoring};
+
+/// A naive Bayes model with a BagOfWords for each class label.
+/// ```
+/// # use rammer::{BagOfWords, MultiClassModel};
+/// let model = MultiClassModel::new()
+///     .add_bow("personal", BagOfWords::from("lunch with you lunch"))
+///     .add_bow("newsletter", BagOfWords::from("news for you this week at lunch"))
+///     .add_bow("spam", BagOfWords::from("you you won free lunch"));
+/// assert_eq!(model.classify("lunch with you").as_deref(), Some("personal"));
+/// ```
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(from = "MultiClassFile")]
+pub struct MultiClassModel {
+    /// The training BagOfWords of each class, by label.
+    classes: BTreeMap<String, BagOfWords>,
+    /// Number of distinct tokens across every class, kept up to date as bags are added.
+    #[serde(skip)]
+    vocabulary: usize,
+}
+
+/// The saved fields of a MultiClassModel, before its vocabulary is counted.
+#[derive(Deserialize)]
+struct MultiClassFile {
+    classes: BTreeMap<String, BagOfWords>,
+}
+
+impl From<MultiClassFile> for MultiClassModel {
+    #[allow(missing_doc_code_examples)]
+    fn from(file: MultiClassFile) -> Self {
+        let vocabulary = file
+            .classes
+            .values()
+            .flat_map(|class| class.tokens().map(|(token, _)| token))
+            .collect::<HashSet<&str>>()
+            .len();
+        MultiClassModel {
+            classes: file.classes,
+            vocabulary,
+        }
+    }
+}
+
+#[allow(missing_doc_code_examples)]
+impl MultiClassModel {
+    /// Create a new model with no classes.
+    /// ```
+    /// # use rammer::MultiClassModel;
+    /// let model = MultiClassModel::new();
+    /// assert_eq!(model.classify("anything"), None);
+    /// ```
+    pub fn new() -> Self {
+        MultiClassModel {
+            classes: BTreeMap::new(),
+            vocabulary: 0,
+        }
+    }
+
+    /// Builder pattern for adding training text to a class, with the
+    /// [combine](struct.BagOfWords.html#method.combine) method. The class is created if it is new.
+    /// ```
+    /// # use rammer::{BagOfWords, MultiClassModel};
+    /// let model = MultiClassModel::new()
+    ///     .add_bow("personal", BagOfWords::from("lunch"))
+    ///     .add_bow("personal", BagOfWords::from("dinner"));
+    /// assert_eq!(model.classes()["personal"].len(), 2);
+    /// ```
+    pub fn add_bow(mut self, label: &str, bow: BagOfWords) -> Self {
+        self.vocabulary += bow
+            .tokens()
+            .filter(|(token, _)| {
+                self.classes
+                    .values()
+                    .all(|class| class.token_count(token).is_none())
+            })
+            .count();
+        let class = self.classes.remove(label).unwrap_or_default();
+        self.classes.insert(label.to_string(), class.combine(bow));
+        self
+    }
+
+    /// The training BagOfWords of each class, by label.
+    pub fn classes(&self) -> &BTreeMap<String, BagOfWords> {
+        &self.classes
+    }
+
+    /// The probability of each class given a slice of text. The probabilities sum to 1, and are
+    /// the classes' priors if no token in the text was seen in training.
+    /// ```
+    /// # use rammer::{BagOfWords, MultiClassModel};
+    /// let model = MultiClassModel::new()
+    ///     .add_bow("a", BagOfWords::from("x x y"))
+    ///     .add_bow("b", BagOfWords::from("x y y"));
+    /// let posterior = model.text_posterior("y");
+    /// assert!(posterior["b"] > posterior["a"]);
+    /// assert!((posterior.values().sum::<f64>() - 1.0).abs() < 1e-12);
+    /// ```
+    pub fn text_posterior(&self, text: &str) -> BTreeMap<String, Probability> {
+        self.bow_posterior(&BagOfWords::from(text))
+    }
+
+    /// The probability of each class given text already tokenized into a BagOfWords.
+    pub fn bow_posterior(&self, bow: &BagOfWords) -> BTreeMap<String, Probability> {
+        let vocabulary = self.vocabulary as Frequency;
+        let documents: Frequency = self
+            .classes
+            .values()
+            .map(|class| class.documents() as Frequency)
+            .sum();
+        let classes = self.classes.len() as Frequency;
+        let mut log_likelihoods: Vec<f64> = self
+            .classes
+            .values()
+            .map(|class| {
+                Frequency::ln((class.documents() as Frequency + 1.0) / (documents + classes))
+            })
+            .collect();
+        for (token, occurrences) in bow.tokens() {
+            let counts: Vec<Frequency> = self
+                .classes
+                .values()
+                .map(|class| class.token_count(token).unwrap_or(0) as Frequency)
+                .collect();
+            if counts.iter().all(|&count| count == 0.0) {
+                continue;
+            }
+            for ((l, count), class) in log_likelihoods
+                .iter_mut()
+                .zip(counts)
+                .zip(self.classes.values())
+            {
+                let total = class.total_word_count() as Frequency;
+                *l += occurrences as f64 * Frequency::ln((count + 1.0) / (total + vocabulary));
+            }
+        }
+
+        // Subtract the largest log likelihood before exponentiating so long texts don't
+        // underflow to zero.
+        let max = log_likelihoods
+            .iter()
+            .cloned()
+            .fold(f64::NEG_INFINITY, f64::max);
+        let weights: Vec<f64> = log_likelihoods.iter().map(|l| (l - max).exp()).collect();
+        let sum: f64 = weights.iter().sum();
+        self.classes
+            .keys()
+            .cloned()
+            .zip(weights.into_iter().map(|w| w / sum))
+            .collect()
+    }
+
+    /// The most probable class label for a slice of text, or None if the model has no classes.
+    /// Ties go to the label which sorts first.
+    pub fn classify(&self, text: &str) -> Option<String> {
+        self.text_posterior(text)
+            .into_iter()
+            .fold(
+                None,
+                |best: Option<(String, Probability)>, (label, p)| match best {
+                    Some((_, best_p)) if best_p >= p => best,
+                    _ => Some((label, p)),
+                },
+            )
+            .map(|(label, _)| label)
+    }
+
+    /// Serialize the model to json and write it to file_path. This write is destructive.
+    /// Returns None if the model could not be written.
+    /// ```no_run
+    /// # use rammer::{BagOfWords, MultiClassModel};
+    /// # let model = MultiClassModel::new().add_bow("personal", BagOfWords::from("lunch"));
+    /// model.write_to_json("out/models/categories.json").expect("Write unsucessful");
+    /// ```
+    pub fn write_to_json(&self, file_path: &str) -> Option<()> {
+        fs::write(file_path, serde_json::to_string(self).ok()?).ok()
+    }
+
+    /// Read a model written by [write_to_json](#method.write_to_json).
+    /// ```no_run
+    /// # use rammer::MultiClassModel;
+    /// let model = MultiClassModel::read_from_json("out/models/categories.json").unwrap();
+    /// ```
+    pub fn read_from_json(file_path: &str) -> Option<Self> {
+        serde_json::from_slice(&fs::read(file_path).ok()?).ok()
+    }
+}
+
+#[allow(missing_doc_code_examples)]
+impl HSModel {
+    /// A two-class MultiClassModel of this model's training data, with its bags labelled `ham`
+    /// and `spam`. It scores with [MultiClassModel](struct.MultiClassModel.html)'s smoothing and
+    /// priors, so its `spam` posterior differs from this model's spam probability, most for texts
+    /// with tokens seen in only one class. Returns None unless the model uses
+    /// [multinomial scoring](enum.Scoring.html#variant.Multinomial) of every token without a
+    /// calibration, as a MultiClassModel can't keep other settings. Thresholds aren't kept either,
+    /// since a MultiClassModel classifies text as its most probable class.
+    /// ```
+    /// # use rammer::{BagOfWords, Calibration, HSModel};
+    /// let model = HSModel::from_bows(BagOfWords::from("hello lunch offer"), BagOfWords::from("free offer"));
+    /// let multi = model.to_multi_class().unwrap();
+    /// assert_eq!(multi.classify("free offer").as_deref(), Some("spam"));
+    /// let calibrated = model.with_calibration(Calibration::Platt { slope: 2.0, intercept: 0.0 });
+    /// assert!(calibrated.to_multi_class().is_none());
+    /// ```
+    pub fn to_multi_class(&self) -> Option<MultiClassModel> {
+        let config = &self.config;
+        if config.scoring != Scoring::Multinomial
+            || config.significant.is_some()
+            || config.calibration.is_some()
+        {
+            return None;
+        }
+        Some(
+            MultiClassModel::new()
+                .add_bow("ham", self.ham_bow.clone())
+                .add_bow("spam", self.spam_bow.clone()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Calibration, SignificantTokens, Thresholds};
+
+    fn documents(texts: &[&str]) -> BagOfWords {
+        texts.iter().map(|&text| BagOfWords::from(text)).collect()
+    }
+
+    #[test]
+    fn matches_the_definition() {
+        let model = MultiClassModel::new()
+            .add_bow("a", documents(&["x x y"]))
+            .add_bow("b", documents(&["x y y", "z"]));
+        // Vocabulary X, Y and Z, with a 1 and b 2 of the 3 documents. W was never seen.
+        let a = 2.0 / 5.0 * (2.0 / 6.0);
+        let b = 3.0 / 5.0 * (3.0 / 7.0);
+        let posterior = model.text_posterior("y w");
+        assert!((posterior["b"] - b / (a + b)).abs() < 1e-12);
+        let prior = model.text_posterior("w");
+        assert!((prior["a"] - 2.0 / 5.0).abs() < 1e-12);
+
+        let read: MultiClassModel =
+            serde_json::from_str(&serde_json::to_string(&model).unwrap()).unwrap();
+        assert_eq!((read.vocabulary, read), (3, model));
+    }
+
+    #[test]
+    fn tokens_of_one_class_route_text() {
+        let model = MultiClassModel::new()
+            .add_bow(
+                "newsletter",
+                BagOfWords::from("weekly digest unsubscribe news the"),
+            )
+            .add_bow("notification", BagOfWords::from("alert build failed the"))
+            .add_bow("personal", BagOfWords::from("lunch with you the"))
+            .add_bow("spam", BagOfWords::from("free offer winner the"));
+        let text = "digest digest weekly unsubscribe";
+        let posterior = model.text_posterior(text);
+        assert!(posterior["newsletter"] > 0.75, "{:?}", posterior);
+        assert_eq!(
+            model.classify("build failed").as_deref(),
+            Some("notification")
+        );
+        assert_eq!(model.classify("free lunch offer").as_deref(), Some("spam"));
+        // Unknown tokens leave every class at its prior, and ties go to the first label.
+        let uniform = model.text_posterior("zzz");
+        assert!(uniform.values().all(|&p| (p - 0.25).abs() < 1e-12));
+        assert_eq!(model.classify("zzz").as_deref(), Some("newsletter"));
+    }
+
+    #[test]
+    fn priors_follow_documents() {
+        let model = MultiClassModel::new()
+            .add_bow("rare", documents(&["hello"]))
+            .add_bow("common", documents(&["hello", "hello", "hello"]));
+        // HELLO is all of both classes, so only the priors differ.
+        assert_eq!(model.classify("hello").as_deref(), Some("common"));
+        // Bags without counted documents are still possible.
+        let uncounted = MultiClassModel::new()
+            .add_bow("a", BagOfWords::from_counts(vec![("X".to_string(), 3)]))
+            .add_bow("b", BagOfWords::from("y"));
+        assert!(uncounted.text_posterior("x")["a"] > 0.5);
+    }
+
+    #[test]
+    fn converts_only_models_scored_the_same_way() {
+        let model = HSModel::from_bows(
+            BagOfWords::from("hello lunch offer"),
+            BagOfWords::from("free offer offer"),
+        )
+        .with_thresholds(Thresholds {
+            ham: 0.1,
+            spam: 0.9,
+        });
+        let multi = model.to_multi_class().unwrap();
+        assert_eq!(multi.classes()["ham"], model.ham_bow);
+        assert_eq!(multi.classes()["spam"], model.spam_bow);
+        let converted = |model: HSModel| model.to_multi_class().is_some();
+        assert!(!converted(model.clone().with_scoring(Scoring::Complement)));
+        assert!(!converted(model.clone().with_scoring(Scoring::Bernoulli)));
+        assert!(!converted(
+            model
+                .clone()
+                .with_significant_tokens(SignificantTokens::default())
+        ));
+        assert!(!converted(model.with_calibration(Calibration::Platt {
+            slope: 1.0,
+            intercept: 0.5,
+        })));
+    }
+
+    #[test]
+    fn long_texts_do_not_underflow() {
+        let model = MultiClassModel::new()
+            .add_bow("a", BagOfWords::from("x x x y"))
+            .add_bow("b", BagOfWords::from("x y y y"));
+        let posterior = model.text_posterior(&"y ".repeat(5000));
+        assert_eq!(posterior["b"], 1.0);
+        assert!(posterior["a"].is_finite());
+    }
+}
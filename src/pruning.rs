@@ -0,0 +1,303 @@
+//! Shrinking a model by dropping the tokens which do the least to its scores.
+//! Models only grow as bags are combined, and most of their tokens are seen once or have a spam
+//! probability close to 0.5, so they cost memory and load time without changing classifications.
+//! Pruning removes a token from both BagOfWords, which also lowers the totals that word
+//! frequencies are calculated from, so check the [PruneReport](struct.PruneReport.html) before
+//! replacing a model with its pruned version.
+//! ```no_run
+//! use rammer::{Corpus, HSModel, PruneReport};
+//! let model = HSModel::read_from_file("out/models/enron1_model.json").unwrap();
+//! let pruned = model.clone().prune_min_count(3).prune_neutral(0.05).prune_top_k(20000);
+//! let validation = Corpus::from_folders("data/validate/ham", "data/validate/spam").unwrap();
+//! println!("{}", PruneReport::new(&model, &pruned, &validation).unwrap().to_text());
+//! ```
+use std::{collections::HashSet, time::Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::TokenizedDocument, evaluation::score_tokenized, logistic, Corpus, Count,
+    EvaluationReport, HSModel, Metrics, Probability, TokenCounts,
+};
+
+#[allow(missing_doc_code_examples)]
+impl HSModel {
+    /// Drop every token seen fewer than min_count times in ham and spam combined.
+    /// ```
+    /// # use rammer::{BagOfWords, HSModel};
+    /// let model = HSModel::from_bows(BagOfWords::from("hello lunch"), BagOfWords::from("hello offer"));
+    /// let pruned = model.prune_min_count(2);
+    /// assert_eq!((pruned.ham_bow.len(), pruned.spam_bow.len()), (1, 1));
+    /// ```
+    pub fn prune_min_count(self, min_count: Count) -> Self {
+        self.retain(|ham, spam, _| ham.saturating_add(spam) >= min_count)
+    }
+
+    /// Drop tokens whose log odds under the model's [Scoring](enum.Scoring.html) give a spam
+    /// probability less than epsilon away from 0.5, as they barely move a score. Tokens the
+    /// scoring ignores, like those seen in only one BagOfWords with multinomial scoring, are kept.
+    /// ```
+    /// # use rammer::{BagOfWords, HSModel};
+    /// let model = HSModel::from_bows(BagOfWords::from("hello offer"), BagOfWords::from("hello free"));
+    /// let pruned = model.prune_neutral(0.01);
+    /// assert_eq!(pruned.ham_bow, BagOfWords::from("offer"));
+    /// ```
+    pub fn prune_neutral(self, epsilon: Probability) -> Self {
+        self.retain(|_, _, p| p.is_none_or(|p| (p - 0.5).abs() >= epsilon))
+    }
+
+    /// Keep only the k most informative tokens, those which moved the scores of the training data
+    /// most: their combined count times the absolute log odds the model's
+    /// [Scoring](enum.Scoring.html) gives them. Tokens the scoring ignores, like those seen in
+    /// only one BagOfWords with multinomial scoring, go first. Ties are broken alphabetically.
+    /// ```
+    /// # use rammer::{BagOfWords, HSModel};
+    /// let model = HSModel::from_bows(
+    ///     BagOfWords::from("hello hello offer lunch"),
+    ///     BagOfWords::from("hello offer offer offer"),
+    /// );
+    /// let pruned = model.prune_top_k(1);
+    /// assert_eq!(pruned.spam_bow, BagOfWords::from("offer offer offer"));
+    /// ```
+    pub fn prune_top_k(self, k: usize) -> Self {
+        let totals = self.totals();
+        let weights = self.scoring_weights();
+        let mut ranked: Vec<(f64, &str)> = self
+            .ham_bow
+            .tokens()
+            .chain(self.spam_bow.tokens())
+            .map(|(token, _)| token)
+            .collect::<HashSet<&str>>()
+            .into_iter()
+            .map(|token| {
+                let (ham, spam) = self.counts(token);
+                let weight = self
+                    .token_score(token, &weights, totals)
+                    .map_or(0.0, |(_, log_odds)| {
+                        (ham as f64 + spam as f64) * log_odds.abs()
+                    });
+                (weight, token)
+            })
+            .collect();
+        if ranked.len() <= k {
+            return self;
+        }
+        ranked.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+        let kept: HashSet<String> = ranked
+            .into_iter()
+            .take(k)
+            .map(|(_, token)| token.to_string())
+            .collect();
+        HSModel {
+            ham_bow: self.ham_bow.retain(|token, _| kept.contains(token)),
+            spam_bow: self.spam_bow.retain(|token, _| kept.contains(token)),
+            config: self.config,
+        }
+    }
+
+    /// Keep the tokens for which keep, given the ham count, spam count and the spam probability of
+    /// the token's log odds under the model's scoring, returns true.
+    fn retain<F: Fn(Count, Count, Option<Probability>) -> bool>(self, keep: F) -> Self {
+        let totals = self.totals();
+        let weights = self.scoring_weights();
+        let dropped: HashSet<String> = self
+            .ham_bow
+            .tokens()
+            .chain(self.spam_bow.tokens())
+            .filter(|(token, _)| {
+                let (ham, spam) = self.counts(token);
+                let p = self
+                    .token_score(token, &weights, totals)
+                    .map(|(_, log_odds)| logistic(log_odds));
+                !keep(ham, spam, p)
+            })
+            .map(|(token, _)| token.to_string())
+            .collect();
+        HSModel {
+            ham_bow: self.ham_bow.retain(|token, _| !dropped.contains(token)),
+            spam_bow: self.spam_bow.retain(|token, _| !dropped.contains(token)),
+            config: self.config,
+        }
+    }
+}
+
+/// How much smaller a pruned model is, and how it does on validation data compared to the
+/// original.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct PruneReport {
+    /// Distinct tokens in the original model.
+    pub tokens_before: usize,
+    /// Distinct tokens in the pruned model.
+    pub tokens_after: usize,
+    /// Size of the original model in the binary model format.
+    pub bytes_before: usize,
+    /// Size of the pruned model in the binary model format.
+    pub bytes_after: usize,
+    /// The original model evaluated on the validation data.
+    pub before: EvaluationReport,
+    /// The pruned model evaluated on the validation data.
+    pub after: EvaluationReport,
+}
+
+#[allow(missing_doc_code_examples)]
+impl PruneReport {
+    /// Compare the original model before with its pruned version after on validation. Each is
+    /// classified with its own thresholds. Returns None if either model can't be encoded.
+    /// ```
+    /// # use rammer::{BagOfWords, Corpus, HSModel, Label, PruneReport};
+    /// let model = HSModel::from_bows(BagOfWords::from("hello there world"), BagOfWords::from("hello offer"));
+    /// let pruned = model.clone().prune_min_count(2);
+    /// let validation = Corpus::new().add_folder("test_resources/test_data", Label::Ham).unwrap();
+    /// let report = PruneReport::new(&model, &pruned, &validation).unwrap();
+    /// assert_eq!((report.tokens_before, report.tokens_after), (4, 1));
+    /// assert!(report.bytes_after < report.bytes_before);
+    /// ```
+    pub fn new(before: &HSModel, after: &HSModel, validation: &Corpus) -> Option<Self> {
+        let docs = validation.tokenize();
+        let docs: Vec<&TokenizedDocument> = docs.iter().collect();
+        let evaluate = |model: &HSModel| {
+            let start = Instant::now();
+            let scored = score_tokenized(model, &docs);
+            let seconds = start.elapsed().as_secs_f64();
+            EvaluationReport::from_scores(&scored, &model.config.thresholds, seconds)
+        };
+        Some(PruneReport {
+            tokens_before: vocabulary_size(before),
+            tokens_after: vocabulary_size(after),
+            bytes_before: before.to_bytes()?.len(),
+            bytes_after: after.to_bytes()?.len(),
+            before: evaluate(before),
+            after: evaluate(after),
+        })
+    }
+
+    /// Change in accuracy from pruning, negative if the pruned model is less accurate.
+    pub fn accuracy_change(&self) -> f64 {
+        self.after.metrics.accuracy - self.before.metrics.accuracy
+    }
+
+    /// The report as a human readable table.
+    pub fn to_text(&self) -> String {
+        let reduction = |before: usize, after: usize| {
+            if before == 0 {
+                0.0
+            } else {
+                100.0 * (before as f64 - after as f64) / before as f64
+            }
+        };
+        let mut text = format!(
+            "{:<20} {:>12} {:>12} {:>10}\n\
+             {:<20} {:>12} {:>12} {:>9.1}%\n\
+             {:<20} {:>12} {:>12} {:>9.1}%\n",
+            "",
+            "before",
+            "after",
+            "reduction",
+            "tokens",
+            self.tokens_before,
+            self.tokens_after,
+            reduction(self.tokens_before, self.tokens_after),
+            "bytes",
+            self.bytes_before,
+            self.bytes_after,
+            reduction(self.bytes_before, self.bytes_after),
+        );
+        text.push_str(&format!(
+            "\n{:<20} {:>12} {:>12} {:>10}\n",
+            "", "before", "after", "change"
+        ));
+        let names = Metrics::NAMES.iter();
+        let before = self.before.metrics.values();
+        let after = self.after.metrics.values();
+        for ((name, b), a) in names.zip(before.iter()).zip(after.iter()) {
+            text.push_str(&format!(
+                "{:<20} {:>12.4} {:>12.4} {:>+10.4}\n",
+                name,
+                b,
+                a,
+                a - b
+            ));
+        }
+        text
+    }
+
+    /// The report as pretty printed json.
+    pub fn to_json(&self) -> Option<String> {
+        serde_json::to_string_pretty(self).ok()
+    }
+}
+
+/// Distinct tokens across both of a model's BagOfWords.
+fn vocabulary_size(model: &HSModel) -> usize {
+    model.ham_bow.len()
+        + model
+            .spam_bow
+            .tokens()
+            .filter(|(t, _)| model.ham_bow.token_count(t).is_none())
+            .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BagOfWords, Scoring, Thresholds};
+
+    fn model() -> HSModel {
+        HSModel::from_bows(
+            BagOfWords::from("hello hello hello lunch meeting meeting offer"),
+            BagOfWords::from("hello offer offer offer free free meeting"),
+        )
+        .with_thresholds(Thresholds {
+            ham: 0.3,
+            spam: 0.7,
+        })
+    }
+
+    #[test]
+    fn pruning_keeps_config_and_drops_from_both_bags() {
+        let pruned = model().prune_min_count(4);
+        assert_eq!(pruned.ham_bow, BagOfWords::from("hello hello hello offer"));
+        assert_eq!(pruned.spam_bow, BagOfWords::from("hello offer offer offer"));
+        assert_eq!(pruned.config, model().config);
+        assert_eq!(model().prune_min_count(0).ham_bow, model().ham_bow);
+    }
+
+    #[test]
+    fn top_k_prefers_informative_tokens() {
+        let model = model();
+        // HELLO and OFFER are strongly ham and spam and seen 4 times each, MEETING is seen 3
+        // times, and LUNCH and FREE are never scored.
+        let pruned = model.clone().prune_top_k(2);
+        assert_eq!(vocabulary_size(&pruned), 2);
+        assert!(pruned.ham_bow.token_count("HELLO").is_some());
+        assert!(pruned.spam_bow.token_count("OFFER").is_some());
+        assert_eq!(model.clone().prune_top_k(100).ham_bow, model.ham_bow);
+        assert_eq!(vocabulary_size(&model.prune_top_k(0)), 0);
+    }
+
+    #[test]
+    fn neutral_tokens_are_dropped() {
+        // MEETING has spam probability 1/7 / (1/7 + 2/7) = 1/3, so is only neutral at 0.2.
+        let kept = model().prune_neutral(0.1);
+        assert_eq!(vocabulary_size(&kept), 5);
+        let pruned = model().prune_neutral(0.2);
+        assert!(pruned.ham_bow.token_count("MEETING").is_none());
+        assert!(pruned.spam_bow.token_count("FREE").is_some());
+    }
+
+    #[test]
+    fn pruning_follows_the_model_scoring() {
+        let model = HSModel::from_bows(
+            BagOfWords::from("hello hello weekly"),
+            BagOfWords::from("hello hello viagra"),
+        )
+        .with_scoring(Scoring::Complement);
+        // VIAGRA is only seen in spam, which complement scoring counts, while HELLO is neutral.
+        let pruned = model.clone().prune_top_k(1);
+        assert!(pruned.spam_bow.token_count("VIAGRA").is_some());
+        assert!(pruned.ham_bow.token_count("HELLO").is_none());
+        let pruned = model.prune_neutral(0.01);
+        assert!(pruned.spam_bow.token_count("VIAGRA").is_some());
+        assert!(pruned.ham_bow.token_count("HELLO").is_none());
+    }
+}
@@ -0,0 +1,285 @@
+//! ROC and precision-recall curves for scored validation data, and picking
+//! [Thresholds](struct.Thresholds.html) from them instead of guessing.
+//! ```no_run
+//! use rammer::{optimize_thresholds, score_corpus, Corpus, HSModel, RocCurve};
+//! let model = HSModel::read_from_json("out/models/enron1_model.json").unwrap();
+//! let corpus = Corpus::from_folders("data/validate/ham", "data/validate/spam").unwrap();
+//! let scored = score_corpus(&model, &corpus);
+//! println!("AUC: {:.4}", RocCurve::from_scores(&scored).auc());
+//! let thresholds = optimize_thresholds(&scored, 0.01, 0.05).expect("need both ham and spam");
+//! model.with_thresholds(thresholds).write_to_json("out/models/enron1_model.json");
+//! ```
+use serde::{Deserialize, Serialize};
+
+use crate::{Label, Probability, ScoredDocument, Thresholds};
+
+/// One point of a ROC or precision-recall curve: the rates reached when every document with a
+/// probability of at least `threshold` is called spam.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct CurvePoint {
+    /// Documents scoring at least this are called spam.
+    pub threshold: Probability,
+    /// Log odds of the threshold. Thresholds of long documents round to 0.0 or 1.0, but their log
+    /// odds still tell the points apart.
+    pub threshold_log_odds: f64,
+    /// Fraction of ham called spam.
+    pub false_positive_rate: f64,
+    /// Fraction of spam called spam, also known as recall.
+    pub true_positive_rate: f64,
+    /// Fraction of documents called spam which are spam.
+    pub precision: f64,
+}
+
+/// A ROC curve, which is also the precision-recall curve, with one point per distinct log odds
+/// from the highest to the lowest.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RocCurve {
+    /// Points ordered from the highest threshold to the lowest.
+    pub points: Vec<CurvePoint>,
+}
+
+#[allow(missing_doc_code_examples)]
+impl RocCurve {
+    /// Build the curve from scored documents.
+    /// ```
+    /// # use rammer::{logit, Label, RocCurve, ScoredDocument};
+    /// let scored = vec![
+    ///     ScoredDocument { path: "a".into(), label: Label::Spam, probability: 0.9, log_odds: logit(0.9) },
+    ///     ScoredDocument { path: "b".into(), label: Label::Ham, probability: 0.1, log_odds: logit(0.1) },
+    /// ];
+    /// let curve = RocCurve::from_scores(&scored);
+    /// assert_eq!(curve.points.len(), 2);
+    /// assert_eq!(curve.auc(), 1.0);
+    /// ```
+    pub fn from_scores(scored: &[ScoredDocument]) -> Self {
+        let spam = scored.iter().filter(|d| d.label == Label::Spam).count();
+        let ham = scored.len() - spam;
+        let mut sorted: Vec<&ScoredDocument> = scored.iter().collect();
+        sorted.sort_by(|a, b| b.log_odds.total_cmp(&a.log_odds));
+
+        let mut points = Vec::new();
+        let (mut tp, mut fp) = (0, 0);
+        for (i, doc) in sorted.iter().enumerate() {
+            match doc.label {
+                Label::Spam => tp += 1,
+                Label::Ham => fp += 1,
+            }
+            let last_of_tie = sorted
+                .get(i + 1)
+                .is_none_or(|next| next.log_odds != doc.log_odds);
+            if last_of_tie {
+                points.push(CurvePoint {
+                    threshold: doc.probability,
+                    threshold_log_odds: doc.log_odds,
+                    false_positive_rate: ratio(fp, ham),
+                    true_positive_rate: ratio(tp, spam),
+                    precision: ratio(tp, tp + fp),
+                });
+            }
+        }
+        RocCurve { points }
+    }
+
+    /// Area under the ROC curve, using the trapezoid rule so tied scores count as half right.
+    /// 1.0 means every spam scored above every ham, 0.5 is no better than chance.
+    pub fn auc(&self) -> f64 {
+        let mut area = 0.0;
+        let (mut x, mut y) = (0.0, 0.0);
+        for p in &self.points {
+            area += (p.false_positive_rate - x) * (p.true_positive_rate + y) / 2.0;
+            x = p.false_positive_rate;
+            y = p.true_positive_rate;
+        }
+        area
+    }
+
+    /// Area under the precision-recall curve, calculated as average precision.
+    pub fn average_precision(&self) -> f64 {
+        let mut area = 0.0;
+        let mut recall = 0.0;
+        for p in &self.points {
+            area += (p.true_positive_rate - recall) * p.precision;
+            recall = p.true_positive_rate;
+        }
+        area
+    }
+
+    /// The curve as csv, with a header line.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "threshold,threshold_log_odds,false_positive_rate,true_positive_rate,precision\n",
+        );
+        for p in &self.points {
+            csv.push_str(&format!(
+                "{},{},{},{},{}\n",
+                p.threshold,
+                p.threshold_log_odds,
+                p.false_positive_rate,
+                p.true_positive_rate,
+                p.precision
+            ));
+        }
+        csv
+    }
+}
+
+fn ratio(numerator: usize, denominator: usize) -> f64 {
+    if denominator == 0 {
+        0.0
+    } else {
+        numerator as f64 / denominator as f64
+    }
+}
+
+/// Pick Thresholds from scored validation data.
+/// The spam threshold is the lowest cutoff which calls at most `max_false_positive_rate` of ham
+/// spam, catching as much spam as that allows. The ham threshold is the highest cutoff which
+/// calls at most `max_false_negative_rate` of spam ham. Documents between the two are unsure.
+/// Cutoffs are placed halfway between neighbouring scores. Returns None unless scored contains
+/// both ham and spam.
+/// ```
+/// # use rammer::{logit, optimize_thresholds, Label, ScoredDocument};
+/// let doc = |label, probability| ScoredDocument { path: "x".into(), label, probability, log_odds: logit(probability) };
+/// let scored = vec![doc(Label::Ham, 0.1), doc(Label::Ham, 0.3), doc(Label::Spam, 0.5), doc(Label::Spam, 0.9)];
+/// let thresholds = optimize_thresholds(&scored, 0.0, 0.0).unwrap();
+/// assert_eq!((thresholds.ham, thresholds.spam), (0.4, 0.4));
+/// ```
+pub fn optimize_thresholds(
+    scored: &[ScoredDocument],
+    max_false_positive_rate: f64,
+    max_false_negative_rate: f64,
+) -> Option<Thresholds> {
+    let spam: Vec<Probability> = scores(scored, Label::Spam);
+    let ham: Vec<Probability> = scores(scored, Label::Ham);
+    if spam.is_empty() || ham.is_empty() {
+        return None;
+    }
+    let mut distinct: Vec<Probability> = spam.iter().chain(ham.iter()).copied().collect();
+    distinct.sort_by(f64::total_cmp);
+    distinct.dedup();
+
+    let mut cutoffs = vec![distinct[distinct.len() - 1]];
+    cutoffs.extend(distinct.windows(2).rev().map(|w| (w[0] + w[1]) / 2.0));
+    cutoffs.push(0.0);
+
+    // cutoffs run from high to low, so the spam threshold only gets lower while it is allowed.
+    let allowed_fp = max_false_positive_rate * ham.len() as f64;
+    let spam_threshold = cutoffs
+        .iter()
+        .take_while(|&&t| (ham.len() - ham.partition_point(|&p| p <= t)) as f64 <= allowed_fp)
+        .last()
+        .copied()
+        .unwrap_or(1.0);
+
+    let allowed_fn = max_false_negative_rate * spam.len() as f64;
+    let ham_threshold = cutoffs
+        .iter()
+        .rev()
+        .take_while(|&&t| spam.partition_point(|&p| p < t) as f64 <= allowed_fn)
+        .last()
+        .copied()
+        .unwrap_or(0.0);
+
+    Some(Thresholds {
+        ham: ham_threshold.min(spam_threshold),
+        spam: spam_threshold,
+    })
+}
+
+/// Sorted probabilities of the documents with label.
+fn scores(scored: &[ScoredDocument], label: Label) -> Vec<Probability> {
+    let mut scores: Vec<Probability> = scored
+        .iter()
+        .filter(|d| d.label == label)
+        .map(|d| d.probability)
+        .collect();
+    scores.sort_by(f64::total_cmp);
+    scores
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Classification;
+
+    fn doc(label: Label, probability: Probability) -> ScoredDocument {
+        ScoredDocument {
+            path: "x".into(),
+            label,
+            probability,
+            log_odds: crate::logit(probability),
+        }
+    }
+
+    #[test]
+    fn saturated_probabilities_rank_by_log_odds() {
+        let doc = |label, log_odds| ScoredDocument {
+            path: "x".into(),
+            label,
+            probability: 1.0,
+            log_odds,
+        };
+        let scored = vec![doc(Label::Ham, 800.0), doc(Label::Spam, 900.0)];
+        let curve = RocCurve::from_scores(&scored);
+        assert_eq!(curve.points.len(), 2);
+        assert_eq!(curve.auc(), 1.0);
+    }
+
+    #[test]
+    fn tied_scores_are_chance() {
+        let scored = vec![doc(Label::Spam, 0.5), doc(Label::Ham, 0.5)];
+        let curve = RocCurve::from_scores(&scored);
+        assert_eq!(curve.points.len(), 1);
+        assert_eq!(curve.auc(), 0.5);
+        assert_eq!(curve.average_precision(), 0.5);
+    }
+
+    #[test]
+    fn auc_counts_ordered_pairs() {
+        // 3 of the 4 spam/ham pairs are ordered correctly.
+        let scored = vec![
+            doc(Label::Spam, 0.9),
+            doc(Label::Ham, 0.7),
+            doc(Label::Spam, 0.6),
+            doc(Label::Ham, 0.2),
+        ];
+        let curve = RocCurve::from_scores(&scored);
+        assert_eq!(curve.auc(), 0.75);
+        assert_eq!(curve.points[0].precision, 1.0);
+        assert_eq!(curve.average_precision(), 0.5 * 1.0 + 0.5 * (2.0 / 3.0));
+    }
+
+    #[test]
+    fn thresholds_respect_false_positive_rate() {
+        let mut scored: Vec<ScoredDocument> =
+            (0..10).map(|i| doc(Label::Ham, i as f64 / 20.0)).collect();
+        scored.extend((0..10).map(|i| doc(Label::Spam, 0.4 + i as f64 / 20.0)));
+        scored.push(doc(Label::Ham, 0.95));
+
+        let thresholds = optimize_thresholds(&scored, 0.1, 0.0).unwrap();
+        let fp = scored
+            .iter()
+            .filter(|d| {
+                d.label == Label::Ham && thresholds.classify(d.probability) == Classification::Spam
+            })
+            .count();
+        let fn_ = scored
+            .iter()
+            .filter(|d| {
+                d.label == Label::Spam && thresholds.classify(d.probability) == Classification::Ham
+            })
+            .count();
+        assert!(fp as f64 <= 0.1 * 11.0);
+        assert_eq!(fn_, 0);
+        assert!(thresholds.ham <= thresholds.spam);
+        // a stricter target can only raise the spam threshold
+        let strict = optimize_thresholds(&scored, 0.0, 0.0).unwrap();
+        assert!(strict.spam >= thresholds.spam);
+        assert!(strict.spam >= 0.95);
+    }
+
+    #[test]
+    fn one_class_is_none() {
+        assert!(optimize_thresholds(&[doc(Label::Ham, 0.1)], 0.1, 0.1).is_none());
+    }
+}
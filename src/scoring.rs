@@ -0,0 +1,246 @@
+//! Dispatch from a model's [Scoring](enum.Scoring.html) to the calculation it scores text with.
+//! Complement and Bernoulli scoring need constants which depend on every token in the model, so
+//! they are worked out once into ScoringWeights and shared by every text a caller scores, rather
+//! than on every call. A [Scorer](struct.Scorer.html) holds them for callers outside the crate. Models set to score only their
+//! [significant tokens](struct.SignificantTokens.html) look up each token's log odds here too,
+//! as do models which only have token counts, like [MappedModel](struct.MappedModel.html).
+//! Multinomial scoring only counts tokens seen in both ham and spam, without smoothing or
+//! priors, unlike [MultiClassModel](struct.MultiClassModel.html)'s posterior.
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    bernoulli::BernoulliWeights, complement::ComplementWeights, logistic, BagOfWords,
+    Classification, Count, Explanation, HSModel, Probability, Scoring, SignificantTokens,
+    TokenCounts,
+};
+
+/// Whatever a model's scoring needs besides the token counts.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub(crate) enum ScoringWeights {
+    Multinomial,
+    Complement(ComplementWeights),
+    Bernoulli(BernoulliWeights),
+}
+
+impl ScoringWeights {
+    /// Log odds of a text none of whose tokens affect its score.
+    pub(crate) fn base(&self) -> f64 {
+        match self {
+            ScoringWeights::Bernoulli(weights) => weights.absent(),
+            _ => 0.0,
+        }
+    }
+}
+
+#[allow(missing_doc_code_examples)]
+impl HSModel {
+    /// Builder pattern for setting how text is scored, saved with the model like its thresholds.
+    /// ```
+    /// # use rammer::{HSModel, Scoring};
+    /// let model = HSModel::new().with_scoring(Scoring::Complement);
+    /// assert_eq!(model.config.scoring, Scoring::Complement);
+    /// ```
+    pub fn with_scoring(mut self, scoring: Scoring) -> Self {
+        self.config.scoring = scoring;
+        self
+    }
+
+    /// Work out the weights of the model's scoring once, to score many texts with. HSModel's own
+    /// scoring methods work them out on every call, which for
+    /// [complement](enum.Scoring.html#variant.Complement) and
+    /// [Bernoulli](enum.Scoring.html#variant.Bernoulli) scoring means going through every token in
+    /// the model. The model can't change while it has a scorer.
+    /// ```
+    /// # use rammer::{BagOfWords, HSModel, Scoring};
+    /// let model = HSModel::from_bows(BagOfWords::from("hello lunch"), BagOfWords::from("free offer"))
+    ///     .with_scoring(Scoring::Complement);
+    /// let scorer = model.scorer();
+    /// for text in &["free lunch", "hello offer"] {
+    ///     assert_eq!(scorer.text_spam_probability(text), model.text_spam_probability(text));
+    /// }
+    /// ```
+    pub fn scorer(&self) -> Scorer<'_> {
+        Scorer {
+            model: self,
+            weights: self.scoring_weights(),
+        }
+    }
+
+    /// The weights of the model's scoring. Callers scoring many texts work these out once.
+    pub(crate) fn scoring_weights(&self) -> ScoringWeights {
+        match self.config.scoring {
+            Scoring::Multinomial => ScoringWeights::Multinomial,
+            Scoring::Complement => ScoringWeights::Complement(ComplementWeights::for_model(self)),
+            Scoring::Bernoulli => ScoringWeights::Bernoulli(BernoulliWeights::for_model(self)),
+        }
+    }
+
+    /// The spam probability of a text containing only token, and the log odds the token adds to a
+    /// text each time it is counted, or None if it doesn't affect the score. totals is passed in
+    /// so callers scoring many tokens look it up once.
+    pub(crate) fn token_score(
+        &self,
+        token: &str,
+        weights: &ScoringWeights,
+        totals: (Count, Count),
+    ) -> Option<(Probability, f64)> {
+        match weights {
+            ScoringWeights::Multinomial => {
+                let log_odds = self.multinomial_token_log_odds(token, totals)?;
+                Some((logistic(log_odds), log_odds))
+            }
+            ScoringWeights::Complement(weights) => {
+                let (ham, spam) = self.counts(token);
+                let log_odds = weights.token_log_odds(ham, spam)?;
+                Some((logistic(log_odds), log_odds))
+            }
+            ScoringWeights::Bernoulli(weights) => {
+                let log_odds = weights.token_log_odds(
+                    self.ham_bow.token_document_frequency(token).unwrap_or(0),
+                    self.spam_bow.token_document_frequency(token).unwrap_or(0),
+                )?;
+                Some((logistic(weights.absent() + log_odds), log_odds))
+            }
+        }
+    }
+
+    /// Log odds one occurrence of token adds with multinomial scoring, or None unless it was seen
+    /// in both ham and spam: the same weight as [TokenCounts](trait.TokenCounts.html) gives it.
+    fn multinomial_token_log_odds(&self, token: &str, totals: (Count, Count)) -> Option<f64> {
+        self.token_log_odds(token, totals)
+    }
+
+    /// Multinomial log odds of a text made of tokens, each with its number of occurrences.
+    fn multinomial_log_odds<'t, I: Iterator<Item = (&'t str, Count)>>(&self, tokens: I) -> f64 {
+        let totals = self.totals();
+        tokens
+            .filter_map(|(token, occurrences)| {
+                self.multinomial_token_log_odds(token, totals)
+                    .map(|log_odds| occurrences as f64 * log_odds)
+            })
+            .sum()
+    }
+
+    /// Log odds of a text made of tokens, scoring only its significant tokens, or None if the
+    /// model scores every token.
+    fn significant_log_odds<'t, I: Iterator<Item = &'t str>>(
+        &self,
+        tokens: I,
+        weights: &ScoringWeights,
+    ) -> Option<f64> {
+        let significant = self.config.significant?;
+        let totals = self.totals();
+        Some(significant.log_odds(tokens, weights.base(), |token| {
+            self.token_score(token, weights, totals)
+                .map(|(_, log_odds)| log_odds)
+        }))
+    }
+
+    /// Log odds of a slice of text, scored with weights.
+    pub(crate) fn text_log_odds_with(&self, text: &str, weights: &ScoringWeights) -> f64 {
+        if self.config.significant.is_some() {
+            let upper = text.to_uppercase();
+            let tokens = upper.split_word_bounds().filter(|&s| !s.trim().is_empty());
+            if let Some(n) = self.significant_log_odds(tokens, weights) {
+                return n;
+            }
+        }
+        match weights {
+            ScoringWeights::Multinomial => {
+                let upper = text.to_uppercase();
+                let tokens = upper.split_word_bounds().filter(|&s| !s.trim().is_empty());
+                self.multinomial_log_odds(tokens.map(|token| (token, 1)))
+            }
+            ScoringWeights::Complement(weights) => weights.text_log_odds(self, text),
+            ScoringWeights::Bernoulli(weights) => weights.text_log_odds(self, text),
+        }
+    }
+
+    /// Log odds of text already tokenized into a BagOfWords, scored with weights.
+    pub(crate) fn bow_log_odds_with(&self, bow: &BagOfWords, weights: &ScoringWeights) -> f64 {
+        let tokens = bow.tokens().map(|(token, _)| token);
+        if let Some(n) = self.significant_log_odds(tokens, weights) {
+            return n;
+        }
+        match weights {
+            ScoringWeights::Multinomial => self.multinomial_log_odds(bow.tokens()),
+            ScoringWeights::Complement(weights) => weights.log_odds(self, bow.tokens()),
+            ScoringWeights::Bernoulli(weights) => weights.bow_log_odds(self, bow),
+        }
+    }
+}
+
+/// An HSModel with the weights of its scoring worked out, made by
+/// [HSModel::scorer](struct.HSModel.html#method.scorer). It scores text exactly as the model's own
+/// methods of the same names do.
+#[derive(Clone, Copy)]
+pub struct Scorer<'m> {
+    model: &'m HSModel,
+    weights: ScoringWeights,
+}
+
+#[allow(missing_doc_code_examples)]
+impl<'m> Scorer<'m> {
+    /// The model being scored with.
+    pub fn model(&self) -> &'m HSModel {
+        self.model
+    }
+
+    /// The same as [HSModel::text_log_odds](struct.HSModel.html#method.text_log_odds).
+    pub fn text_log_odds(&self, text: &str) -> f64 {
+        self.model.text_log_odds_with(text, &self.weights)
+    }
+
+    /// The same as [HSModel::text_spam_probability](struct.HSModel.html#method.text_spam_probability).
+    pub fn text_spam_probability(&self, text: &str) -> Probability {
+        self.model.config.probability(self.text_log_odds(text))
+    }
+
+    /// The same as [HSModel::bow_log_odds](struct.HSModel.html#method.bow_log_odds).
+    pub fn bow_log_odds(&self, bow: &BagOfWords) -> f64 {
+        self.model.bow_log_odds_with(bow, &self.weights)
+    }
+
+    /// The same as [HSModel::bow_spam_probability](struct.HSModel.html#method.bow_spam_probability).
+    pub fn bow_spam_probability(&self, bow: &BagOfWords) -> Probability {
+        self.model.config.probability(self.bow_log_odds(bow))
+    }
+
+    /// The same as [HSModel::classify](struct.HSModel.html#method.classify).
+    pub fn classify(&self, text: &str) -> Classification {
+        self.model
+            .classify_probability(self.text_spam_probability(text))
+    }
+
+    /// The same as [HSModel::explain](struct.HSModel.html#method.explain).
+    pub fn explain(&self, text: &str) -> Explanation {
+        self.model.explain_with(text, &self.weights)
+    }
+}
+
+/// Log odds of a slice of text for a model which only has token counts, scored with complement
+/// weights if it has them and multinomial naive Bayes otherwise, over only its significant tokens
+/// if significant is set.
+pub(crate) fn counts_text_log_odds<C: TokenCounts + ?Sized>(
+    model: &C,
+    complement: Option<&ComplementWeights>,
+    significant: Option<SignificantTokens>,
+    text: &str,
+) -> f64 {
+    if let Some(significant) = significant {
+        let totals = model.totals();
+        let upper = text.to_uppercase();
+        let tokens = upper.split_word_bounds().filter(|&s| !s.trim().is_empty());
+        return significant.log_odds(tokens, 0.0, |token| match complement {
+            Some(weights) => {
+                let (ham, spam) = model.counts(token);
+                weights.token_log_odds(ham, spam)
+            }
+            None => model.token_log_odds(token, totals),
+        });
+    }
+    match complement {
+        Some(weights) => weights.text_log_odds(model, text),
+        None => model.multinomial_text_log_odds(text),
+    }
+}
@@ -0,0 +1,128 @@
+//! Scoring only a text's most significant tokens, as in Paul Graham's
+//! [A Plan for Spam](http://www.paulgraham.com/spam.html).
+//! Every scoring method sums the log odds of all the known tokens in a text, so a long ham
+//! message can be pushed towards spam by hundreds of mildly spammy words which each say very
+//! little. With [SignificantTokens](struct.SignificantTokens.html) set in a model's config, only
+//! the distinct tokens whose spam probabilities lie furthest from 0.5 are scored, each counted
+//! once however often it occurs, as Graham did.
+//! ```
+//! use rammer::{BagOfWords, HSModel, SignificantTokens};
+//! let ham = format!("{}{}", "meeting ".repeat(6), "report lunch ".repeat(2));
+//! let spam = format!("meeting {}", "report lunch offer ".repeat(3));
+//! let model = HSModel::from_bows(BagOfWords::from(&ham[..]), BagOfWords::from(&spam[..]));
+//! // One strongly ham token among many mildly spammy ones.
+//! let text = format!("meeting {}", "report lunch ".repeat(20));
+//! assert!(model.text_spam_probability(&text) > 0.5);
+//! let model = model.with_significant_tokens(SignificantTokens { count: 1, min_deviation: 0.0 });
+//! assert!(model.text_spam_probability(&text) < 0.5);
+//! ```
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{logistic, Probability};
+
+/// How many tokens of a text are scored, and how far from 0.5 their spam probabilities must be.
+/// ```
+/// # use rammer::SignificantTokens;
+/// let significant = SignificantTokens::default();
+/// assert_eq!((significant.count, significant.min_deviation), (15, 0.0));
+/// ```
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct SignificantTokens {
+    /// Most distinct tokens scored in a text. Graham used 15.
+    pub count: usize,
+    /// Tokens whose spam probability is closer to 0.5 than this are never scored.
+    pub min_deviation: Probability,
+}
+
+impl Default for SignificantTokens {
+    fn default() -> Self {
+        SignificantTokens {
+            count: 15,
+            min_deviation: 0.0,
+        }
+    }
+}
+
+impl SignificantTokens {
+    /// How far from 0.5 the spam probability of a token with the given log odds lies.
+    /// ```
+    /// # use rammer::SignificantTokens;
+    /// assert_eq!(SignificantTokens::deviation(0.0), 0.0);
+    /// assert!((SignificantTokens::deviation(-(3.0f64).ln()) - 0.25).abs() < 1e-12);
+    /// ```
+    pub fn deviation(log_odds: f64) -> Probability {
+        (logistic(log_odds) - 0.5).abs()
+    }
+
+    /// Keep the tokens to score from tokens and their log odds: at most count of them, furthest
+    /// from 0.5 first, leaving out any closer than min_deviation. Ties are broken by token so the
+    /// choice doesn't depend on the order tokens are given in.
+    pub(crate) fn select<T: Ord>(&self, mut tokens: Vec<(T, f64)>) -> Vec<(T, f64)> {
+        tokens.retain(|&(_, log_odds)| Self::deviation(log_odds) >= self.min_deviation);
+        tokens.sort_by(|a, b| {
+            b.1.abs()
+                .partial_cmp(&a.1.abs())
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.0.cmp(&b.0))
+        });
+        tokens.truncate(self.count);
+        tokens
+    }
+
+    /// Log odds that a text made of tokens is spam, scoring only its significant distinct tokens.
+    /// log_odds gives a token's log odds, or None if it doesn't affect the score, and base is the
+    /// log odds of a text with no scored tokens.
+    pub(crate) fn log_odds<'t, I, F>(&self, tokens: I, base: f64, log_odds: F) -> f64
+    where
+        I: Iterator<Item = &'t str>,
+        F: Fn(&str) -> Option<f64>,
+    {
+        let distinct: HashSet<&str> = tokens.collect();
+        let scored = distinct
+            .into_iter()
+            .filter_map(|token| log_odds(token).map(|w| (token, w)))
+            .collect();
+        base + self.select(scored).iter().map(|&(_, w)| w).sum::<f64>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn select_keeps_the_furthest_from_even() {
+        let significant = SignificantTokens {
+            count: 2,
+            min_deviation: 0.1,
+        };
+        let tokens = vec![("A", 0.1), ("B", -3.0), ("C", 2.0), ("D", 3.0), ("E", 0.5)];
+        // A is within 0.1 of 0.5, and B and D tie, so B comes first.
+        assert_eq!(significant.select(tokens), vec![("B", -3.0), ("D", 3.0)]);
+        let none = SignificantTokens {
+            count: 5,
+            min_deviation: 0.3,
+        };
+        assert!(none.select(vec![("A", 1.0), ("B", -1.0)]).is_empty());
+    }
+
+    #[test]
+    fn tokens_count_once() {
+        let significant = SignificantTokens::default();
+        let log_odds = |token: &str| match token {
+            "FREE" => Some(2.0),
+            "HELLO" => Some(-1.0),
+            _ => None,
+        };
+        let once = significant.log_odds(["FREE", "HELLO"].iter().cloned(), 0.0, log_odds);
+        let repeated = significant.log_odds(
+            ["FREE", "FREE", "UNKNOWN", "HELLO", "FREE"].iter().cloned(),
+            0.0,
+            log_odds,
+        );
+        assert_eq!(once, repeated);
+        assert_eq!(once, 1.0);
+    }
+}
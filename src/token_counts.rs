@@ -0,0 +1,103 @@
+//! The token counts a model needs to score text, whatever they are stored in.
+//! Anything which can report how often a token was seen in ham and in spam gets the same
+//! multinomial naive Bayes scoring as [HSModel](struct.HSModel.html), so read-only or approximate
+//! representations of a model give identical scores for identical counts. The trait's scoring
+//! methods are always multinomial and ignore a model's config; each model's own
+//! `text_spam_probability` applies its scoring, significant tokens and calibration.
+//! ```
+//! use rammer::{BagOfWords, HSModel, TokenCounts};
+//! let model = HSModel::from_bows(BagOfWords::from("hello lunch"), BagOfWords::from("hello offer"));
+//! assert_eq!(model.counts("HELLO"), (1, 1));
+//! assert_eq!(model.multinomial_text_spam_probability("hello"), 0.5);
+//! ```
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{logistic, BagOfWords, Count, Frequency, Probability};
+
+/// Ham and spam counts of tokens, and the scoring shared by every model representation.
+/// Tokens are looked up already uppercased, as produced by
+/// [From<&str>](struct.BagOfWords.html#impl-From%3C%26str%3E).
+pub trait TokenCounts {
+    /// Total number of tokens seen in ham and in spam.
+    fn totals(&self) -> (Count, Count);
+
+    /// Number of times token was seen in ham and in spam, 0 for unseen.
+    fn counts(&self, token: &str) -> (Count, Count);
+
+    /// Probability that a text containing only token is spam, or None unless the token was seen in
+    /// both ham and spam. totals is passed in so callers scoring many tokens look it up once.
+    #[allow(missing_doc_code_examples)]
+    fn token_spam_probability(
+        &self,
+        token: &str,
+        (ham_total, spam_total): (Count, Count),
+    ) -> Option<Probability> {
+        let (ham, spam) = self.counts(token);
+        if ham == 0 || spam == 0 {
+            return None;
+        }
+        let spam_freq = spam as Frequency / spam_total as Frequency;
+        let ham_freq = ham as Frequency / ham_total as Frequency;
+        Some(spam_freq / (spam_freq + ham_freq))
+    }
+
+    /// Log odds one occurrence of token adds to a text, positive towards spam, or None unless the
+    /// token was seen in both ham and spam. This is the log of the token's frequency in spam less
+    /// the log of its frequency in ham, unsmoothed, so tokens seen in only one class are skipped
+    /// rather than given infinite weight.
+    #[allow(missing_doc_code_examples)]
+    fn token_log_odds(&self, token: &str, (ham_total, spam_total): (Count, Count)) -> Option<f64> {
+        let (ham, spam) = self.counts(token);
+        if ham == 0 || spam == 0 {
+            return None;
+        }
+        Some(
+            Frequency::ln(spam as Frequency / spam_total as Frequency)
+                - Frequency::ln(ham as Frequency / ham_total as Frequency),
+        )
+    }
+
+    /// Returns the multinomial log odds that a slice of text is spam, positive towards spam,
+    /// whatever scoring the model's config sets. Unlike the probability these keep growing with
+    /// long texts, so they rank texts without ties. Read about how this is calulated here on the
+    /// [Naive Bayes Spam Filtering Wikipedia Page](https://en.wikipedia.org/wiki/Naive_Bayes_spam_filtering)
+    #[allow(missing_doc_code_examples)]
+    fn multinomial_text_log_odds(&self, text: &str) -> f64 {
+        let totals = self.totals();
+        text.to_uppercase()
+            .split_word_bounds()
+            .filter(|&s| !s.trim().is_empty())
+            .filter_map(|word| self.token_log_odds(word, totals))
+            .sum()
+    }
+
+    /// Returns the multinomial probability that a slice of text is spam, the
+    /// [logistic](fn.logistic.html) of its [log odds](#method.multinomial_text_log_odds).
+    #[allow(missing_doc_code_examples)]
+    fn multinomial_text_spam_probability(&self, text: &str) -> Probability {
+        logistic(self.multinomial_text_log_odds(text))
+    }
+
+    /// Returns the multinomial log odds that text already tokenized into a BagOfWords is spam. This
+    /// is the same calculation as [multinomial_text_log_odds](#method.multinomial_text_log_odds),
+    /// up to floating point rounding.
+    #[allow(missing_doc_code_examples)]
+    fn multinomial_bow_log_odds(&self, bow: &BagOfWords) -> f64 {
+        let totals = self.totals();
+        bow.tokens()
+            .filter_map(|(word, count)| {
+                self.token_log_odds(word, totals)
+                    .map(|log_odds| count as f64 * log_odds)
+            })
+            .sum()
+    }
+
+    /// Returns the multinomial probability that text already tokenized into a BagOfWords is spam.
+    /// This is the same calculation as
+    /// [multinomial_text_spam_probability](#method.multinomial_text_spam_probability), up to
+    /// floating point rounding.
+    #[allow(missing_doc_code_examples)]
+    fn multinomial_bow_spam_probability(&self, bow: &BagOfWords) -> Probability {
+        logistic(self.multinomial_bow_log_odds(bow))
+    }
+}